@@ -0,0 +1,15 @@
+use std::{env, error::Error};
+
+fn main() -> Result<(), Box<dyn Error>> {
+    // protoc is vendored, so building does not depend on a system installation
+    env::set_var("PROTOC", protoc_bin_vendored::protoc_bin_path()?);
+    let well_known_types = protoc_bin_vendored::include_path()?;
+    tonic_build::configure().compile_protos(
+        &["src/tonic/proto/quantixar.proto"],
+        &[
+            "src/tonic/proto",
+            well_known_types.to_str().expect("include path is utf-8"),
+        ],
+    )?;
+    Ok(())
+}
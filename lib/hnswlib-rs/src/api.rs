@@ -3,7 +3,7 @@
 
 
 use std::io::prelude::*;
-use std::path::PathBuf;
+use std::path::Path;
 
 
 use serde::{Serialize, de::DeserializeOwned};
@@ -31,6 +31,8 @@ pub trait AnnT {
     /// If these files already exist , they are not overwritten and a unique filename is generated by concatenating a random number to filename.
     /// The function returns the basename used for the dump
     fn file_dump(&self, filename: &String) -> anyhow::Result<String>;
+    /// same as file_dump, with the files written in directory dir instead of the current directory
+    fn file_dump_in(&self, dir: &Path, filename: &str) -> anyhow::Result<String>;
  }
 
 
@@ -60,32 +62,29 @@ impl <'b, T,D> AnnT for Hnsw<'b, T,D>  where T:Serialize+DeserializeOwned+Clone+
 
    ///
    fn file_dump(&self, filename: &String) -> anyhow::Result<String> {
+        self.file_dump_in(Path::new("."), filename)
+   }
+   ///
+   fn file_dump_in(&self, dir: &Path, filename: &str) -> anyhow::Result<String> {
         log::info!("in Hnsw::file_dump");
         //
-        let mut dir = PathBuf::new();
-        dir.push(".");
         // do not overwrite if mmap is active
         let overwrite = !self.get_datamap_opt();
-        let mut dumpinit = DumpInit::new(dir, filename.clone(), overwrite);
+        let mut dumpinit = DumpInit::new(dir.to_path_buf(), filename.to_string(), overwrite);
         let dumpname = dumpinit.get_basename().clone();
         //
         let res = self.dump(DumpMode::Full, &mut dumpinit);
         //
         let outgraph = &mut dumpinit.graph_out;
         let outdata = &mut dumpinit.data_out;
-        outgraph.flush().unwrap();
-        outdata.flush().unwrap();
+        outgraph.flush()?;
+        outdata.flush()?;
         //
         drop(dumpinit.graph_out);
         drop(dumpinit.data_out);
         //
         log::info!("\n end of dump");
-        if res.is_ok() {
-            return Ok(dumpname);
-        }
-        else {
-            return Err(anyhow::anyhow!("unexpected error"));
-        }
+        res.map(|_| dumpname)
    }   // end of dump
 } // end of impl block AnnT for Hnsw<T,D>
 
@@ -380,6 +380,11 @@ pub struct PointIndexation<'b, T: Clone + Send + Sync> {
     pub(crate) nb_point: Arc<RwLock<usize>>,
     /// curent enter_point: an Arc RwLock on a possible Arc Point
     pub(crate) entry_point: Arc<RwLock<Option<Arc<Point<'b, T>>>>>,
+    /// tombstones. Deleted points stay in their layer slot so that PointId of other points do not move,
+    /// but they are unlinked from the graph and never returned by searches.
+    pub(crate) deleted: Arc<RwLock<HashSet<PointId>>>,
+    /// PointId of the live point stored for each origin id
+    pub(crate) origin_ids: Arc<RwLock<HashMap<DataId, PointId>>>,
 }
 
 // A point indexation may contain circular references. To deallocate these after a point indexation goes out of scope,
@@ -446,9 +451,31 @@ impl<'b, T: Clone + Send + Sync> PointIndexation<'b, T> {
             layer_g,
             nb_point: Arc::new(RwLock::new(0)),
             entry_point: Arc::new(RwLock::new(None)),
+            deleted: Arc::new(RwLock::new(HashSet::new())),
+            origin_ids: Arc::new(RwLock::new(HashMap::new())),
         }
     } // end of new
 
+    /// Recover tombstones and origin ids of points reloaded from a dump.
+    /// Tombstones are not dumped, but a deleted point has no neighbours left, so every point
+    /// (except the entry point) without neighbours in any layer is a deleted one.
+    pub(crate) fn index_loaded_points(
+        points_by_layer: &[Layer<'b, T>],
+        entry_point: &Arc<Point<'b, T>>,
+    ) -> (HashSet<PointId>, HashMap<DataId, PointId>) {
+        let mut deleted = HashSet::new();
+        let mut origin_ids = HashMap::new();
+        for point in points_by_layer.iter().flatten() {
+            let isolated = point.neighbours.read().iter().all(|layer| layer.is_empty());
+            if isolated && point.p_id != entry_point.p_id {
+                deleted.insert(point.p_id);
+            } else {
+                origin_ids.insert(point.origin_id, point.p_id);
+            }
+        }
+        (deleted, origin_ids)
+    }
+
     /// returns the maximum level of layer observed
     pub fn get_max_level_observed(&self) -> u8 {
         let opt = self.entry_point.read();
@@ -492,6 +519,7 @@ impl<'b, T: Clone + Send + Sync> PointIndexation<'b, T> {
             new_point = Arc::new(point);
             log::trace!("definitive pushing of point {:?}", p_id);
             points_by_layer_ref[p_id.0 as usize].push(Arc::clone(&new_point));
+            self.origin_ids.write().insert(origin_id, p_id);
         } // close write lock on points_by_layer
           //
         let nb_point;
@@ -599,6 +627,65 @@ impl<'b, T: Clone + Send + Sync> PointIndexation<'b, T> {
     pub fn get_layer_iterator<'a>(&'a self, layer: usize) -> IterPointLayer<'a, 'b, T> {
         IterPointLayer::new(&self, layer)
     } // end of get_layer_iterator
+
+    /// returns the PointId of the live point stored with a given origin id
+    pub fn get_origin_point_id(&self, origin_id: DataId) -> Option<PointId> {
+        self.origin_ids.read().get(&origin_id).copied()
+    }
+
+    /// returns true if the point was deleted from the graph
+    pub fn is_deleted(&self, p_id: &PointId) -> bool {
+        self.deleted.read().contains(p_id)
+    }
+
+    /// returns the number of deleted points still occupying a slot in layers
+    pub fn get_nb_deleted(&self) -> usize {
+        self.deleted.read().len()
+    }
+
+    /// set a tombstone on a point already unlinked from the graph
+    fn mark_deleted(&self, point: &Point<'b, T>) {
+        self.deleted.write().insert(point.p_id);
+        let mut origin_ids = self.origin_ids.write();
+        if origin_ids.get(&point.origin_id) == Some(&point.p_id) {
+            origin_ids.remove(&point.origin_id);
+        }
+    }
+
+    /// store a point in the slot given by its PointId, in place of the previous one
+    fn replace_point(&self, new_point: &Arc<Point<'b, T>>) {
+        let p_id = new_point.p_id;
+        self.points_by_layer.write()[p_id.0 as usize][p_id.1 as usize] = Arc::clone(new_point);
+        self.origin_ids.write().insert(new_point.origin_id, p_id);
+    }
+
+    /// if the entry point is among removed points, move it to a live point of the highest non empty layer.
+    /// The entry point is left empty if there is no live point.
+    fn repair_entry_point(&self, removed: &HashSet<PointId>) {
+        let entry_point_removed = match self.entry_point.read().as_ref() {
+            Some(entry_point) => removed.contains(&entry_point.p_id),
+            None => false,
+        };
+        if !entry_point_removed {
+            return;
+        }
+        // do not hold the entry point lock while reading layers, iterators lock them the other way round
+        let new_entry_point = {
+            let deleted = self.deleted.read();
+            let points_by_layer = self.points_by_layer.read();
+            points_by_layer
+                .iter()
+                .rev()
+                .flatten()
+                .find(|point| !removed.contains(&point.p_id) && !deleted.contains(&point.p_id))
+                .cloned()
+        };
+        log::debug!(
+            "moving entry point to {:?}",
+            new_entry_point.as_ref().map(|point| point.p_id)
+        );
+        *self.entry_point.write() = new_entry_point;
+    } // end of repair_entry_point
 } // end of impl PointIndexation
 
 //============================================================================================
@@ -728,6 +815,9 @@ pub struct Hnsw<'b, T: Clone + Send + Sync + 'b, D: Distance<T>> {
     pub(crate) extend_candidates: bool,
     /// defuault to false
     pub(crate) keep_pruned: bool,
+    /// keep pruned vectors only when repairing neighbourhoods after deletions. default to false
+    /// Can be set to true with method :set_keeping_pruned_on_repair
+    pub(crate) keep_pruned_on_repair: bool,
     /// max layer , recall rust is in 0..maxlevel right bound excluded
     pub(crate) max_layer: usize,
     /// The global table containing points
@@ -778,6 +868,7 @@ impl<'b, T: Clone + Send + Sync, D: Distance<T> + Send + Sync> Hnsw<'b, T, D> {
             ef_construction,
             extend_candidates,
             keep_pruned,
+            keep_pruned_on_repair: false,
             max_layer: adjusted_max_layer,
             layer_indexed_points,
             data_dimension: 0,
@@ -827,6 +918,15 @@ impl<'b, T: Clone + Send + Sync, D: Distance<T> + Send + Sync> Hnsw<'b, T, D> {
         self.keep_pruned = flag;
     }
 
+    /// set the flag asking to keep pruned vectors when neighbourhoods are repaired after deletions,
+    /// whatever the flag of [`Self::set_keeping_pruned`].
+    /// Repair only selects among the neighbours of the deleted points, the pruning heuristic can then
+    /// leave points without the links that kept them reachable, mostly in low dimension data
+    /// where the graph is close to a chain. By default it is false.
+    pub fn set_keeping_pruned_on_repair(&mut self, flag: bool) {
+        self.keep_pruned_on_repair = flag;
+    }
+
     /// retrieves the distance used in Hnsw construction
     pub fn get_distance(&self) -> &D {
         &self.dist_f
@@ -1031,12 +1131,18 @@ impl<'b, T: Clone + Send + Sync, D: Distance<T> + Send + Sync> Hnsw<'b, T, D> {
     pub fn insert_slice(&self, data_with_id: (&[T], usize)) {
         //
         let (data, origin_id) = data_with_id;
-        let keep_pruned = self.keep_pruned;
         // insert in indexation and get point_id adn generate a new entry_point if necessary
         let (new_point, point_rank) = self
             .layer_indexed_points
             .generate_new_point(data, origin_id);
         trace!("\n\n Hnsw insert generated new point {:?} ", new_point.p_id);
+        self.link_point(new_point, point_rank);
+    } // end of insert
+
+    // connect a point already stored in its layer slot to the graph
+    fn link_point(&self, new_point: Arc<Point<'b, T>>, point_rank: usize) {
+        let data = new_point.data.get_v();
+        let keep_pruned = self.keep_pruned;
         // now real work begins
         // allocate a binary heap
         let level = new_point.p_id.0;
@@ -1166,7 +1272,204 @@ impl<'b, T: Clone + Send + Sync, D: Distance<T> + Send + Sync> Hnsw<'b, T, D> {
         self.layer_indexed_points.check_entry_point(&new_point);
         //
         trace!("Hnsw exiting insert new point {:?} ", new_point.p_id);
-    } // end of insert
+    } // end of link_point
+
+    /// Delete a point given its origin id. Returns false if there is no such point.
+    /// See [`Self::delete_points`].
+    pub fn delete(&self, origin_id: DataId) -> bool {
+        self.delete_points(&[origin_id]) == 1
+    }
+
+    /// Delete points given their origin ids and returns the number of deleted points.
+    ///
+    /// A deleted point is unlinked from the graph: each point that had it as a neighbour gets its neighbourhood
+    /// rebuilt from its remaining neighbours and the neighbours of the deleted point, so the graph stays navigable.
+    /// The point itself is kept as a tombstone in its layer slot so that the PointId of other points do not move.
+    /// As each deletion scans the whole graph, deleting points in batches is much cheaper than one by one.
+    pub fn delete_points(&self, origin_ids: &[DataId]) -> usize {
+        let removed: Vec<Arc<Point<'b, T>>> = {
+            let origin_index = self.layer_indexed_points.origin_ids.read();
+            let points_by_layer = self.layer_indexed_points.points_by_layer.read();
+            origin_ids
+                .iter()
+                .filter_map(|origin_id| origin_index.get(origin_id))
+                .map(|p_id| Arc::clone(&points_by_layer[p_id.0 as usize][p_id.1 as usize]))
+                .collect()
+        };
+        if removed.is_empty() {
+            return 0;
+        }
+        let removed_ids = self.unlink_points(&removed);
+        for point in &removed {
+            self.layer_indexed_points.mark_deleted(point);
+        }
+        self.layer_indexed_points.repair_entry_point(&removed_ids);
+        debug!("Hnsw deleted {:?} points", removed_ids.len());
+        removed_ids.len()
+    } // end of delete_points
+
+    /// Replace the vector of the point with a given origin id, or insert it if there is no such point.
+    ///
+    /// The new vector takes the layer slot of the previous one: the old point is unlinked from the graph
+    /// as a deleted one would be, then the new one is connected as a fresh insertion at the same level.
+    /// So the old vector is no more reachable by searches and no tombstone is left.
+    pub fn replace_slice(&self, data_with_id: (&[T], usize)) {
+        let (data, origin_id) = data_with_id;
+        let Some(p_id) = self.layer_indexed_points.get_origin_point_id(origin_id) else {
+            self.insert_slice(data_with_id);
+            return;
+        };
+        let old_point = self
+            .layer_indexed_points
+            .get_point(&p_id)
+            .expect("origin ids refer to stored points");
+        let removed_ids = self.unlink_points(&[old_point]);
+        self.layer_indexed_points.repair_entry_point(&removed_ids);
+        //
+        let new_point = Arc::new(Point::new(data.to_vec(), origin_id, p_id));
+        self.layer_indexed_points.replace_point(&new_point);
+        let point_rank = self.layer_indexed_points.get_nb_point();
+        self.link_point(Arc::clone(&new_point), point_rank);
+        self.reverse_link_all_layers(&new_point);
+        trace!("Hnsw replaced point {:?} ", p_id);
+    } // end of replace_slice
+
+    // The reverse update of insertion links back a new point only in its own layer.
+    // A replaced point keeps the slot of a point that had in-links in all its layers,
+    // so it is linked back in each of them to stay reachable from layer 0.
+    // Its nearest neighbour always keeps the link, even if the point is farther than all its
+    // other neighbours, so that a point moved far from the others is not left without in-links.
+    fn reverse_link_all_layers(&self, point: &Arc<Point<'b, T>>) {
+        let level = point.p_id.0 as usize;
+        let neighbours = point.neighbours.read().clone();
+        for (l, neighbours_l) in neighbours.iter().enumerate().take(level + 1) {
+            let threshold = if l > 0 {
+                self.max_nb_connection
+            } else {
+                2 * self.max_nb_connection
+            };
+            let nearest = neighbours_l
+                .iter()
+                .filter(|q| q.point_ref.p_id != point.p_id)
+                .min_by(|a, b| a.dist_to_ref.total_cmp(&b.dist_to_ref))
+                .map(|q| q.point_ref.p_id);
+            for q in neighbours_l {
+                if q.point_ref.p_id == point.p_id {
+                    continue;
+                }
+                let mut q_neighbours = q.point_ref.neighbours.write();
+                if q_neighbours[l]
+                    .iter()
+                    .any(|old| old.point_ref.p_id == point.p_id)
+                {
+                    continue;
+                }
+                q_neighbours[l].push(Arc::new(PointWithOrder::new(point, q.dist_to_ref)));
+                q_neighbours[l].sort_unstable();
+                if q_neighbours[l].len() > threshold {
+                    if Some(q.point_ref.p_id) == nearest {
+                        let farthest = q_neighbours[l]
+                            .iter()
+                            .rposition(|n| n.point_ref.p_id != point.p_id)
+                            .expect("the neighbourhood is full");
+                        q_neighbours[l].remove(farthest);
+                    } else {
+                        q_neighbours[l].pop();
+                    }
+                }
+            }
+        }
+    } // end of reverse_link_all_layers
+
+    /// Remove points from the graph and repair the neighbourhoods that referenced them.
+    /// Returns the PointId of removed points.
+    fn unlink_points(&self, removed: &[Arc<Point<'b, T>>]) -> HashSet<PointId> {
+        let removed_ids: HashSet<PointId> = removed.iter().map(|point| point.p_id).collect();
+        // keep neighbourhoods of removed points as repair candidates, and clear them
+        // so that removed points do not reference the graph anymore
+        let removed_neighbours: HashMap<PointId, Vec<Vec<Arc<PointWithOrder<'b, T>>>>> = removed
+            .iter()
+            .map(|point| {
+                let mut neighbours = point.neighbours.write();
+                let snapshot = neighbours.clone();
+                neighbours.iter_mut().for_each(|layer| layer.clear());
+                (point.p_id, snapshot)
+            })
+            .collect();
+        //
+        let points_by_layer = self.layer_indexed_points.points_by_layer.read();
+        points_by_layer.par_iter().for_each(|layer_points| {
+            layer_points
+                .par_iter()
+                .filter(|point| !removed_ids.contains(&point.p_id))
+                .for_each(|point| {
+                    self.repair_neighbourhood(point, &removed_ids, &removed_neighbours)
+                });
+        });
+        removed_ids
+    } // end of unlink_points
+
+    // rebuild neighbourhood of point in each layer where it references a removed point
+    fn repair_neighbourhood(
+        &self,
+        point: &Arc<Point<'b, T>>,
+        removed_ids: &HashSet<PointId>,
+        removed_neighbours: &HashMap<PointId, Vec<Vec<Arc<PointWithOrder<'b, T>>>>>,
+    ) {
+        let nb_layer = point.neighbours.read().len();
+        for l in 0..nb_layer {
+            let current = point.neighbours.read()[l].clone();
+            if !current
+                .iter()
+                .any(|n| removed_ids.contains(&n.point_ref.p_id))
+            {
+                continue;
+            }
+            // candidates are the remaining neighbours and the neighbours of removed neighbours
+            let mut candidates_set = HashMap::<PointId, Arc<Point<T>>>::new();
+            for n in &current {
+                match removed_neighbours.get(&n.point_ref.p_id) {
+                    Some(neighbours) => {
+                        for q in neighbours.get(l).into_iter().flatten() {
+                            candidates_set.insert(q.point_ref.p_id, Arc::clone(&q.point_ref));
+                        }
+                    }
+                    None => {
+                        candidates_set.insert(n.point_ref.p_id, Arc::clone(&n.point_ref));
+                    }
+                }
+            }
+            candidates_set.retain(|p_id, _| {
+                *p_id != point.p_id
+                    && !removed_ids.contains(p_id)
+                    && !self.layer_indexed_points.is_deleted(p_id)
+            });
+            let data = point.data.get_v();
+            let mut candidates =
+                BinaryHeap::<Arc<PointWithOrder<T>>>::with_capacity(candidates_set.len());
+            for candidate in candidates_set.values() {
+                let dist = self.dist_f.eval(data, candidate.data.get_v());
+                candidates.push(Arc::new(PointWithOrder::new(candidate, -dist)));
+            }
+            let nb_conn = if l == 0 {
+                2 * self.max_nb_connection
+            } else {
+                self.max_nb_connection
+            };
+            let mut neighbours = Vec::<Arc<PointWithOrder<T>>>::with_capacity(nb_conn);
+            self.select_neighbours(
+                data,
+                &mut candidates,
+                nb_conn,
+                false,
+                l as u8,
+                self.keep_pruned || self.keep_pruned_on_repair,
+                &mut neighbours,
+            );
+            neighbours.sort_unstable();
+            point.neighbours.write()[l] = neighbours;
+        }
+    } // end of repair_neighbourhood
 
     /// Insert in parallel a slice of Vec\<T\> each associated to its id.
     /// It uses Rayon for threading so the number of insertions asked for must be large enough to be efficient.
@@ -1487,10 +1790,12 @@ impl<'b, T: Clone + Send + Sync, D: Distance<T> + Send + Sync> Hnsw<'b, T, D> {
         // go from heap of points with negative dist to a sorted vec of increasing points with > 0 distances.
         let neighbours = neighbours_heap.into_sorted_vec();
         // get the min of K and ef points into a vector.
-        //
-        let last = knbn.min(ef).min(neighbours.len());
-        let knn_neighbours: Vec<Neighbour> = neighbours[0..last]
+        // deleted points are unlinked, but a search running along a deletion can still meet them
+        let deleted = self.layer_indexed_points.deleted.read();
+        let knn_neighbours: Vec<Neighbour> = neighbours
             .iter()
+            .filter(|p| !deleted.contains(&p.point_ref.p_id))
+            .take(knbn.min(ef))
             .map(|p| {
                 Neighbour::new(
                     p.as_ref().point_ref.origin_id,
@@ -1804,4 +2109,115 @@ mod tests {
         //
         assert_eq!(nb_dumped, nbpl);
     } // end of test_iter_layerpoint
+
+    fn random_data(nbcolumn: usize, nbrow: usize) -> Vec<Vec<f32>> {
+        let mut rng = rand::thread_rng();
+        let unif = Uniform::<f32>::new(0., 1.);
+        (0..nbcolumn)
+            .map(|_| (0..nbrow).map(|_| rng.sample(unif)).collect())
+            .collect()
+    }
+
+    #[test]
+    fn test_delete_points() {
+        let nbcolumn = 2000;
+        let data = random_data(nbcolumn, 10);
+        let hns = Hnsw::<f32, dist::DistL2>::new(10, nbcolumn, 16, 50, dist::DistL2 {});
+        for i in 0..data.len() {
+            hns.insert((&data[i], i));
+        }
+        // delete every third point, including the entry point
+        let entry_origin_id = hns
+            .get_point_indexation()
+            .entry_point
+            .read()
+            .as_ref()
+            .unwrap()
+            .get_origin_id();
+        let mut deleted: Vec<DataId> = (0..nbcolumn).step_by(3).collect();
+        if entry_origin_id % 3 != 0 {
+            deleted.push(entry_origin_id);
+        }
+        assert_eq!(hns.delete_points(&deleted), deleted.len());
+        assert!(!hns.delete(deleted[0]));
+        let indexation = hns.get_point_indexation();
+        assert_eq!(indexation.get_nb_deleted(), deleted.len());
+        assert_eq!(indexation.get_nb_point(), nbcolumn);
+        let deleted_set: HashSet<DataId> = deleted.iter().copied().collect();
+        let entry_point = indexation.entry_point.read().clone().unwrap();
+        assert!(!deleted_set.contains(&entry_point.get_origin_id()));
+        // no live point references a deleted one
+        for point in indexation {
+            let p_id = point.get_point_id();
+            if indexation.is_deleted(&p_id) {
+                assert!(point.get_neighborhood_id().iter().all(|l| l.is_empty()));
+                continue;
+            }
+            for layer in point.get_neighborhood_id() {
+                assert!(layer.iter().all(|n| !deleted_set.contains(&n.d_id)));
+            }
+        }
+        // deleted points are never returned, live ones are still found
+        let mut nb_found = 0;
+        let mut nb_live = 0;
+        for i in 0..nbcolumn {
+            let neighbours = hns.search(&data[i], 10, 50);
+            assert!(neighbours.iter().all(|n| !deleted_set.contains(&n.d_id)));
+            if !deleted_set.contains(&i) {
+                nb_live += 1;
+                if neighbours.first().map(|n| n.d_id) == Some(i) {
+                    nb_found += 1;
+                }
+            }
+        }
+        let recall = nb_found as f32 / nb_live as f32;
+        println!("test_delete_points recall after deletion {:?}", recall);
+        assert!(recall > 0.95);
+    } // end of test_delete_points
+
+    #[test]
+    fn test_replace_point() {
+        let nbcolumn = 1000;
+        let data = random_data(nbcolumn, 10);
+        let hns = Hnsw::<f32, dist::DistL2>::new(10, nbcolumn, 16, 50, dist::DistL2 {});
+        for i in 0..data.len() {
+            hns.insert((&data[i], i));
+        }
+        // a far outlier, and a point moved next to another one
+        let near_vector: Vec<f32> = data[500].iter().map(|x| x + 0.001).collect();
+        for (origin_id, new_vector) in [(7, vec![5.0f32; 10]), (8, near_vector.clone())] {
+            let p_id = hns
+                .get_point_indexation()
+                .get_origin_point_id(origin_id)
+                .unwrap();
+            hns.replace_slice((&new_vector, origin_id));
+            let indexation = hns.get_point_indexation();
+            assert_eq!(indexation.get_origin_point_id(origin_id), Some(p_id));
+            assert_eq!(indexation.get_point_data(&p_id), Some(new_vector.clone()));
+            assert_eq!(indexation.get_nb_point(), nbcolumn);
+            assert_eq!(indexation.get_nb_deleted(), 0);
+            // the old vector is not stored anymore, the new one is reachable
+            let new_distance = dist::DistL2.eval(&data[origin_id], &new_vector);
+            let neighbours = hns.search(&data[origin_id], 10, 50);
+            for n in neighbours.iter().filter(|n| n.d_id == origin_id) {
+                assert_eq!(n.distance, new_distance);
+            }
+            // the new vector is linked back in layer 0, so searches can reach it
+            let linked_back = indexation.into_iter().any(|point| {
+                point.get_point_id() != p_id
+                    && point.get_neighborhood_id()[0]
+                        .iter()
+                        .any(|n| n.d_id == origin_id)
+            });
+            assert!(linked_back);
+        }
+        // all points are at about the same distance of the far outlier, so a search for it
+        // may stop at another point, the search for the other one is exact
+        let neighbours = hns.search(&near_vector, 1, 50);
+        assert_eq!(neighbours[0].d_id, 8);
+        assert_eq!(neighbours[0].distance, 0.);
+        // replacing an unknown origin id inserts it
+        hns.replace_slice((&data[0], nbcolumn));
+        assert_eq!(hns.get_nb_point(), nbcolumn + 1);
+    } // end of test_replace_point
 } // end of module test
@@ -409,7 +409,7 @@ impl HnswIo {
         let mut graph_in = BufReader::new(graphfile);
         let data_in = BufReader::new(datafile);
         // we need to call load_description first to get distance name
-        let hnsw_description = load_description(&mut graph_in).unwrap();
+        let hnsw_description = load_description(&mut graph_in)?;
         //
         return Ok(LoadInit {
             descr: hnsw_description,
@@ -446,19 +446,20 @@ impl HnswIo {
         let mut it_slice = [0u8; std::mem::size_of::<u32>()];
         data_in.read_exact(&mut it_slice)?;
         let magic = u32::from_ne_bytes(it_slice);
-        assert_eq!(
-            magic, MAGICDATAP,
-            "magic not equal to MAGICDATAP in load_point"
-        );
+        if magic != MAGICDATAP {
+            return Err(anyhow!("magic not equal to MAGICDATAP in data file"));
+        }
         //
         let mut it_slice = [0u8; std::mem::size_of::<usize>()];
         data_in.read_exact(&mut it_slice)?;
         let dimension = usize::from_ne_bytes(it_slice);
-        assert_eq!(
-            dimension, description.dimension,
-            "data dimension incoherent {:?} {:?} ",
-            dimension, description.dimension
-        );
+        if dimension != description.dimension {
+            return Err(anyhow!(
+                "data dimension incoherent {:?} {:?} ",
+                dimension,
+                description.dimension
+            ));
+        }
         //
         let _mode = description.dumpmode;
         let distname = description.distname.clone();
@@ -496,7 +497,8 @@ impl HnswIo {
             }
         }
         // reloader can use datamap
-        let layer_point_indexation = self.load_point_indexation(graph_in, &description, data_in)?;
+        let layer_point_indexation =
+            self.load_point_indexation(graph_in, &description, data_in, self.datamap.as_ref())?;
         let data_dim = layer_point_indexation.get_data_dimension();
 
         let hnsw: Hnsw<T, D> = Hnsw {
@@ -504,6 +506,7 @@ impl HnswIo {
             ef_construction: description.ef,
             extend_candidates: true,
             keep_pruned: false,
+            keep_pruned_on_repair: false,
             max_layer: description.nb_layer as usize,
             layer_indexed_points: layer_point_indexation,
             data_dimension: data_dim,
@@ -527,11 +530,14 @@ impl HnswIo {
     /// This function makes reload of a Hnsw dump with a given Dist.
     /// It is dedicated to distance of type  [crate::dist::DistPtr] that cannot implement Default.
     /// **It is the user responsability to reload with the same function as used in the dump**
-    pub fn load_hnsw_with_dist<'b, 'a, T, D>(&'a self, f: D) -> anyhow::Result<Hnsw<'b, T, D>>
+    ///
+    /// Data points are always loaded in memory (mmap options are ignored), so the reloaded Hnsw
+    /// does not borrow the Hnswio and can outlive it.
+    /// A corrupted or truncated dump is reported as an error.
+    pub fn load_hnsw_with_dist<'b, T, D>(&self, f: D) -> anyhow::Result<Hnsw<'b, T, D>>
     where
         T: 'static + Serialize + DeserializeOwned + Clone + Sized + Send + Sync + std::fmt::Debug,
         D: Distance<T> + Send + Sync,
-        'a: 'b,
     {
         //
         log::debug!("\n\n HnswIo::load_hnsw_with_dist");
@@ -549,19 +555,20 @@ impl HnswIo {
         let mut it_slice = [0u8; std::mem::size_of::<u32>()];
         data_in.read_exact(&mut it_slice)?;
         let magic = u32::from_ne_bytes(it_slice);
-        assert_eq!(
-            magic, MAGICDATAP,
-            "magic not equal to MAGICDATAP in load_point"
-        );
+        if magic != MAGICDATAP {
+            return Err(anyhow!("magic not equal to MAGICDATAP in data file"));
+        }
         //
         let mut it_slice = [0u8; std::mem::size_of::<usize>()];
         data_in.read_exact(&mut it_slice)?;
         let dimension = usize::from_ne_bytes(it_slice);
-        assert_eq!(
-            dimension, description.dimension,
-            "data dimension incoherent {:?} {:?} ",
-            dimension, description.dimension
-        );
+        if dimension != description.dimension {
+            return Err(anyhow!(
+                "data dimension incoherent {:?} {:?} ",
+                dimension,
+                description.dimension
+            ));
+        }
         //
         let _mode = description.dumpmode;
         let distname = description.distname.clone();
@@ -590,7 +597,8 @@ impl HnswIo {
         log::debug!("T type name in dump = {:?}", t_type);
         //
         //
-        let layer_point_indexation = self.load_point_indexation(graph_in, &description, data_in)?;
+        let layer_point_indexation =
+            self.load_point_indexation(graph_in, &description, data_in, None)?;
         let data_dim = layer_point_indexation.get_data_dimension();
         //
         let hnsw: Hnsw<T, D> = Hnsw {
@@ -598,6 +606,7 @@ impl HnswIo {
             ef_construction: description.ef,
             extend_candidates: true,
             keep_pruned: false,
+            keep_pruned_on_repair: false,
             max_layer: description.nb_layer as usize,
             layer_indexed_points: layer_point_indexation,
             data_dimension: data_dim,
@@ -612,15 +621,17 @@ impl HnswIo {
         Ok(hnsw)
     } // end of load_hnsw_with_dist
 
-    fn load_point_indexation<'b, 'a, T>(
-        &'a self,
+    // Points are mapped from datamap if given and mmap is asked for in options, otherwise loaded in
+    // memory.
+    fn load_point_indexation<'b, T>(
+        &self,
         graph_in: &mut dyn Read,
         descr: &Description,
         data_in: &mut dyn Read,
+        datamap: Option<&'b DataMap>,
     ) -> anyhow::Result<PointIndexation<'b, T>>
     where
         T: 'static + Serialize + DeserializeOwned + Clone + Sized + Send + Sync + std::fmt::Debug,
-        'a: 'b,
     {
         //
         log::debug!(" in load_point_indexation");
@@ -634,7 +645,7 @@ impl HnswIo {
                 descr.t_name,
                 std::any::type_name::<T>()
             );
-            panic!("incohrent size of T in description");
+            return Err(anyhow!("incoherent type T in description"));
         }
         //
         let mut points_by_layer: Vec<Vec<Arc<Point<T>>>> =
@@ -652,6 +663,7 @@ impl HnswIo {
         let mut nb_points_loaded: usize = 0;
         let mut nb_still_to_load = descr.nb_point as i64;
         let (use_mmap, max_nbpoint_in_memory) = self.options.use_mmap();
+        let use_mmap = use_mmap && datamap.is_some();
         //
         for l in 0..nb_layer as usize {
             // read and check magic
@@ -666,6 +678,9 @@ impl HnswIo {
             graph_in.read_exact(&mut it_slice)?;
             let nbpoints = usize::from_ne_bytes(it_slice);
             log::debug!(" layer {:?} , nb points {:?}", l, nbpoints);
+            if nbpoints as i64 > nb_still_to_load {
+                return Err(anyhow!("more points in layers than in description"));
+            }
             let mut vlayer: Vec<Arc<Point<T>>> = Vec::with_capacity(nbpoints);
             // load graph and data part of point. Points are dumped in the same order.
             for r in 0..nbpoints {
@@ -691,7 +706,8 @@ impl HnswIo {
                         }
                     }
                 };
-                let load_point_res = self.load_point(graph_in, descr, data_in, point_use_mmap);
+                let load_point_res =
+                    self.load_point(graph_in, descr, data_in, datamap.filter(|_| point_use_mmap));
                 match load_point_res {
                     Err(other) => {
                         log::error!("in load_point_indexation, loading of point {} failed", r);
@@ -703,7 +719,9 @@ impl HnswIo {
                 let point = load_point_res.0;
                 let p_id = point.get_point_id();
                 // some checks
-                assert_eq!(l, p_id.0 as usize);
+                if l != p_id.0 as usize {
+                    return Err(anyhow!("point {:?} stored in layer {}", p_id, l));
+                }
                 if r != p_id.1 as usize {
                     log::debug!(
                         "\n\n origin= {:?},  p_id = {:?}",
@@ -712,13 +730,14 @@ impl HnswIo {
                     );
                     log::debug!("storing at l {:?}, r {:?}", l, r);
                 }
-                assert_eq!(r, p_id.1 as usize);
+                if r != p_id.1 as usize {
+                    return Err(anyhow!("point {:?} stored at rank {}", p_id, r));
+                }
                 // store neoghbour info of this point
                 neighbourhood_map.insert(p_id, load_point_res.1);
                 vlayer.push(point);
                 nb_points_loaded += 1;
                 nb_still_to_load -= 1;
-                assert!(nb_still_to_load >= 0);
             }
             points_by_layer.push(vlayer);
         }
@@ -729,7 +748,10 @@ impl HnswIo {
             let point = &points_by_layer[p_id.0 as usize][p_id.1 as usize];
             for l in 0..neighbours.len() {
                 for n in &neighbours[l] {
-                    let n_point = &points_by_layer[n.p_id.0 as usize][n.p_id.1 as usize];
+                    let n_point = points_by_layer
+                        .get(n.p_id.0 as usize)
+                        .and_then(|layer| layer.get(n.p_id.1 as usize))
+                        .ok_or_else(|| anyhow!("unknown neighbour {:?}", n.p_id))?;
                     // now n_point is the Arc<Point> corresponding to neighbour n of point,
                     // construct a corresponding PointWithOrder
                     let n_pwo = PointWithOrder::<T>::new(n_point, n.distance);
@@ -769,13 +791,19 @@ impl HnswIo {
             layer,
             rank_in_l
         );
-        let entry_point = Arc::clone(&points_by_layer[layer as usize][rank_in_l as usize]);
+        let entry_point = points_by_layer
+            .get(layer as usize)
+            .and_then(|points| points.get(rank_in_l as usize))
+            .ok_or_else(|| anyhow!("unknown entry point ({}, {})", layer, rank_in_l))?;
+        let entry_point = Arc::clone(entry_point);
         log::info!(
             " loaded entry point, origin_id {:} p_id {:?}",
             entry_point.get_origin_id(),
             entry_point.get_point_id()
         );
         //
+        let (deleted, origin_ids) =
+            PointIndexation::index_loaded_points(&points_by_layer, &entry_point);
         let point_indexation = PointIndexation {
             max_nb_connection: descr.max_nb_connection as usize,
             max_layer: NB_LAYER_MAX as usize,
@@ -785,6 +813,8 @@ impl HnswIo {
                                                                 * the whole thing is to be able
                                                                 * to increment graph ? */
             entry_point: Arc::new(RwLock::new(Some(entry_point))),
+            deleted: Arc::new(RwLock::new(deleted)),
+            origin_ids: Arc::new(RwLock::new(origin_ids)),
         };
         //
         log::debug!("\n exiting load_pointIndexation");
@@ -797,16 +827,17 @@ impl HnswIo {
     //  The graph part is loaded from graph_in file
     // the data vector itself is loaded from data_in
     //
-    fn load_point<'b, 'a, T>(
-        &'a self,
+    //  The data vector is mapped from datamap if given.
+    //
+    fn load_point<'b, T>(
+        &self,
         graph_in: &mut dyn Read,
         descr: &Description,
         data_in: &mut dyn Read,
-        point_use_mmap: bool,
+        datamap: Option<&'b DataMap>,
     ) -> anyhow::Result<(Arc<Point<'b, T>>, Vec<Vec<Neighbour>>)>
     where
         T: 'static + DeserializeOwned + Clone + Sized + Send + Sync + std::fmt::Debug,
-        'a: 'b,
     {
         //
         //    log::debug!(" point load {:?} {:?}  ", p_id, origin_id);
@@ -818,20 +849,20 @@ impl HnswIo {
         }
         let (origin_id, p_id, neighborhood) = load_res.unwrap();
         //
-        let point = match point_use_mmap {
-            false => {
+        let point = match datamap {
+            None => {
                 let v = load_point_data::<T>(origin_id, data_in, &descr);
                 if v.is_err() {
                     log::error!("loading point {:?}", origin_id);
-                    std::process::exit(1);
                 }
-                Point::<T>::new(v.unwrap(), origin_id as usize, p_id)
+                Point::<T>::new(v?, origin_id as usize, p_id)
             }
-            true => {
-                skip_point_data::<T>(origin_id, data_in, descr).unwrap(); // keep cohrence between data file and graph file!
+            Some(datamap) => {
+                skip_point_data::<T>(origin_id, data_in, descr)?; // keep cohrence between data file and graph file!
                 log::debug!("constructing point from datamap, dataid : {:?}", origin_id);
-                let s: Option<&'b [T]> = self.datamap.as_ref().unwrap().get_data::<T>(&origin_id);
-                Point::<T>::new_from_mmap(s.unwrap(), origin_id as usize, p_id)
+                let s: Option<&'b [T]> = datamap.get_data::<T>(&origin_id);
+                let s = s.ok_or_else(|| anyhow!("no mapped data for point {}", origin_id))?;
+                Point::<T>::new_from_mmap(s, origin_id as usize, p_id)
             }
         };
         self.nb_point_loaded.fetch_add(1, Ordering::Relaxed);
@@ -881,40 +912,40 @@ impl Description {
     /// . the name of distance used. (nb byes as a usize then list of bytes)
     fn dump<W: Write>(&self, argmode: DumpMode, out: &mut io::BufWriter<W>) -> anyhow::Result<i32> {
         log::info!("in dump of description");
-        out.write(&MAGICDESCR_3.to_ne_bytes()).unwrap();
+        out.write_all(&MAGICDESCR_3.to_ne_bytes())?;
         let mode: u8 = match argmode {
             DumpMode::Full => 1,
             _ => 0,
         };
         // CAVEAT should check mode == self.mode
-        out.write(&mode.to_ne_bytes()).unwrap();
+        out.write_all(&mode.to_ne_bytes())?;
         // dump of max_nb_connection as u8!!
-        out.write(&self.max_nb_connection.to_ne_bytes()).unwrap();
-        out.write(&self.nb_layer.to_ne_bytes()).unwrap();
+        out.write_all(&self.max_nb_connection.to_ne_bytes())?;
+        out.write_all(&self.nb_layer.to_ne_bytes())?;
         if self.nb_layer != NB_LAYER_MAX {
             println!("dump of Description, nb_layer != NB_MAX_LAYER");
             return Err(anyhow!("dump of Description, nb_layer != NB_MAX_LAYER"));
         }
         //
         log::info!("dumping ef {:?}", self.ef);
-        out.write(&self.ef.to_ne_bytes()).unwrap();
+        out.write_all(&self.ef.to_ne_bytes())?;
         //
         log::info!("dumping nb point {:?}", self.nb_point);
-        out.write(&self.nb_point.to_ne_bytes()).unwrap();
+        out.write_all(&self.nb_point.to_ne_bytes())?;
         //
         log::info!("dumping dimension of data {:?}", self.dimension);
-        out.write(&self.dimension.to_ne_bytes()).unwrap();
+        out.write_all(&self.dimension.to_ne_bytes())?;
 
         // dump of distance name
         let namelen: usize = self.distname.len();
         log::info!("distance name {:?} ", self.distname);
-        out.write(&namelen.to_ne_bytes()).unwrap();
-        out.write(self.distname.as_bytes()).unwrap();
+        out.write_all(&namelen.to_ne_bytes())?;
+        out.write_all(self.distname.as_bytes())?;
         // dump of T value typename
         let namelen: usize = self.t_name.len();
         log::info!("T name {:?} ", self.t_name);
-        out.write(&namelen.to_ne_bytes()).unwrap();
-        out.write(self.t_name.as_bytes()).unwrap();
+        out.write_all(&namelen.to_ne_bytes())?;
+        out.write_all(self.t_name.as_bytes())?;
         //
         return Ok(1);
     } // end fo dump
@@ -1004,7 +1035,7 @@ pub fn load_description(io_in: &mut dyn Read) -> anyhow::Result<Description> {
     let mut distv = Vec::<u8>::new();
     distv.resize(len, 0);
     io_in.read_exact(distv.as_mut_slice())?;
-    let distname = String::from_utf8(distv).unwrap();
+    let distname = String::from_utf8(distv)?;
     log::debug!("distance name {:?} ", distname);
     descr.distname = distname;
     // reload of type name
@@ -1019,7 +1050,7 @@ pub fn load_description(io_in: &mut dyn Read) -> anyhow::Result<Description> {
     let mut tnamev = Vec::<u8>::new();
     tnamev.resize(len, 0);
     io_in.read_exact(tnamev.as_mut_slice())?;
-    let t_name = String::from_utf8(tnamev).unwrap();
+    let t_name = String::from_utf8(tnamev)?;
     log::debug!("T type name {:?} ", t_name);
     descr.t_name = t_name;
     log::debug!(" end of description load \n");
@@ -1053,15 +1084,13 @@ fn dump_point<'a, T: Serialize + Clone + Sized + Send + Sync, W: Write>(
     dataout: &mut io::BufWriter<W>,
 ) -> anyhow::Result<i32> {
     //
-    graphout.write(&MAGICPOINT.to_ne_bytes()).unwrap();
+    graphout.write_all(&MAGICPOINT.to_ne_bytes())?;
     // dump ext_id: usize , layer : u8 , rank in layer : i32
-    graphout
-        .write(&point.get_origin_id().to_ne_bytes())
-        .unwrap();
+    graphout.write_all(&point.get_origin_id().to_ne_bytes())?;
     let p_id = point.get_point_id();
     if mode == DumpMode::Full {
-        graphout.write(&p_id.0.to_ne_bytes()).unwrap();
-        graphout.write(&p_id.1.to_ne_bytes()).unwrap();
+        graphout.write_all(&p_id.0.to_ne_bytes())?;
+        graphout.write_all(&p_id.1.to_ne_bytes())?;
     }
     log::trace!(" point dump {:?} {:?}  ", p_id, point.get_origin_id());
     // then dump neighborhood info : nb neighbours : u32 , then list of origin_id, layer,
@@ -1074,23 +1103,23 @@ fn dump_point<'a, T: Serialize + Clone + Sized + Send + Sync, W: Write>(
         // Caution : we dump number of neighbours as a usize, even if it cannot be so large!
         let nbg_l: usize = neighbours_at_l.len();
         log::trace!("\t dumping nbng : {} at l {}", nbg_l, l);
-        graphout.write(&nbg_l.to_ne_bytes()).unwrap();
+        graphout.write_all(&nbg_l.to_ne_bytes())?;
         for n in neighbours_at_l {
             // dump d_id : uszie , distance : f32, layer : u8, rank in layer : i32
-            graphout.write(&n.d_id.to_ne_bytes()).unwrap();
+            graphout.write_all(&n.d_id.to_ne_bytes())?;
             if mode == DumpMode::Full {
-                graphout.write(&n.p_id.0.to_ne_bytes()).unwrap();
-                graphout.write(&n.p_id.1.to_ne_bytes()).unwrap();
+                graphout.write_all(&n.p_id.0.to_ne_bytes())?;
+                graphout.write_all(&n.p_id.1.to_ne_bytes())?;
             }
-            graphout.write(&n.distance.to_ne_bytes()).unwrap();
+            graphout.write_all(&n.distance.to_ne_bytes())?;
             //                log::debug!("        voisins  {:?}  {:?}  {:?}", n.p_id,  n.d_id ,
             // n.distance);
         }
     }
     // now we dump data vector!
-    dataout.write(&MAGICDATAP.to_ne_bytes()).unwrap();
+    dataout.write_all(&MAGICDATAP.to_ne_bytes())?;
     let origin_u64 = point.get_origin_id() as u64;
-    dataout.write(&origin_u64.to_ne_bytes()).unwrap();
+    dataout.write_all(&origin_u64.to_ne_bytes())?;
     //
     let serialized = unsafe {
         std::slice::from_raw_parts(
@@ -1100,8 +1129,8 @@ fn dump_point<'a, T: Serialize + Clone + Sized + Send + Sync, W: Write>(
     };
     log::trace!("serializing len {:?}", serialized.len());
     let len_64 = serialized.len() as u64;
-    dataout.write(&len_64.to_ne_bytes()).unwrap();
-    dataout.write_all(&serialized).unwrap();
+    dataout.write_all(&len_64.to_ne_bytes())?;
+    dataout.write_all(&serialized)?;
     //
     return Ok(1);
 } // end of dump for Point<T>
@@ -1124,24 +1153,36 @@ where
     let mut it_slice = [0u8; std::mem::size_of::<u32>()];
     data_in.read_exact(&mut it_slice)?;
     let magic = u32::from_ne_bytes(it_slice);
-    assert_eq!(
-        magic, MAGICDATAP,
-        "magic not equal to MAGICDATAP in load_point, point_id : {:?} ",
-        origin_id
-    );
+    if magic != MAGICDATAP {
+        return Err(anyhow!(
+            "magic not equal to MAGICDATAP in load_point, point_id : {:?} ",
+            origin_id
+        ));
+    }
     // read origin id
     let mut it_slice = [0u8; std::mem::size_of::<u64>()];
     data_in.read_exact(&mut it_slice)?;
     let origin_id_data = u64::from_ne_bytes(it_slice) as usize;
-    assert_eq!(
-        origin_id, origin_id_data as usize,
-        "origin_id incoherent between graph and data"
-    );
+    if origin_id != origin_id_data {
+        return Err(anyhow!("origin_id incoherent between graph and data"));
+    }
     // now read data. we use size_t that is in description, to take care of the casewhere we reload
     let mut it_slice = [0u8; std::mem::size_of::<u64>()];
     data_in.read_exact(&mut it_slice)?;
     let serialized_len = u64::from_ne_bytes(it_slice);
     log::trace!("serialized len to reload {:?}", serialized_len);
+    // format 3 dumps raw values, which are read back as a slice of dimension values
+    if descr.format_version == 3
+        && std::any::TypeId::of::<T>() != std::any::TypeId::of::<NoData>()
+        && serialized_len as usize != descr.dimension * std::mem::size_of::<T>()
+    {
+        return Err(anyhow!(
+            "point {} has {} bytes of data, expected dimension {}",
+            origin_id,
+            serialized_len,
+            descr.dimension
+        ));
+    }
     let mut v_serialized = Vec::<u8>::new();
     // TODO avoid initialization
     v_serialized.resize(serialized_len as usize, 0);
@@ -1149,7 +1190,7 @@ where
     let v: Vec<T>;
     if std::any::TypeId::of::<T>() != std::any::TypeId::of::<NoData>() {
         v = match descr.format_version {
-            2 => bincode::deserialize(&v_serialized).unwrap(),
+            2 => bincode::deserialize(&v_serialized)?,
             3 => {
                 let slice_t = unsafe {
                     std::slice::from_raw_parts(
@@ -1164,7 +1205,7 @@ where
                     "error in load_point, unknow format_version : {:?}",
                     descr.format_version
                 );
-                std::process::exit(1);
+                return Err(anyhow!("unknown format version {:?}", descr.format_version));
             }
         };
     } else {
@@ -1184,19 +1225,19 @@ fn skip_point_data<T>(
     let mut it_slice = [0u8; std::mem::size_of::<u32>()];
     data_in.read_exact(&mut it_slice)?;
     let magic = u32::from_ne_bytes(it_slice);
-    assert_eq!(
-        magic, MAGICDATAP,
-        "magic not equal to MAGICDATAP in load_point, point_id : {:?} ",
-        origin_id
-    );
+    if magic != MAGICDATAP {
+        return Err(anyhow!(
+            "magic not equal to MAGICDATAP in load_point, point_id : {:?} ",
+            origin_id
+        ));
+    }
     // read origin id
     let mut it_slice = [0u8; std::mem::size_of::<u64>()];
     data_in.read_exact(&mut it_slice)?;
     let origin_id_data = u64::from_ne_bytes(it_slice) as usize;
-    assert_eq!(
-        origin_id, origin_id_data as usize,
-        "origin_id incoherent between graph and data"
-    );
+    if origin_id != origin_id_data {
+        return Err(anyhow!("origin_id incoherent between graph and data"));
+    }
     //
     // now read data. we use size_t that is in description, to take care of the casewhere we reload
     let mut it_slice = [0u8; std::mem::size_of::<u64>()];
@@ -1206,10 +1247,10 @@ fn skip_point_data<T>(
         "skip_point_data : serialized len to reload {:?}",
         serialized_len
     );
-    let mut v_serialized = Vec::<u8>::new();
-    // TODO avoid initialization
-    v_serialized.resize(serialized_len as usize, 0);
-    data_in.read_exact(&mut v_serialized)?;
+    let skipped = io::copy(&mut data_in.take(serialized_len), &mut io::sink())?;
+    if skipped != serialized_len {
+        return Err(anyhow!("data of point {} is truncated", origin_id));
+    }
     //
     return Ok(());
 } // end of skip_point_data
@@ -1229,23 +1270,23 @@ fn load_point_graph(
     log::trace!("in load_point_graph");
     // read and check magic
     let mut it_slice = [0u8; std::mem::size_of::<u32>()];
-    graph_in.read_exact(&mut it_slice).unwrap();
+    graph_in.read_exact(&mut it_slice)?;
     let magic = u32::from_ne_bytes(it_slice);
     if magic != MAGICPOINT {
         log::error!("got instead of MAGICPOINT {:x}", magic);
         return Err(anyhow!("bad magic at point beginning"));
     }
     let mut it_slice = [0u8; std::mem::size_of::<DataId>()];
-    graph_in.read_exact(&mut it_slice).unwrap();
+    graph_in.read_exact(&mut it_slice)?;
     let origin_id = DataId::from_ne_bytes(it_slice);
     //
     // read point_id
     let mut it_slice = [0u8; std::mem::size_of::<u8>()];
-    graph_in.read_exact(&mut it_slice).unwrap();
+    graph_in.read_exact(&mut it_slice)?;
     let layer = u8::from_ne_bytes(it_slice);
     //
     let mut it_slice = [0u8; std::mem::size_of::<i32>()];
-    graph_in.read_exact(&mut it_slice).unwrap();
+    graph_in.read_exact(&mut it_slice)?;
     let rank_in_l = i32::from_ne_bytes(it_slice);
     let p_id = PointId {
         0: layer,
@@ -1259,30 +1300,36 @@ fn load_point_graph(
     //
     // Now  for each layer , read neighbours
     let nb_layer = descr.nb_layer;
+    if nb_layer > NB_LAYER_MAX {
+        return Err(anyhow!("inconsistent number of layers"));
+    }
     let mut neighborhood = Vec::<Vec<Neighbour>>::with_capacity(NB_LAYER_MAX as usize);
     for _l in 0..nb_layer {
         let mut neighbour: Neighbour = Default::default();
         // read nb_neighbour as usize!!! CAUTION, then nb_neighbours times identity(depends on Full
         // or Light) distance : f32
         let mut it_slice = [0u8; std::mem::size_of::<usize>()];
-        graph_in.read_exact(&mut it_slice).unwrap();
+        graph_in.read_exact(&mut it_slice)?;
         let nb_neighbours = usize::from_ne_bytes(it_slice);
+        if nb_neighbours > descr.nb_point {
+            return Err(anyhow!("more neighbours than points"));
+        }
         let mut neighborhood_l: Vec<Neighbour> = Vec::with_capacity(nb_neighbours as usize);
         for _j in 0..nb_neighbours {
             let mut it_slice = [0u8; std::mem::size_of::<DataId>()];
-            graph_in.read_exact(&mut it_slice).unwrap();
+            graph_in.read_exact(&mut it_slice)?;
             neighbour.d_id = DataId::from_ne_bytes(it_slice);
             if descr.dumpmode == 1 {
                 let mut it_slice = [0u8; std::mem::size_of::<u8>()];
-                graph_in.read_exact(&mut it_slice).unwrap();
+                graph_in.read_exact(&mut it_slice)?;
                 neighbour.p_id.0 = u8::from_ne_bytes(it_slice);
                 //
                 let mut it_slice = [0u8; std::mem::size_of::<i32>()];
-                graph_in.read_exact(&mut it_slice).unwrap();
+                graph_in.read_exact(&mut it_slice)?;
                 neighbour.p_id.1 = i32::from_ne_bytes(it_slice);
             }
             let mut it_slice = [0u8; std::mem::size_of::<f32>()];
-            graph_in.read_exact(&mut it_slice).unwrap();
+            graph_in.read_exact(&mut it_slice)?;
             neighbour.distance = f32::from_ne_bytes(it_slice);
             //  log::debug!("        voisins  load {:?} {:?} {:?} ", neighbour.p_id, neighbour.d_id
             // , neighbour.distance); now we have a new neighbour, we must really fill
@@ -1318,13 +1365,13 @@ impl<'b, T: Serialize + DeserializeOwned + Clone + Send + Sync> HnswIoT for Poin
         // dump max_layer
         let layers = self.points_by_layer.read();
         let nb_layer = layers.len() as u8;
-        graphout.write(&nb_layer.to_ne_bytes()).unwrap();
+        graphout.write_all(&nb_layer.to_ne_bytes())?;
         // dump layers from lower (most populatated to higher level)
         for i in 0..layers.len() {
             let nb_point = layers[i].len();
             log::debug!("dumping layer {:?}, nb_point {:?}", i, nb_point);
-            graphout.write(&MAGICLAYER.to_ne_bytes()).unwrap();
-            graphout.write(&nb_point.to_ne_bytes()).unwrap();
+            graphout.write_all(&MAGICLAYER.to_ne_bytes())?;
+            graphout.write_all(&nb_point.to_ne_bytes())?;
             for j in 0..layers[i].len() {
                 assert_eq!(
                     layers[i][j].get_point_id(),
@@ -1338,13 +1385,14 @@ impl<'b, T: Serialize + DeserializeOwned + Clone + Send + Sync> HnswIoT for Poin
         }
         // dump id of entry point
         let ep_read = self.entry_point.read();
-        assert!(ep_read.is_some());
-        let ep = ep_read.as_ref().unwrap();
-        graphout.write(&ep.get_origin_id().to_ne_bytes()).unwrap();
+        let ep = ep_read
+            .as_ref()
+            .ok_or_else(|| anyhow!("cannot dump a graph without entry point"))?;
+        graphout.write_all(&ep.get_origin_id().to_ne_bytes())?;
         let p_id = ep.get_point_id();
         if mode == DumpMode::Full {
-            graphout.write(&p_id.0.to_ne_bytes()).unwrap();
-            graphout.write(&p_id.1.to_ne_bytes()).unwrap();
+            graphout.write_all(&p_id.0.to_ne_bytes())?;
+            graphout.write_all(&p_id.1.to_ne_bytes())?;
         }
         log::info!(
             "dumped entry_point origin_d {:?}, p_id {:?} ",
@@ -1397,8 +1445,8 @@ impl<
         log::debug!("dump  obtained typename {:?}", type_name::<T>());
         description.dump(mode, graphout)?;
         // We must dump a header for dataout.
-        dataout.write(&MAGICDATAP.to_ne_bytes()).unwrap();
-        dataout.write(&datadim.to_ne_bytes()).unwrap();
+        dataout.write_all(&MAGICDATAP.to_ne_bytes())?;
+        dataout.write_all(&datadim.to_ne_bytes())?;
         //
         self.layer_indexed_points.dump(mode, dumpinit)?;
         Ok(1)
@@ -1698,6 +1746,59 @@ mod tests {
         let _ = std::fs::remove_file(&to_remove);
     } // end of reload_with_mmap
 
+    // reloads a dump with deleted points in a Hnsw outliving its Hnswio, then a truncated dump
+    #[test]
+    fn test_reload_owned_with_deleted() {
+        log_init_test();
+        let mut rng = rand::thread_rng();
+        let unif = Uniform::<f32>::new(0., 1.);
+        let nbcolumn = 300;
+        let data: Vec<Vec<f32>> = (0..nbcolumn)
+            .map(|_| (0..10).map(|_| unif.sample(&mut rng)).collect())
+            .collect();
+        let hnsw = Hnsw::<f32, dist::DistL1>::new(10, nbcolumn, 16, 25, dist::DistL1 {});
+        for i in 0..data.len() {
+            hnsw.insert((&data[i], i));
+        }
+        let deleted: Vec<DataId> = (0..nbcolumn).step_by(3).collect();
+        assert_eq!(hnsw.delete_points(&deleted), deleted.len());
+        let fname = "ownedreloadtest";
+        let directory = PathBuf::from(".");
+        let dumpname = hnsw.file_dump_in(&directory, fname).unwrap();
+        assert_eq!(dumpname, fname);
+        //
+        let hnsw_loaded: Hnsw<'static, f32, DistL1> = {
+            let reloader = HnswIo::new(directory.clone(), fname.to_string());
+            reloader.load_hnsw_with_dist(DistL1 {}).unwrap()
+        };
+        check_graph_equality(&hnsw_loaded, &hnsw);
+        let indexation = hnsw_loaded.get_point_indexation();
+        assert_eq!(indexation.get_nb_deleted(), deleted.len());
+        for id in 0..nbcolumn {
+            let live = indexation.get_origin_point_id(id).is_some();
+            assert_eq!(live, id % 3 != 0);
+        }
+        let search_res = hnsw_loaded.search(&data[1], 5, 25);
+        assert_eq!(search_res[0].d_id, 1);
+        assert!(search_res.iter().all(|n| n.d_id % 3 != 0));
+        //
+        let graph_path = directory.join(format!("{fname}.hnsw.graph"));
+        let graph_len = std::fs::metadata(&graph_path).unwrap().len();
+        OpenOptions::new()
+            .write(true)
+            .open(&graph_path)
+            .unwrap()
+            .set_len(graph_len / 2)
+            .unwrap();
+        let reloader = HnswIo::new(directory, fname.to_string());
+        assert!(reloader
+            .load_hnsw_with_dist::<f32, DistL1>(DistL1 {})
+            .is_err());
+        //
+        let _ = std::fs::remove_file("ownedreloadtest.hnsw.data");
+        let _ = std::fs::remove_file("ownedreloadtest.hnsw.graph");
+    } // end of test_reload_owned_with_deleted
+
     #[test]
     fn test_bincode() {
         let mut rng = rand::thread_rng();
@@ -0,0 +1,249 @@
+use actix_web::{
+    body::{EitherBody, MessageBody},
+    dev::{ServiceRequest, ServiceResponse},
+    http::{header, Method, StatusCode},
+    middleware::Next,
+    web::Data,
+    Error, HttpResponse,
+};
+
+use crate::{actix::helpers::error_response, setting::ServiceConfig};
+
+pub const API_KEY_HEADER: &str = "api-key";
+
+/// POST routes which only read data, allowed with the read-only key
+const READ_ONLY_POST_SUFFIXES: &[&str] = &["/search", "/recommend"];
+
+/// Api keys required by the service, see [`api_key_middleware`]
+pub struct ApiKeys {
+    read_write: Option<String>,
+    read_only: Option<String>,
+    exempt_paths: Vec<String>,
+}
+
+impl ApiKeys {
+    /// Returns None if no api key is configured and the service is open
+    pub fn from_config(config: &ServiceConfig) -> Option<Self> {
+        if config.api_key.is_none() && config.read_only_api_key.is_none() {
+            return None;
+        }
+        Some(ApiKeys {
+            read_write: config.api_key.clone(),
+            read_only: config.read_only_api_key.clone(),
+            exempt_paths: config.api_key_exempt_paths.clone(),
+        })
+    }
+
+    /// An exempt path also exempts all paths below it
+    fn is_exempt(&self, path: &str) -> bool {
+        self.exempt_paths.iter().any(|exempt| {
+            let exempt = exempt.trim_end_matches('/');
+            path == exempt
+                || path
+                    .strip_prefix(exempt)
+                    .is_some_and(|rest| rest.starts_with('/'))
+        })
+    }
+
+    fn check(&self, req: &ServiceRequest) -> Result<(), (StatusCode, &'static str)> {
+        // CORS preflight requests never carry credentials
+        if req.method() == Method::OPTIONS || self.is_exempt(req.path()) {
+            return Ok(());
+        }
+        self.check_key(
+            request_key(req),
+            is_read_only_request(req.method(), req.path()),
+        )
+    }
+
+    /// Check the key given with an operation, read-only operations also accept the read-only key
+    pub fn check_key(
+        &self,
+        key: Option<&str>,
+        read_only: bool,
+    ) -> Result<(), (StatusCode, &'static str)> {
+        let Some(key) = key else {
+            return Err((StatusCode::UNAUTHORIZED, "Must provide an API key"));
+        };
+        let matches = |expected: &Option<String>| {
+            expected
+                .as_deref()
+                .is_some_and(|expected| constant_time_eq(expected.as_bytes(), key.as_bytes()))
+        };
+        if matches(&self.read_write) {
+            return Ok(());
+        }
+        if matches(&self.read_only) {
+            return if read_only {
+                Ok(())
+            } else {
+                Err((
+                    StatusCode::FORBIDDEN,
+                    "Read-only API key is not allowed for this operation",
+                ))
+            };
+        }
+        Err((StatusCode::UNAUTHORIZED, "Invalid API key"))
+    }
+}
+
+/// Key from the `api-key` header, or from a bearer token
+fn request_key(req: &ServiceRequest) -> Option<&str> {
+    let headers = req.headers();
+    if let Some(key) = headers.get(API_KEY_HEADER) {
+        return key.to_str().ok();
+    }
+    headers
+        .get(header::AUTHORIZATION)?
+        .to_str()
+        .ok()?
+        .strip_prefix("Bearer ")
+        .map(str::trim)
+}
+
+fn is_read_only_request(method: &Method, path: &str) -> bool {
+    match *method {
+        Method::GET | Method::HEAD => true,
+        Method::POST => READ_ONLY_POST_SUFFIXES
+            .iter()
+            .any(|suffix| path.trim_end_matches('/').ends_with(suffix)),
+        _ => false,
+    }
+}
+
+/// Compare without early exit, so the response time does not reveal how much of the key matched.
+/// Only the length of the key may leak.
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    if a.len() != b.len() {
+        return false;
+    }
+    a.iter().zip(b).fold(0u8, |diff, (x, y)| diff | (x ^ y)) == 0
+}
+
+/// Reject requests without a valid api key, if [`ApiKeys`] are registered as app data
+pub async fn api_key_middleware(
+    req: ServiceRequest,
+    next: Next<impl MessageBody>,
+) -> Result<ServiceResponse<EitherBody<impl MessageBody>>, Error> {
+    let checked = match req.app_data::<Data<ApiKeys>>() {
+        Some(api_keys) => api_keys.check(&req),
+        None => Ok(()),
+    };
+    match checked {
+        Ok(()) => Ok(next.call(req).await?.map_into_left_body()),
+        Err((status, description)) => {
+            let response = error_response(status, description, 0.0);
+            Ok(req.into_response(response).map_into_right_body())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use actix_web::{
+        get,
+        middleware::from_fn,
+        post, put,
+        test::{call_service, init_service, TestRequest},
+        App,
+    };
+
+    use super::*;
+
+    #[get("/collections")]
+    async fn list() -> HttpResponse {
+        HttpResponse::Ok().finish()
+    }
+
+    #[post("/collections/test/points/search")]
+    async fn search() -> HttpResponse {
+        HttpResponse::Ok().finish()
+    }
+
+    #[put("/collections/test")]
+    async fn create() -> HttpResponse {
+        HttpResponse::Ok().finish()
+    }
+
+    #[get("/healthz")]
+    async fn health() -> HttpResponse {
+        HttpResponse::Ok().finish()
+    }
+
+    #[actix_web::test]
+    async fn test_api_key_middleware() {
+        let api_keys = Data::new(ApiKeys {
+            read_write: Some("secret".to_string()),
+            read_only: Some("reader".to_string()),
+            exempt_paths: vec!["/healthz".to_string()],
+        });
+        let app = init_service(
+            App::new()
+                .app_data(api_keys)
+                .wrap(from_fn(api_key_middleware))
+                .service(list)
+                .service(search)
+                .service(create)
+                .service(health),
+        )
+        .await;
+
+        let cases = [
+            (Method::GET, "/healthz", None, StatusCode::OK),
+            (Method::GET, "/collections", None, StatusCode::UNAUTHORIZED),
+            (
+                Method::GET,
+                "/collections",
+                Some("secre"),
+                StatusCode::UNAUTHORIZED,
+            ),
+            (Method::GET, "/collections", Some("secret"), StatusCode::OK),
+            (
+                Method::PUT,
+                "/collections/test",
+                Some("secret"),
+                StatusCode::OK,
+            ),
+            (Method::GET, "/collections", Some("reader"), StatusCode::OK),
+            (
+                Method::POST,
+                "/collections/test/points/search",
+                Some("reader"),
+                StatusCode::OK,
+            ),
+            (
+                Method::PUT,
+                "/collections/test",
+                Some("reader"),
+                StatusCode::FORBIDDEN,
+            ),
+        ];
+        for (method, path, key, expected) in cases {
+            let mut request = TestRequest::default().method(method.clone()).uri(path);
+            if let Some(key) = key {
+                request = request.insert_header((API_KEY_HEADER, key));
+            }
+            let response = call_service(&app, request.to_request()).await;
+            assert_eq!(response.status(), expected, "{method} {path} with {key:?}");
+        }
+
+        let request = TestRequest::get()
+            .uri("/collections")
+            .insert_header((header::AUTHORIZATION, "Bearer secret"))
+            .to_request();
+        assert_eq!(call_service(&app, request).await.status(), StatusCode::OK);
+    }
+
+    #[test]
+    fn test_exempt_paths() {
+        let api_keys = ApiKeys {
+            read_write: Some("secret".to_string()),
+            read_only: None,
+            exempt_paths: vec!["/swagger-ui/".to_string(), "/healthz".to_string()],
+        };
+        assert!(api_keys.is_exempt("/healthz"));
+        assert!(api_keys.is_exempt("/swagger-ui/index.html"));
+        assert!(!api_keys.is_exempt("/healthzz"));
+        assert!(!api_keys.is_exempt("/collections"));
+    }
+}
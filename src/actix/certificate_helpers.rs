@@ -0,0 +1,258 @@
+use std::{
+    fmt, fs, io,
+    path::PathBuf,
+    sync::Arc,
+    time::{Duration, Instant, SystemTime},
+};
+
+use parking_lot::{Mutex, RwLock};
+use rustls::{
+    crypto::{ring, CryptoProvider},
+    pki_types::{pem::PemObject, CertificateDer, PrivateKeyDer},
+    server::{ClientHello, ResolvesServerCert, WebPkiClientVerifier},
+    sign::CertifiedKey,
+    RootCertStore, ServerConfig,
+};
+
+use crate::setting::TlsConfig;
+
+/// Server config of the HTTPS and gRPC apis, verifying client certificates against
+/// `tls_config.ca_cert` when mutual TLS is enabled
+pub fn tls_server_config(
+    tls_config: &TlsConfig,
+    verify_client_certificate: bool,
+) -> io::Result<ServerConfig> {
+    let provider = Arc::new(ring::default_provider());
+    let resolver = CertResolver::new(tls_config, provider.clone())?;
+
+    let builder = ServerConfig::builder_with_provider(provider.clone())
+        .with_safe_default_protocol_versions()
+        .map_err(tls_error)?;
+    let builder = if verify_client_certificate {
+        let ca_path = tls_config
+            .ca_cert
+            .as_ref()
+            .ok_or_else(|| tls_error("Client certificate verification requires tls.ca_cert"))?;
+        let mut roots = RootCertStore::empty();
+        for cert in load_certs(ca_path)? {
+            roots.add(cert).map_err(tls_error)?;
+        }
+        let verifier = WebPkiClientVerifier::builder_with_provider(Arc::new(roots), provider)
+            .build()
+            .map_err(tls_error)?;
+        builder.with_client_cert_verifier(verifier)
+    } else {
+        builder.with_no_client_auth()
+    };
+    Ok(builder.with_cert_resolver(Arc::new(resolver)))
+}
+
+/// Serves the configured certificate, and reloads it when the certificate or key file changes.
+///
+/// Files are checked at most once per `cert_reload_interval_sec`, during a handshake.
+/// If the new files can't be loaded, the previous certificate is kept.
+struct CertResolver {
+    cert_path: PathBuf,
+    key_path: PathBuf,
+    reload_interval: Option<Duration>,
+    provider: Arc<CryptoProvider>,
+    loaded: RwLock<LoadedCert>,
+    last_check: Mutex<Instant>,
+}
+
+struct LoadedCert {
+    key: Arc<CertifiedKey>,
+    modified: (Option<SystemTime>, Option<SystemTime>),
+}
+
+impl CertResolver {
+    fn new(config: &TlsConfig, provider: Arc<CryptoProvider>) -> io::Result<Self> {
+        let key = certified_key(&config.cert, &config.key, &provider)?;
+        let resolver = CertResolver {
+            cert_path: PathBuf::from(&config.cert),
+            key_path: PathBuf::from(&config.key),
+            reload_interval: (config.cert_reload_interval_sec > 0)
+                .then(|| Duration::from_secs(config.cert_reload_interval_sec)),
+            provider,
+            loaded: RwLock::new(LoadedCert {
+                key: Arc::new(key),
+                modified: (None, None),
+            }),
+            last_check: Mutex::new(Instant::now()),
+        };
+        resolver.loaded.write().modified = resolver.modified();
+        Ok(resolver)
+    }
+
+    fn modified(&self) -> (Option<SystemTime>, Option<SystemTime>) {
+        let modified = |path: &PathBuf| fs::metadata(path).and_then(|meta| meta.modified()).ok();
+        (modified(&self.cert_path), modified(&self.key_path))
+    }
+
+    /// Current certificate, reloaded first if the files changed since the last check
+    fn current(&self) -> Arc<CertifiedKey> {
+        if let Some(interval) = self.reload_interval {
+            let mut last_check = self.last_check.lock();
+            if last_check.elapsed() >= interval {
+                *last_check = Instant::now();
+                drop(last_check);
+                self.reload_if_changed();
+            }
+        }
+        self.loaded.read().key.clone()
+    }
+
+    fn reload_if_changed(&self) {
+        let modified = self.modified();
+        if modified == self.loaded.read().modified {
+            return;
+        }
+        match certified_key(&self.cert_path, &self.key_path, &self.provider) {
+            Ok(key) => {
+                log::info!("Reloaded TLS certificate {}", self.cert_path.display());
+                *self.loaded.write() = LoadedCert {
+                    key: Arc::new(key),
+                    modified,
+                };
+            }
+            // Files may be caught in the middle of an update, retry on the next check
+            Err(err) => log::warn!(
+                "Failed to reload TLS certificate {}, keeping the previous one: {err}",
+                self.cert_path.display()
+            ),
+        }
+    }
+}
+
+impl fmt::Debug for CertResolver {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("CertResolver")
+            .field("cert_path", &self.cert_path)
+            .field("key_path", &self.key_path)
+            .finish_non_exhaustive()
+    }
+}
+
+impl ResolvesServerCert for CertResolver {
+    fn resolve(&self, _client_hello: ClientHello<'_>) -> Option<Arc<CertifiedKey>> {
+        Some(self.current())
+    }
+}
+
+fn certified_key(
+    cert_path: impl AsRef<std::path::Path>,
+    key_path: impl AsRef<std::path::Path>,
+    provider: &CryptoProvider,
+) -> io::Result<CertifiedKey> {
+    let certs = load_certs(cert_path)?;
+    let key_path = key_path.as_ref();
+    let key = PrivateKeyDer::from_pem_file(key_path).map_err(|err| {
+        tls_error(format!(
+            "Failed to read private key {}: {err}",
+            key_path.display()
+        ))
+    })?;
+    CertifiedKey::from_der(certs, key, provider).map_err(tls_error)
+}
+
+fn load_certs(path: impl AsRef<std::path::Path>) -> io::Result<Vec<CertificateDer<'static>>> {
+    let path = path.as_ref();
+    let certs = CertificateDer::pem_file_iter(path)
+        .and_then(|certs| certs.collect::<Result<Vec<_>, _>>())
+        .map_err(|err| {
+            tls_error(format!(
+                "Failed to read certificates {}: {err}",
+                path.display()
+            ))
+        })?;
+    if certs.is_empty() {
+        return Err(tls_error(format!(
+            "No certificate found in {}",
+            path.display()
+        )));
+    }
+    Ok(certs)
+}
+
+fn tls_error(err: impl ToString) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidInput, err.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs::File;
+
+    use rcgen::{generate_simple_self_signed, CertifiedKey as GeneratedCert};
+    use tempfile::Builder;
+
+    use super::*;
+
+    fn write_cert(dir: &std::path::Path, name: &str) -> GeneratedCert {
+        let generated = generate_simple_self_signed(vec![name.to_string()]).unwrap();
+        fs::write(dir.join("cert.pem"), generated.cert.pem()).unwrap();
+        fs::write(dir.join("key.pem"), generated.key_pair.serialize_pem()).unwrap();
+        generated
+    }
+
+    fn tls_config(dir: &std::path::Path, interval: u64) -> TlsConfig {
+        TlsConfig {
+            cert: dir.join("cert.pem").to_string_lossy().into_owned(),
+            key: dir.join("key.pem").to_string_lossy().into_owned(),
+            ca_cert: Some(dir.join("cert.pem").to_string_lossy().into_owned()),
+            cert_reload_interval_sec: interval,
+        }
+    }
+
+    #[test]
+    fn test_cert_reload() {
+        let dir = Builder::new().prefix("tls").tempdir().unwrap();
+        let first = write_cert(dir.path(), "first.local");
+        let resolver = CertResolver::new(
+            &tls_config(dir.path(), 1),
+            Arc::new(ring::default_provider()),
+        )
+        .unwrap();
+        assert_eq!(resolver.current().cert[0], *first.cert.der());
+
+        let second = write_cert(dir.path(), "second.local");
+        // Make sure the change is visible even with a coarse file system clock
+        let later = SystemTime::now() + Duration::from_secs(5);
+        for file in ["cert.pem", "key.pem"] {
+            let file = File::options()
+                .write(true)
+                .open(dir.path().join(file))
+                .unwrap();
+            file.set_modified(later).unwrap();
+        }
+        // Not checked again before the interval
+        assert_eq!(resolver.current().cert[0], *first.cert.der());
+        *resolver.last_check.lock() -= Duration::from_secs(1);
+        assert_eq!(resolver.current().cert[0], *second.cert.der());
+
+        // A broken key keeps the previous certificate
+        fs::write(dir.path().join("key.pem"), "not a key").unwrap();
+        File::options()
+            .write(true)
+            .open(dir.path().join("key.pem"))
+            .unwrap()
+            .set_modified(later + Duration::from_secs(5))
+            .unwrap();
+        *resolver.last_check.lock() -= Duration::from_secs(1);
+        assert_eq!(resolver.current().cert[0], *second.cert.der());
+    }
+
+    #[test]
+    fn test_server_config() {
+        let dir = Builder::new().prefix("tls").tempdir().unwrap();
+        write_cert(dir.path(), "localhost");
+        let mut config = tls_config(dir.path(), 0);
+        assert!(tls_server_config(&config, true).is_ok());
+
+        config.ca_cert = None;
+        assert!(tls_server_config(&config, true).is_err());
+        assert!(tls_server_config(&config, false).is_ok());
+
+        config.key = dir.path().join("missing.pem").to_string_lossy().into();
+        assert!(tls_server_config(&config, false).is_err());
+    }
+}
@@ -0,0 +1,413 @@
+use std::{
+  path::Path,
+  sync::atomic::AtomicBool,
+  time::{Duration, Instant},
+};
+
+use hdf5::{Dataset, File};
+use serde::Serialize;
+use uuid::Uuid;
+
+use crate::{
+  actix::{
+    handlers::dataset::{import_dataset, log_progress, DatasetFields, DatasetSource},
+    routes::dataset_api::DataType,
+    table::{
+      collections::{CollectionConfig, HnswConfig},
+      toc::TableOfContent,
+    },
+  },
+  common::{
+    operation_error::{OperationError, OperationResult},
+    point_id::PointIdType,
+  },
+  engine::{segments::optimizer::OptimizersConfig, types::distance::Distance},
+};
+
+/// Datasets of the ann-benchmarks files
+const TEST_DATASET: &str = "test";
+const NEIGHBORS_DATASET: &str = "neighbors";
+const TRAIN_DATASET: &str = "train";
+
+pub const DEFAULT_BENCH_K: usize = 10;
+pub const DEFAULT_BENCH_EF: [usize; 5] = [16, 32, 64, 128, 256];
+
+/// Parameters of a recall benchmark on an ann-benchmarks HDF5 file
+#[derive(Debug, Clone)]
+pub struct BenchConfig {
+  pub distance: Distance,
+  pub m: usize,
+  pub ef_construct: usize,
+  /// Number of searched neighbours, the recall is measured at this `k`
+  pub k: usize,
+  /// Values of `ef` the test queries are run with
+  pub ef: Vec<usize>,
+  /// Only the first test queries are run, if set
+  pub queries: Option<usize>,
+  pub batch_size: usize,
+}
+
+impl BenchConfig {
+  /// Temporary collection with a single HNSW indexed segment, built with the benchmarked params
+  fn collection_config(&self, vector_size: usize) -> CollectionConfig {
+    CollectionConfig {
+      vector_size,
+      distance: self.distance,
+      hnsw_config: Some(HnswConfig {
+        m: self.m,
+        ef_construct: self.ef_construct,
+        ..Default::default()
+      }),
+      quantization_config: None,
+      vector_storage_type: None,
+      optimizers_config: Some(OptimizersConfig {
+        indexing_threshold_kb: Some(1),
+        default_segment_number: 1,
+        // The benchmark builds the index itself, see `Collection::optimize`
+        max_optimization_threads: 0,
+        ..Default::default()
+      }),
+      normalize: false,
+    }
+  }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct BenchReport {
+  pub distance: Distance,
+  pub m: usize,
+  pub ef_construct: usize,
+  pub k: usize,
+  /// Points of the `train` dataset
+  pub points: usize,
+  pub dim: usize,
+  pub queries: usize,
+  /// Seconds to upsert the points
+  pub upload_seconds: f64,
+  /// Seconds to build the HNSW index once the points are upserted
+  pub index_seconds: f64,
+  pub results: Vec<EfResult>,
+}
+
+/// Quality and speed of the test queries at an `ef`
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct EfResult {
+  pub ef: usize,
+  /// Fraction of the `k` true nearest neighbours found, averaged over the queries
+  pub recall: f64,
+  /// Queries per second, searched one after the other
+  pub qps: f64,
+  pub latency_ms: Latency,
+}
+
+/// Latency percentiles of the queries, in milliseconds
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Latency {
+  pub mean: f64,
+  pub p50: f64,
+  pub p95: f64,
+  pub p99: f64,
+  pub max: f64,
+}
+
+impl Latency {
+  fn new(mut latencies: Vec<Duration>) -> Self {
+    latencies.sort();
+    let millis = |duration: Duration| duration.as_secs_f64() * 1000.0;
+    // Nearest rank, the smallest latency of at least `rank` of the queries
+    let percentile = |rank: f64| {
+      let index = (rank * latencies.len() as f64).ceil() as usize;
+      latencies
+        .get(index.saturating_sub(1))
+        .copied()
+        .map_or(0.0, millis)
+    };
+    let total: Duration = latencies.iter().sum();
+    Latency {
+      mean: millis(total) / latencies.len().max(1) as f64,
+      p50: percentile(0.5),
+      p95: percentile(0.95),
+      p99: percentile(0.99),
+      max: percentile(1.0),
+    }
+  }
+}
+
+/// Test queries with the ids of their true nearest neighbours in the `train` dataset
+struct GroundTruth {
+  queries: Vec<Vec<f32>>,
+  neighbors: Vec<Vec<u64>>,
+}
+
+fn bench_error(description: impl Into<String>) -> OperationError {
+  OperationError::ValidationError {
+    description: description.into(),
+  }
+}
+
+/// `(rows, columns)` of a 2-dimensional dataset
+fn matrix_shape(file: &File, name: &str) -> OperationResult<(usize, usize)> {
+  match file.dataset(name)?.shape().as_slice() {
+    &[rows, columns] => Ok((rows, columns)),
+    _ => Err(bench_error(format!(
+      "dataset {name} must have 2 dimensions"
+    ))),
+  }
+}
+
+fn read_rows<T: hdf5::H5Type + Copy>(
+  dataset: &Dataset,
+  rows: usize,
+  columns: usize,
+) -> OperationResult<Vec<Vec<T>>> {
+  let matrix = dataset.read_slice_2d::<T, _>((0..rows, 0..columns))?;
+  Ok(matrix.rows().into_iter().map(|row| row.to_vec()).collect())
+}
+
+impl GroundTruth {
+  fn read(file: &File, dim: usize, k: usize, limit: Option<usize>) -> OperationResult<Self> {
+    let (queries, query_dim) = matrix_shape(file, TEST_DATASET)?;
+    let (neighbor_rows, neighbor_count) = matrix_shape(file, NEIGHBORS_DATASET)?;
+    if query_dim != dim {
+      return Err(bench_error(format!(
+        "test queries have {query_dim} dimensions, train vectors have {dim}"
+      )));
+    }
+    if neighbor_rows != queries {
+      return Err(bench_error(format!(
+        "{neighbor_rows} rows of neighbors for {queries} test queries"
+      )));
+    }
+    if neighbor_count < k {
+      return Err(bench_error(format!(
+        "k is {k}, but the ground truth has only {neighbor_count} neighbors per query"
+      )));
+    }
+    let rows = limit.map_or(queries, |limit| limit.min(queries));
+    let neighbors = read_rows::<i64>(&file.dataset(NEIGHBORS_DATASET)?, rows, k)?
+      .into_iter()
+      .map(|ids| {
+        ids
+          .into_iter()
+          .map(|id| u64::try_from(id).map_err(|_| bench_error(format!("invalid neighbor {id}"))))
+          .collect()
+      })
+      .collect::<OperationResult<_>>()?;
+    Ok(GroundTruth {
+      queries: read_rows::<f32>(&file.dataset(TEST_DATASET)?, rows, dim)?,
+      neighbors,
+    })
+  }
+}
+
+/// Load the `train` vectors of an ann-benchmarks file into a temporary collection, then run the
+/// `test` queries at every `ef` and compare the results with the `neighbors` dataset
+///
+/// Point ids are the rows of the `train` vectors, as in the ground truth. The collection is
+/// deleted afterwards, whether the benchmark succeeds or not.
+pub async fn run_bench(
+  toc: &TableOfContent,
+  file_path: &Path,
+  config: &BenchConfig,
+) -> OperationResult<BenchReport> {
+  if config.k == 0 || config.ef.is_empty() {
+    return Err(bench_error(
+      "k must be positive and at least one ef must be given",
+    ));
+  }
+  // The file is closed before the import opens it again
+  let (points, dim, ground_truth) = {
+    let file = File::open(file_path)?;
+    let (points, dim) = matrix_shape(&file, TRAIN_DATASET)?;
+    let ground_truth = GroundTruth::read(&file, dim, config.k, config.queries)?;
+    (points, dim, ground_truth)
+  };
+
+  let collection_name = format!("bench-{}", Uuid::new_v4().simple());
+  toc
+    .create_collection(&collection_name, config.collection_config(dim))
+    .await?;
+  let result = bench_collection(toc, &collection_name, file_path, config, &ground_truth).await;
+  toc.delete_collection(&collection_name).await?;
+  let (upload_seconds, index_seconds, results) = result?;
+  Ok(BenchReport {
+    distance: config.distance,
+    m: config.m,
+    ef_construct: config.ef_construct,
+    k: config.k,
+    points,
+    dim,
+    queries: ground_truth.queries.len(),
+    upload_seconds,
+    index_seconds,
+    results,
+  })
+}
+
+async fn bench_collection(
+  toc: &TableOfContent,
+  collection_name: &str,
+  file_path: &Path,
+  config: &BenchConfig,
+  ground_truth: &GroundTruth,
+) -> OperationResult<(f64, f64, Vec<EfResult>)> {
+  let upload = Instant::now();
+  let source = DatasetSource::new(&DataType::HDF5, DatasetFields::default())?;
+  import_dataset(
+    toc,
+    collection_name,
+    file_path,
+    &source,
+    config.batch_size,
+    &AtomicBool::new(false),
+    log_progress(collection_name),
+  )
+  .await?;
+  let upload_seconds = upload.elapsed().as_secs_f64();
+
+  let index = Instant::now();
+  toc.optimize_collection(collection_name).await?;
+  let index_seconds = index.elapsed().as_secs_f64();
+
+  let mut results = Vec::with_capacity(config.ef.len());
+  for &ef in &config.ef {
+    let result = measure(toc, collection_name, ground_truth, config.k, ef).await?;
+    log::info!(
+      "ef {ef}: recall@{} {:.4}, {:.1} queries per second",
+      config.k,
+      result.recall,
+      result.qps
+    );
+    results.push(result);
+  }
+  Ok((upload_seconds, index_seconds, results))
+}
+
+/// Run the queries one by one with the given `ef`
+async fn measure(
+  toc: &TableOfContent,
+  collection_name: &str,
+  ground_truth: &GroundTruth,
+  k: usize,
+  ef: usize,
+) -> OperationResult<EfResult> {
+  let mut latencies = Vec::with_capacity(ground_truth.queries.len());
+  let mut found = 0;
+  let start = Instant::now();
+  for (query, neighbors) in ground_truth.queries.iter().zip(&ground_truth.neighbors) {
+    let search = Instant::now();
+    let result = toc
+      .search_with_ef(collection_name, query, k, Some(ef))
+      .await?;
+    latencies.push(search.elapsed());
+    found += result
+      .iter()
+      .filter(|point| matches!(point.id, PointIdType::NumId(id) if neighbors.contains(&id)))
+      .count();
+  }
+  let elapsed = start.elapsed().as_secs_f64();
+  let queries = ground_truth.queries.len();
+  Ok(EfResult {
+    ef,
+    recall: found as f64 / (queries * k).max(1) as f64,
+    qps: if elapsed > 0.0 {
+      queries as f64 / elapsed
+    } else {
+      0.0
+    },
+    latency_ms: Latency::new(latencies),
+  })
+}
+
+#[cfg(test)]
+mod tests {
+  use std::sync::Arc;
+
+  use rand::{rngs::StdRng, Rng, SeedableRng};
+  use serde_json::json;
+  use tempfile::Builder;
+
+  use super::*;
+  use crate::actix::model::points::PointStruct;
+
+  #[test]
+  fn test_latency() {
+    let latencies = (1..=100).rev().map(Duration::from_millis).collect();
+    let latency = Latency::new(latencies);
+    assert_eq!(latency.p50, 50.0);
+    assert_eq!(latency.p95, 95.0);
+    assert_eq!(latency.p99, 99.0);
+    assert_eq!(latency.max, 100.0);
+    assert_eq!(latency.mean, 50.5);
+    assert_eq!(Latency::new(Vec::new()).max, 0.0);
+  }
+
+  #[tokio::test]
+  async fn test_measure() {
+    let dir = Builder::new().prefix("bench").tempdir().unwrap();
+    let storage_config = serde_json::from_value(json!({
+      "storage_path": dir.path().join("storage"),
+      "snapshots_path": dir.path().join("snapshots"),
+    }))
+    .unwrap();
+    let toc = TableOfContent::new(Arc::new(storage_config)).unwrap();
+    let config = BenchConfig {
+      distance: Distance::Euclidean,
+      m: 16,
+      ef_construct: 64,
+      k: 5,
+      ef: vec![64],
+      queries: None,
+      batch_size: 100,
+    };
+    toc
+      .create_collection("bench", config.collection_config(4))
+      .await
+      .unwrap();
+
+    let mut rng = StdRng::seed_from_u64(42);
+    let mut random_vector = || (0..4).map(|_| rng.gen::<f32>()).collect::<Vec<_>>();
+    let train: Vec<_> = (0..300).map(|_| random_vector()).collect();
+    let queries: Vec<_> = (0..20).map(|_| random_vector()).collect();
+    let points = train
+      .iter()
+      .enumerate()
+      .map(|(id, vector)| PointStruct {
+        id: Some((id as u64).into()),
+        vector: vector.clone(),
+        payload: None,
+      })
+      .collect();
+    toc.upsert_points("bench", points).await.unwrap();
+    assert!(toc.optimize_collection("bench").await.unwrap() > 0);
+    let info = toc.collection_info("bench").await.unwrap();
+    assert_eq!(info.indexed_vectors_count, 300);
+
+    // Exact nearest neighbours as ground truth
+    let neighbors = queries
+      .iter()
+      .map(|query| {
+        let mut ids: Vec<_> = (0..train.len() as u64).collect();
+        let distance = |id: &u64| {
+          let vector = &train[*id as usize];
+          query
+            .iter()
+            .zip(vector)
+            .map(|(a, b)| (a - b) * (a - b))
+            .sum::<f32>()
+        };
+        ids.sort_by(|a, b| distance(a).total_cmp(&distance(b)));
+        ids.truncate(config.k);
+        ids
+      })
+      .collect();
+    let ground_truth = GroundTruth { queries, neighbors };
+    let result = measure(&toc, "bench", &ground_truth, config.k, 64)
+      .await
+      .unwrap();
+    assert_eq!(result.ef, 64);
+    assert!(result.recall > 0.9, "recall {}", result.recall);
+    assert!(result.qps > 0.0);
+    assert!(result.latency_ms.p50 <= result.latency_ms.max);
+  }
+}
@@ -0,0 +1,251 @@
+use std::time::Instant;
+
+use actix_web::{delete, get, post, put, web::Data, web::Path, HttpResponse};
+use actix_web_validator::Json;
+
+use crate::actix::{
+  helpers::process_response,
+  model::{
+    points::{PointsList, PointsSelector, RecommendRequest},
+    vector::SearchVector,
+  },
+  table::{collections::CollectionConfig, toc::TableOfContent},
+};
+
+#[utoipa::path(
+  get,
+  path = "/collections",
+  responses(
+    (status = 200, description = "Names of all collections")
+  )
+)]
+#[get("/collections")]
+pub async fn list_collections(toc: Data<TableOfContent>) -> HttpResponse {
+  let timing = Instant::now();
+  process_response(Ok(toc.list_collections().await), timing)
+}
+
+#[utoipa::path(
+  get,
+  path = "/collections/{collection_name}",
+  responses(
+    (status = 200, description = "Counts, segments, storage sizes and HNSW structure of the collection")
+  )
+)]
+#[get("/collections/{collection_name}")]
+pub async fn get_collection(
+  toc: Data<TableOfContent>,
+  collection_name: Path<String>,
+) -> HttpResponse {
+  let timing = Instant::now();
+  process_response(toc.collection_info(&collection_name).await, timing)
+}
+
+#[utoipa::path(
+  put,
+  path = "/collections/{collection_name}",
+  responses(
+    (status = 200, description = "Collection created")
+  )
+)]
+#[put("/collections/{collection_name}")]
+pub async fn create_collection(
+  toc: Data<TableOfContent>,
+  collection_name: Path<String>,
+  config: Json<CollectionConfig>,
+) -> HttpResponse {
+  let timing = Instant::now();
+  let result = toc
+    .create_collection(&collection_name, config.into_inner())
+    .await
+    .map(|()| true);
+  process_response(result, timing)
+}
+
+#[utoipa::path(
+  delete,
+  path = "/collections/{collection_name}",
+  responses(
+    (status = 200, description = "Collection deleted, false if it did not exist")
+  )
+)]
+#[delete("/collections/{collection_name}")]
+pub async fn delete_collection(
+  toc: Data<TableOfContent>,
+  collection_name: Path<String>,
+) -> HttpResponse {
+  let timing = Instant::now();
+  let result = toc.delete_collection(&collection_name).await;
+  process_response(result, timing)
+}
+
+#[utoipa::path(
+  put,
+  path = "/collections/{collection_name}/points",
+  request_body(
+    content_type = "application/json",
+    content = PointsList,
+  ),
+  responses(
+    (status = 200, description = "Ids of the upserted points")
+  )
+)]
+#[put("/collections/{collection_name}/points")]
+pub async fn upsert_points(
+  toc: Data<TableOfContent>,
+  collection_name: Path<String>,
+  operation: Json<PointsList>,
+) -> HttpResponse {
+  let timing = Instant::now();
+  let result = toc
+    .upsert_points(&collection_name, operation.into_inner().points)
+    .await;
+  process_response(result, timing)
+}
+
+#[utoipa::path(
+  post,
+  path = "/collections/{collection_name}/points/delete",
+  request_body(
+    content_type = "application/json",
+    content = PointsSelector,
+  ),
+  responses(
+    (status = 200, description = "Number of deleted points")
+  )
+)]
+#[post("/collections/{collection_name}/points/delete")]
+pub async fn delete_points(
+  toc: Data<TableOfContent>,
+  collection_name: Path<String>,
+  operation: Json<PointsSelector>,
+) -> HttpResponse {
+  let timing = Instant::now();
+  let result = toc.delete_points(&collection_name, &operation.points).await;
+  process_response(result, timing)
+}
+
+#[utoipa::path(
+  post,
+  path = "/collections/{collection_name}/points/search",
+  request_body(
+    content_type = "application/json",
+    content = SearchVector,
+  ),
+  responses(
+    (status = 200, description = "Closest points of the collection")
+  )
+)]
+#[post("/collections/{collection_name}/points/search")]
+pub async fn search_points(
+  toc: Data<TableOfContent>,
+  collection_name: Path<String>,
+  operation: Json<SearchVector>,
+) -> HttpResponse {
+  let timing = Instant::now();
+  let result = toc
+    .search(&collection_name, &operation.vector, operation.k)
+    .await;
+  process_response(result, timing)
+}
+
+#[utoipa::path(
+  post,
+  path = "/collections/{collection_name}/vacuum",
+  responses(
+    (status = 200, description = "Number of rewritten segments and reclaimed vectors")
+  )
+)]
+#[post("/collections/{collection_name}/vacuum")]
+pub async fn vacuum_collection(
+  toc: Data<TableOfContent>,
+  collection_name: Path<String>,
+) -> HttpResponse {
+  let timing = Instant::now();
+  let result = toc.vacuum_collection(&collection_name).await;
+  process_response(result, timing)
+}
+
+#[utoipa::path(
+  post,
+  path = "/collections/{collection_name}/points/recommend",
+  request_body(
+    content_type = "application/json",
+    content = RecommendRequest,
+  ),
+  responses(
+    (status = 200, description = "Points closest to the positive examples and far from the negative ones")
+  )
+)]
+#[post("/collections/{collection_name}/points/recommend")]
+pub async fn recommend_points(
+  toc: Data<TableOfContent>,
+  collection_name: Path<String>,
+  operation: Json<RecommendRequest>,
+) -> HttpResponse {
+  let timing = Instant::now();
+  let result = toc
+    .recommend(
+      &collection_name,
+      &operation.positive,
+      &operation.negative,
+      operation.k,
+    )
+    .await;
+  process_response(result, timing)
+}
+
+#[utoipa::path(
+  post,
+  path = "/collections/{collection_name}/snapshots",
+  responses(
+    (status = 200, description = "Description of the created snapshot")
+  )
+)]
+#[post("/collections/{collection_name}/snapshots")]
+pub async fn create_snapshot(
+  toc: Data<TableOfContent>,
+  collection_name: Path<String>,
+) -> HttpResponse {
+  let timing = Instant::now();
+  let result = toc.create_snapshot(&collection_name).await;
+  process_response(result, timing)
+}
+
+#[utoipa::path(
+  get,
+  path = "/collections/{collection_name}/snapshots",
+  responses(
+    (status = 200, description = "Snapshots of the collection")
+  )
+)]
+#[get("/collections/{collection_name}/snapshots")]
+pub async fn list_snapshots(
+  toc: Data<TableOfContent>,
+  collection_name: Path<String>,
+) -> HttpResponse {
+  let timing = Instant::now();
+  let result = toc.list_snapshots(&collection_name).await;
+  process_response(result, timing)
+}
+
+#[utoipa::path(
+  delete,
+  path = "/collections/{collection_name}/snapshots/{snapshot_name}",
+  responses(
+    (status = 200, description = "Snapshot deleted")
+  )
+)]
+#[delete("/collections/{collection_name}/snapshots/{snapshot_name}")]
+pub async fn delete_snapshot(
+  toc: Data<TableOfContent>,
+  path: Path<(String, String)>,
+) -> HttpResponse {
+  let timing = Instant::now();
+  let (collection_name, snapshot_name) = path.into_inner();
+  let result = toc
+    .delete_snapshot(&collection_name, &snapshot_name)
+    .await
+    .map(|()| true);
+  process_response(result, timing)
+}
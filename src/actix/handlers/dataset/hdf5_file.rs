@@ -0,0 +1,132 @@
+use std::{ops::Range, path::Path, sync::atomic::AtomicBool};
+
+use hdf5::{Dataset, File};
+use serde_json::{Map, Number, Value};
+
+use super::import_points;
+use crate::{
+  actix::{model::points::PointStruct, table::toc::TableOfContent},
+  common::operation_error::{OperationError, OperationResult},
+  engine::types::types::Payload,
+};
+
+/// Datasets of an HDF5 file to import into a collection
+pub struct Hdf5Import {
+  /// Path of the 2-dimensional dataset with one vector per row, like `train` in ann-benchmarks
+  /// files
+  pub vectors: String,
+  /// Path of a 1-dimensional dataset with the numeric ids of the vectors. If not set, the next
+  /// numeric ids of the collection are used.
+  pub ids: Option<String>,
+  /// Paths of 1-dimensional numeric datasets, stored in the payload under the last component of
+  /// their path
+  pub payload: Vec<String>,
+}
+
+pub(super) async fn import_hdf5(
+  toc: &TableOfContent,
+  collection_name: &str,
+  file_path: &Path,
+  import: &Hdf5Import,
+  batch_size: usize,
+  stopped: &AtomicBool,
+  progress: impl FnMut(usize, Option<usize>),
+) -> OperationResult<usize> {
+  let file = File::open(file_path)?;
+  let vectors = file.dataset(&import.vectors)?;
+  let &[total, _dim] = vectors.shape().as_slice() else {
+    return Err(OperationError::ValidationError {
+      description: format!("dataset {} must have 2 dimensions", import.vectors),
+    });
+  };
+  let ids = import
+    .ids
+    .as_deref()
+    .map(|path| column_dataset(&file, path, total))
+    .transpose()?;
+  let payload = import
+    .payload
+    .iter()
+    .map(|path| {
+      let key = path.rsplit('/').next().unwrap_or(path).to_string();
+      Ok((key, column_dataset(&file, path, total)?))
+    })
+    .collect::<OperationResult<Vec<_>>>()?;
+
+  let read_batch = |rows: Range<usize>| -> OperationResult<Vec<PointStruct>> {
+    let batch = vectors.read_slice_2d::<f32, _>((rows.clone(), ..))?;
+    let ids = match &ids {
+      Some(ids) => Some(ids.read_slice_1d::<u64, _>(rows.clone())?.to_vec()),
+      None => None,
+    };
+    let payload_values = payload
+      .iter()
+      .map(|(key, dataset)| Ok((key, dataset.read_slice_1d::<f64, _>(rows.clone())?.to_vec())))
+      .collect::<OperationResult<Vec<_>>>()?;
+    let points = batch
+      .rows()
+      .into_iter()
+      .enumerate()
+      .map(|(row, vector)| {
+        let payload = (!payload_values.is_empty()).then(|| {
+          let fields = payload_values
+            .iter()
+            .map(|(key, values)| (key.to_string(), number_value(values[row])))
+            .collect::<Map<_, _>>();
+          Payload(fields)
+        });
+        PointStruct {
+          id: ids.as_ref().map(|ids| ids[row].into()),
+          vector: vector.to_vec(),
+          payload,
+        }
+      })
+      .collect();
+    Ok(points)
+  };
+  let batches = (0..total)
+    .step_by(batch_size)
+    .map(|start| read_batch(start..total.min(start + batch_size)));
+  import_points(
+    toc,
+    collection_name,
+    batches,
+    Some(total),
+    stopped,
+    progress,
+  )
+  .await
+}
+
+/// 1-dimensional dataset with a value per vector
+fn column_dataset(file: &File, path: &str, total: usize) -> OperationResult<Dataset> {
+  let dataset = file.dataset(path)?;
+  if dataset.shape() != [total] {
+    return Err(OperationError::ValidationError {
+      description: format!("dataset {path} must have 1 dimension of {total} values"),
+    });
+  }
+  Ok(dataset)
+}
+
+/// HDF5 numbers are read as doubles, integral values are stored as integers
+fn number_value(number: f64) -> Value {
+  if number.fract() == 0.0 && number.abs() < i64::MAX as f64 {
+    return Value::Number((number as i64).into());
+  }
+  Number::from_f64(number).map_or(Value::Null, Value::Number)
+}
+
+#[cfg(test)]
+mod tests {
+  use serde_json::json;
+
+  use super::*;
+
+  #[test]
+  fn test_number_value() {
+    assert_eq!(number_value(3.0), json!(3));
+    assert_eq!(number_value(-0.5), json!(-0.5));
+    assert_eq!(number_value(f64::NAN), Value::Null);
+  }
+}
@@ -0,0 +1,459 @@
+mod hdf5_file;
+mod numpy;
+mod tabular;
+mod texmex;
+
+use std::{
+  io::{Seek, Write},
+  path::Path,
+  sync::atomic::AtomicBool,
+};
+
+use crate::{
+  actix::{model::points::PointStruct, routes::dataset_api::DataType, table::toc::TableOfContent},
+  common::operation_error::{check_process_stopped, OperationError, OperationResult},
+};
+pub use hdf5_file::Hdf5Import;
+pub use numpy::NpzImport;
+use numpy::{NpyWriter, NpzWriter};
+pub use tabular::ColumnMapping;
+pub use texmex::TexmexFormat;
+use texmex::TexmexWriter;
+
+/// Number of points upserted at once if the upload does not set it
+pub const DEFAULT_IMPORT_BATCH_SIZE: usize = 1000;
+
+/// Dataset file to import into a collection, with the location of the point fields in it
+pub enum DatasetSource {
+  Hdf5(Hdf5Import),
+  Csv(ColumnMapping),
+  Parquet(ColumnMapping),
+  Texmex(TexmexFormat),
+  Npy,
+  Npz(NpzImport),
+}
+
+/// Location of the point fields in a dataset file, as given by the upload or the command line
+///
+/// Their meaning depends on the format of the file, see [`DatasetSource::new`].
+#[derive(Debug, Clone, Default)]
+pub struct DatasetFields {
+  /// HDF5 dataset or `.npz` array of the vectors
+  pub dataset: Option<String>,
+  /// CSV or Parquet vector columns
+  pub vectors: Vec<String>,
+  /// HDF5 dataset, column or `.npz` array of the point ids
+  pub ids: Option<String>,
+  /// HDF5 datasets or columns stored in the payload
+  pub payload: Vec<String>,
+}
+
+/// Name of the vectors dataset in ann-benchmarks files
+const DEFAULT_VECTORS_DATASET: &str = "train";
+
+impl DatasetSource {
+  pub fn new(datatype: &DataType, fields: DatasetFields) -> OperationResult<Self> {
+    let DatasetFields {
+      dataset,
+      vectors,
+      ids,
+      payload,
+    } = fields;
+    let unsupported = |field: &str| {
+      Err(OperationError::ValidationError {
+        description: format!("{datatype:?} files have no {field}"),
+      })
+    };
+    let mapping = || ColumnMapping {
+      id: ids.clone(),
+      vector: vectors.clone(),
+      payload: payload.clone(),
+    };
+    match datatype {
+      DataType::HDF5 => Ok(DatasetSource::Hdf5(Hdf5Import {
+        vectors: dataset.unwrap_or_else(|| DEFAULT_VECTORS_DATASET.to_string()),
+        ids,
+        payload,
+      })),
+      DataType::CSV => Ok(DatasetSource::Csv(mapping())),
+      DataType::PARQUET => Ok(DatasetSource::Parquet(mapping())),
+      DataType::FVECS | DataType::IVECS | DataType::BVECS | DataType::NPY if ids.is_some() => {
+        unsupported("point ids")
+      }
+      _ if !payload.is_empty() => unsupported("payload"),
+      DataType::FVECS => Ok(DatasetSource::Texmex(TexmexFormat::Fvecs)),
+      DataType::IVECS => Ok(DatasetSource::Texmex(TexmexFormat::Ivecs)),
+      DataType::BVECS => Ok(DatasetSource::Texmex(TexmexFormat::Bvecs)),
+      DataType::NPY => Ok(DatasetSource::Npy),
+      DataType::NPZ => Ok(DatasetSource::Npz(NpzImport {
+        vectors: dataset,
+        ids,
+      })),
+    }
+  }
+}
+
+/// Import the points of a dataset file into the collection, see [`import_points`]
+pub async fn import_dataset(
+  toc: &TableOfContent,
+  collection_name: &str,
+  file_path: &Path,
+  source: &DatasetSource,
+  batch_size: usize,
+  stopped: &AtomicBool,
+  progress: impl FnMut(usize, Option<usize>),
+) -> OperationResult<usize> {
+  if batch_size == 0 {
+    return Err(OperationError::ValidationError {
+      description: "batch size must be positive".to_string(),
+    });
+  }
+  match source {
+    DatasetSource::Hdf5(import) => {
+      hdf5_file::import_hdf5(
+        toc,
+        collection_name,
+        file_path,
+        import,
+        batch_size,
+        stopped,
+        progress,
+      )
+      .await
+    }
+    DatasetSource::Csv(mapping) => {
+      tabular::import_csv(
+        toc,
+        collection_name,
+        file_path,
+        mapping,
+        batch_size,
+        stopped,
+        progress,
+      )
+      .await
+    }
+    DatasetSource::Parquet(mapping) => {
+      tabular::import_parquet(
+        toc,
+        collection_name,
+        file_path,
+        mapping,
+        batch_size,
+        stopped,
+        progress,
+      )
+      .await
+    }
+    DatasetSource::Texmex(format) => {
+      texmex::import_texmex(
+        toc,
+        collection_name,
+        file_path,
+        *format,
+        batch_size,
+        stopped,
+        progress,
+      )
+      .await
+    }
+    DatasetSource::Npy => {
+      numpy::import_npy(
+        toc,
+        collection_name,
+        file_path,
+        batch_size,
+        stopped,
+        progress,
+      )
+      .await
+    }
+    DatasetSource::Npz(import) => {
+      numpy::import_npz(
+        toc,
+        collection_name,
+        file_path,
+        import,
+        batch_size,
+        stopped,
+        progress,
+      )
+      .await
+    }
+  }
+}
+
+/// Log the progress of an import into the collection
+pub fn log_progress(collection_name: &str) -> impl FnMut(usize, Option<usize>) + '_ {
+  move |ingested, total| match total {
+    Some(total) => {
+      log::info!("Imported {ingested} of {total} points into collection {collection_name}")
+    }
+    None => log::info!("Imported {ingested} points into collection {collection_name}"),
+  }
+}
+
+/// File format of exported vectors
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExportFormat {
+  Texmex(TexmexFormat),
+  Npy,
+  Npz,
+}
+
+impl ExportFormat {
+  pub fn new(datatype: &DataType) -> OperationResult<Self> {
+    match datatype {
+      DataType::FVECS => Ok(ExportFormat::Texmex(TexmexFormat::Fvecs)),
+      DataType::IVECS => Ok(ExportFormat::Texmex(TexmexFormat::Ivecs)),
+      DataType::BVECS => Ok(ExportFormat::Texmex(TexmexFormat::Bvecs)),
+      DataType::NPY => Ok(ExportFormat::Npy),
+      DataType::NPZ => Ok(ExportFormat::Npz),
+      DataType::HDF5 | DataType::CSV | DataType::PARQUET => Err(OperationError::ValidationError {
+        description: format!("vectors can not be exported as {datatype:?}"),
+      }),
+    }
+  }
+
+  pub fn extension(self) -> &'static str {
+    match self {
+      ExportFormat::Texmex(TexmexFormat::Fvecs) => "fvecs",
+      ExportFormat::Texmex(TexmexFormat::Ivecs) => "ivecs",
+      ExportFormat::Texmex(TexmexFormat::Bvecs) => "bvecs",
+      ExportFormat::Npy => "npy",
+      ExportFormat::Npz => "npz",
+    }
+  }
+}
+
+/// Write the vectors of the collection into `writer`, returns the number of exported points
+///
+/// Only `.npz` archives keep the point ids, the other formats store the vectors in the order of
+/// the segments.
+pub async fn export_dataset(
+  toc: &TableOfContent,
+  collection_name: &str,
+  format: ExportFormat,
+  writer: impl Write + Seek,
+) -> OperationResult<usize> {
+  let count = match format {
+    ExportFormat::Texmex(format) => {
+      let mut sink = TexmexWriter::new(writer, format);
+      let count = toc.export_vectors(collection_name, &mut sink).await?;
+      sink.finish()?;
+      count
+    }
+    ExportFormat::Npy => {
+      let mut sink = NpyWriter::new(writer);
+      let count = toc.export_vectors(collection_name, &mut sink).await?;
+      sink.finish()?;
+      count
+    }
+    ExportFormat::Npz => {
+      let mut sink = NpzWriter::new(writer);
+      let count = toc.export_vectors(collection_name, &mut sink).await?;
+      sink.finish()?;
+      count
+    }
+  };
+  Ok(count)
+}
+
+/// Upsert batches of points into the collection as they are read
+///
+/// `progress` is called with the number of ingested points after every batch, along with the
+/// number of points of the dataset if it is known. The import is cancelled before the next batch
+/// once `stopped` is set. Returns the number of ingested points.
+pub async fn import_points(
+  toc: &TableOfContent,
+  collection_name: &str,
+  batches: impl Iterator<Item = OperationResult<Vec<PointStruct>>>,
+  total: Option<usize>,
+  stopped: &AtomicBool,
+  mut progress: impl FnMut(usize, Option<usize>),
+) -> OperationResult<usize> {
+  let mut ingested = 0;
+  for points in batches {
+    check_process_stopped(stopped)?;
+    let points = points?;
+    ingested += points.len();
+    toc.upsert_points(collection_name, points).await?;
+    progress(ingested, total);
+  }
+  Ok(ingested)
+}
+
+/// Group points read one by one into batches of `batch_size` points
+pub fn batched(
+  mut points: impl Iterator<Item = OperationResult<PointStruct>>,
+  batch_size: usize,
+) -> impl Iterator<Item = OperationResult<Vec<PointStruct>>> {
+  std::iter::from_fn(move || {
+    let batch = points
+      .by_ref()
+      .take(batch_size)
+      .collect::<OperationResult<Vec<_>>>();
+    match batch {
+      Ok(batch) if batch.is_empty() => None,
+      batch => Some(batch),
+    }
+  })
+}
+
+#[cfg(test)]
+mod tests {
+  use std::sync::{atomic::Ordering, Arc};
+
+  use serde_json::json;
+  use tempfile::Builder;
+
+  use super::*;
+
+  #[tokio::test]
+  async fn test_import_points() {
+    let dir = Builder::new().prefix("import").tempdir().unwrap();
+    let storage_config = serde_json::from_value(json!({
+      "storage_path": dir.path().join("storage"),
+      "snapshots_path": dir.path().join("snapshots"),
+    }))
+    .unwrap();
+    let toc = TableOfContent::new(Arc::new(storage_config)).unwrap();
+    let config = serde_json::from_value(json!({
+      "vector_size": 2,
+      "distance": "Euclidean",
+      "vector_storage_type": "AppendableMemmap",
+    }))
+    .unwrap();
+    toc.create_collection("test", config).await.unwrap();
+
+    let points = (0..25).map(|row| {
+      Ok(PointStruct {
+        id: Some((row as u64 * 10).into()),
+        vector: vec![row as f32, 0.0],
+        payload: None,
+      })
+    });
+    let stopped = AtomicBool::new(false);
+    let mut reported = Vec::new();
+    let ingested = import_points(
+      &toc,
+      "test",
+      batched(points.clone(), 10),
+      Some(25),
+      &stopped,
+      |ingested, total| reported.push((ingested, total)),
+    )
+    .await
+    .unwrap();
+    assert_eq!(ingested, 25);
+    assert_eq!(reported, [(10, Some(25)), (20, Some(25)), (25, Some(25))]);
+    let found = toc.search("test", &[24.0, 0.0], 1).await.unwrap();
+    assert_eq!(found[0].id, 240.into());
+
+    // Batches are upserted with the usual validation
+    let invalid = PointStruct {
+      id: None,
+      vector: vec![1.0],
+      payload: None,
+    };
+    let err = import_points(
+      &toc,
+      "test",
+      batched([Ok(invalid)].into_iter(), 10),
+      None,
+      &stopped,
+      |_, _| (),
+    )
+    .await
+    .unwrap_err();
+    assert!(matches!(err, OperationError::WrongVector { .. }));
+
+    // A stopped import is cancelled before its next batch
+    let mut reported = Vec::new();
+    let err = import_points(
+      &toc,
+      "test",
+      batched(points, 10),
+      Some(25),
+      &stopped,
+      |ingested, _| {
+        reported.push(ingested);
+        stopped.store(true, Ordering::Relaxed);
+      },
+    )
+    .await
+    .unwrap_err();
+    assert!(matches!(err, OperationError::Cancelled { .. }));
+    assert_eq!(reported, [10]);
+  }
+
+  #[tokio::test]
+  async fn test_export_import() {
+    let dir = Builder::new().prefix("export").tempdir().unwrap();
+    let storage_config = serde_json::from_value(json!({
+      "storage_path": dir.path().join("storage"),
+      "snapshots_path": dir.path().join("snapshots"),
+    }))
+    .unwrap();
+    let toc = TableOfContent::new(Arc::new(storage_config)).unwrap();
+    let config = json!({
+      "vector_size": 2,
+      "distance": "Euclidean",
+      "vector_storage_type": "AppendableMemmap",
+    });
+    for name in ["source", "npz", "fvecs"] {
+      let config = serde_json::from_value(config.clone()).unwrap();
+      toc.create_collection(name, config).await.unwrap();
+    }
+    let points = (0..5)
+      .map(|id| PointStruct {
+        id: Some((id * 7).into()),
+        vector: vec![id as f32, 1.0],
+        payload: None,
+      })
+      .collect();
+    toc.upsert_points("source", points).await.unwrap();
+    toc.delete_points("source", &[14.into()]).await.unwrap();
+
+    let stopped = AtomicBool::new(false);
+    let npz_path = dir.path().join("source.npz");
+    let file = std::fs::File::create(&npz_path).unwrap();
+    let exported = export_dataset(&toc, "source", ExportFormat::Npz, file)
+      .await
+      .unwrap();
+    assert_eq!(exported, 4);
+    let fields = DatasetFields {
+      ids: Some("ids".to_string()),
+      ..Default::default()
+    };
+    let source = DatasetSource::new(&DataType::NPZ, fields).unwrap();
+    let imported = import_dataset(&toc, "npz", &npz_path, &source, 3, &stopped, |_, _| ())
+      .await
+      .unwrap();
+    assert_eq!(imported, 4);
+    let found = toc.search("npz", &[3.0, 1.0], 1).await.unwrap();
+    assert_eq!(found[0].id, 21.into());
+
+    // Other formats only keep the vectors
+    let fvecs_path = dir.path().join("source.fvecs");
+    let format = ExportFormat::new(&DataType::FVECS).unwrap();
+    let file = std::fs::File::create(&fvecs_path).unwrap();
+    export_dataset(&toc, "source", format, file).await.unwrap();
+    let source = DatasetSource::new(&DataType::FVECS, DatasetFields::default()).unwrap();
+    let imported = import_dataset(&toc, "fvecs", &fvecs_path, &source, 3, &stopped, |_, _| ())
+      .await
+      .unwrap();
+    assert_eq!(imported, 4);
+    assert_eq!(
+      toc.search("fvecs", &[4.0, 1.0], 1).await.unwrap()[0].distance,
+      0.0
+    );
+
+    let fields = DatasetFields {
+      ids: Some("ids".to_string()),
+      ..Default::default()
+    };
+    assert!(DatasetSource::new(&DataType::FVECS, fields).is_err());
+    assert!(ExportFormat::new(&DataType::CSV).is_err());
+  }
+}
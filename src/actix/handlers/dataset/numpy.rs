@@ -0,0 +1,435 @@
+use std::{
+  fs::File,
+  io::{self, BufReader, BufWriter, Read, Seek, Write},
+  path::Path,
+  sync::atomic::AtomicBool,
+};
+
+use zip::{result::ZipError, write::FileOptions, CompressionMethod, ZipArchive, ZipWriter};
+
+use super::{batched, import_points};
+use crate::{
+  actix::{
+    model::points::PointStruct,
+    table::{collections::VectorSink, toc::TableOfContent},
+  },
+  common::{
+    operation_error::{OperationError, OperationResult},
+    point_id::PointIdType,
+  },
+  engine::types::types::VectorElementType,
+};
+
+const NPY_MAGIC: &[u8] = b"\x93NUMPY";
+/// Little endian `f32`, the only type of vector arrays
+const VECTORS_DESCR: &str = "<f4";
+/// Names of the arrays of exported `.npz` archives
+const NPZ_VECTORS: &str = "vectors";
+const NPZ_IDS: &str = "ids";
+
+/// Arrays of an `.npz` archive to import
+pub struct NpzImport {
+  /// Array of the vectors. If not set, the first array of the archive is used.
+  pub vectors: Option<String>,
+  /// Array of the numeric point ids, `<i8` or `<u8`. If not set, the next numeric ids of the
+  /// collection are used.
+  pub ids: Option<String>,
+}
+
+fn npy_error(description: impl std::fmt::Display) -> OperationError {
+  OperationError::ValidationError {
+    description: format!("Invalid NumPy file: {description}"),
+  }
+}
+
+fn npz_error(err: ZipError) -> OperationError {
+  match err {
+    ZipError::Io(err) => err.into(),
+    ZipError::FileNotFound => npy_error("the array does not exist in the archive"),
+    err => npy_error(err),
+  }
+}
+
+/// Type and shape of a `.npy` array
+#[derive(Debug, PartialEq)]
+struct NpyHeader {
+  descr: String,
+  shape: Vec<usize>,
+}
+
+impl NpyHeader {
+  /// Read the header of a C-ordered array, the reader is left at the start of the data
+  fn read(reader: &mut impl Read) -> OperationResult<Self> {
+    let mut preamble = [0; 8];
+    reader.read_exact(&mut preamble)?;
+    if &preamble[..6] != NPY_MAGIC {
+      return Err(npy_error("the magic string is missing"));
+    }
+    let header_len = match preamble[6] {
+      1 => {
+        let mut len = [0; 2];
+        reader.read_exact(&mut len)?;
+        u16::from_le_bytes(len) as usize
+      }
+      2 | 3 => {
+        let mut len = [0; 4];
+        reader.read_exact(&mut len)?;
+        u32::from_le_bytes(len) as usize
+      }
+      version => return Err(npy_error(format!("unsupported version {version}"))),
+    };
+    let mut header = vec![0; header_len];
+    reader.read_exact(&mut header)?;
+    let header = String::from_utf8_lossy(&header);
+
+    let field = |name: &str| {
+      let start = header
+        .find(&format!("'{name}':"))
+        .ok_or_else(|| npy_error(format!("the header has no {name}")))?;
+      Ok::<_, OperationError>(header[start + name.len() + 3..].trim_start())
+    };
+    if field("fortran_order")?.starts_with("True") {
+      return Err(npy_error("arrays in Fortran order are not supported"));
+    }
+    let descr = field("descr")?
+      .strip_prefix('\'')
+      .and_then(|descr| descr.split('\'').next())
+      .ok_or_else(|| npy_error("invalid descr"))?
+      .to_string();
+    let shape = field("shape")?
+      .strip_prefix('(')
+      .and_then(|shape| shape.split(')').next())
+      .ok_or_else(|| npy_error("invalid shape"))?
+      .split(',')
+      .map(str::trim)
+      .filter(|dim| !dim.is_empty())
+      .map(|dim| dim.parse().map_err(|_| npy_error("invalid shape")))
+      .collect::<OperationResult<_>>()?;
+    Ok(NpyHeader { descr, shape })
+  }
+
+  /// Write a version 1.0 header, padded so that the data is aligned to 64 bytes
+  fn write(&self, writer: &mut impl Write) -> io::Result<()> {
+    let shape = match self.shape.as_slice() {
+      [len] => format!("({len},)"),
+      shape => format!(
+        "({})",
+        shape
+          .iter()
+          .map(usize::to_string)
+          .collect::<Vec<_>>()
+          .join(", ")
+      ),
+    };
+    let mut header = format!(
+      "{{'descr': '{}', 'fortran_order': False, 'shape': {shape}, }}",
+      self.descr
+    );
+    let unpadded = NPY_MAGIC.len() + 4 + header.len() + 1;
+    header.push_str(&" ".repeat(unpadded.next_multiple_of(64) - unpadded));
+    header.push('\n');
+    writer.write_all(NPY_MAGIC)?;
+    writer.write_all(&[1, 0])?;
+    writer.write_all(&(header.len() as u16).to_le_bytes())?;
+    writer.write_all(header.as_bytes())
+  }
+
+  /// Number of rows and of values per row of a vectors array
+  fn vectors_shape(&self) -> OperationResult<(usize, usize)> {
+    if self.descr != VECTORS_DESCR {
+      return Err(npy_error(format!(
+        "vectors must have type {VECTORS_DESCR}, not {}",
+        self.descr
+      )));
+    }
+    match self.shape.as_slice() {
+      &[rows, dim] => Ok((rows, dim)),
+      _ => Err(npy_error("vectors must have 2 dimensions")),
+    }
+  }
+}
+
+/// Rows of a vectors array, after its header
+fn read_rows(
+  mut reader: impl Read,
+  rows: usize,
+  dim: usize,
+) -> impl Iterator<Item = OperationResult<Vec<VectorElementType>>> {
+  let mut values = vec![0; dim * 4];
+  (0..rows).map(move |_| {
+    reader.read_exact(&mut values)?;
+    Ok(
+      values
+        .as_chunks::<4>()
+        .0
+        .iter()
+        .map(|value| f32::from_le_bytes(*value))
+        .collect(),
+    )
+  })
+}
+
+/// Ids of a 1-dimensional `<i8` or `<u8` array, after its header
+fn read_ids(
+  mut reader: impl Read,
+  header: &NpyHeader,
+  rows: usize,
+) -> OperationResult<impl Iterator<Item = OperationResult<PointIdType>>> {
+  let signed = match header.descr.as_str() {
+    "<i8" => true,
+    "<u8" => false,
+    descr => return Err(npy_error(format!("ids must be integers, not {descr}"))),
+  };
+  if header.shape != [rows] {
+    return Err(npy_error(format!(
+      "ids must have 1 dimension of {rows} values"
+    )));
+  }
+  Ok((0..rows).map(move |_| {
+    let mut value = [0; 8];
+    reader.read_exact(&mut value)?;
+    if signed && i64::from_le_bytes(value) < 0 {
+      return Err(npy_error("ids must not be negative"));
+    }
+    Ok(u64::from_le_bytes(value).into())
+  }))
+}
+
+/// Points of the vectors and their ids, in batches of `batch_size` points
+fn row_batches(
+  vectors: impl Iterator<Item = OperationResult<Vec<VectorElementType>>>,
+  mut ids: Option<impl Iterator<Item = OperationResult<PointIdType>>>,
+  batch_size: usize,
+) -> impl Iterator<Item = OperationResult<Vec<PointStruct>>> {
+  let points = vectors.map(move |vector| {
+    let id = match &mut ids {
+      Some(ids) => ids.next().transpose()?,
+      None => None,
+    };
+    Ok(PointStruct {
+      id,
+      vector: vector?,
+      payload: None,
+    })
+  });
+  batched(points, batch_size)
+}
+
+/// Import the `f32` vectors of an `.npy` file, the collection assigns the point ids
+pub(super) async fn import_npy(
+  toc: &TableOfContent,
+  collection_name: &str,
+  file_path: &Path,
+  batch_size: usize,
+  stopped: &AtomicBool,
+  progress: impl FnMut(usize, Option<usize>),
+) -> OperationResult<usize> {
+  let mut reader = BufReader::new(File::open(file_path)?);
+  let (rows, dim) = NpyHeader::read(&mut reader)?.vectors_shape()?;
+  // `.npy` files hold a single array
+  let batches = row_batches(
+    read_rows(reader, rows, dim),
+    None::<std::iter::Empty<_>>,
+    batch_size,
+  );
+  import_points(toc, collection_name, batches, Some(rows), stopped, progress).await
+}
+
+/// Import the vectors of an `.npz` archive of arrays, like the ones of `numpy.savez`
+pub(super) async fn import_npz(
+  toc: &TableOfContent,
+  collection_name: &str,
+  file_path: &Path,
+  import: &NpzImport,
+  batch_size: usize,
+  stopped: &AtomicBool,
+  progress: impl FnMut(usize, Option<usize>),
+) -> OperationResult<usize> {
+  // The ids are read alongside the vectors, from a second handle of the archive
+  let mut archive = ZipArchive::new(File::open(file_path)?).map_err(npz_error)?;
+  let mut ids_archive = match &import.ids {
+    Some(_) => Some(ZipArchive::new(File::open(file_path)?).map_err(npz_error)?),
+    None => None,
+  };
+  let vectors_entry = match &import.vectors {
+    Some(name) => archive.by_name(&format!("{name}.npy")),
+    None => archive.by_index(0),
+  };
+  let mut vectors_reader = BufReader::new(vectors_entry.map_err(npz_error)?);
+  let (rows, dim) = NpyHeader::read(&mut vectors_reader)?.vectors_shape()?;
+  let ids = match (&import.ids, &mut ids_archive) {
+    (Some(name), Some(archive)) => {
+      let entry = archive.by_name(&format!("{name}.npy")).map_err(npz_error)?;
+      let mut reader = BufReader::new(entry);
+      let header = NpyHeader::read(&mut reader)?;
+      Some(read_ids(reader, &header, rows)?)
+    }
+    _ => None,
+  };
+  let batches = row_batches(read_rows(vectors_reader, rows, dim), ids, batch_size);
+  import_points(toc, collection_name, batches, Some(rows), stopped, progress).await
+}
+
+/// Writes exported vectors as a 2-dimensional `f32` array, point ids are not stored
+pub struct NpyWriter<W: Write> {
+  writer: BufWriter<W>,
+}
+
+impl<W: Write> NpyWriter<W> {
+  pub fn new(writer: W) -> Self {
+    NpyWriter {
+      writer: BufWriter::new(writer),
+    }
+  }
+
+  pub fn finish(self) -> OperationResult<W> {
+    self
+      .writer
+      .into_inner()
+      .map_err(|err| err.into_error().into())
+  }
+}
+
+impl<W: Write> VectorSink for NpyWriter<W> {
+  fn start(&mut self, count: usize, dim: usize) -> OperationResult<()> {
+    let header = NpyHeader {
+      descr: VECTORS_DESCR.to_string(),
+      shape: vec![count, dim],
+    };
+    Ok(header.write(&mut self.writer)?)
+  }
+
+  fn write(&mut self, _id: PointIdType, vector: &[VectorElementType]) -> OperationResult<()> {
+    for value in vector {
+      self.writer.write_all(&value.to_le_bytes())?;
+    }
+    Ok(())
+  }
+}
+
+/// Writes exported points as an `.npz` archive with a `vectors` array and, if all point ids are
+/// numeric, an `ids` array of `<u8`
+pub struct NpzWriter<W: Write + Seek> {
+  writer: ZipWriter<W>,
+  /// Numeric ids of the written points, `None` once a UUID is written
+  ids: Option<Vec<u64>>,
+}
+
+impl<W: Write + Seek> NpzWriter<W> {
+  pub fn new(writer: W) -> Self {
+    NpzWriter {
+      writer: ZipWriter::new(writer),
+      ids: Some(Vec::new()),
+    }
+  }
+
+  fn options() -> FileOptions {
+    FileOptions::default()
+      .compression_method(CompressionMethod::Stored)
+      .large_file(true)
+  }
+
+  pub fn finish(mut self) -> OperationResult<W> {
+    if let Some(ids) = self.ids.take() {
+      self
+        .writer
+        .start_file(format!("{NPZ_IDS}.npy"), Self::options())
+        .map_err(npz_error)?;
+      let header = NpyHeader {
+        descr: "<u8".to_string(),
+        shape: vec![ids.len()],
+      };
+      header.write(&mut self.writer)?;
+      for id in ids {
+        self.writer.write_all(&id.to_le_bytes())?;
+      }
+    }
+    self.writer.finish().map_err(npz_error)
+  }
+}
+
+impl<W: Write + Seek> VectorSink for NpzWriter<W> {
+  fn start(&mut self, count: usize, dim: usize) -> OperationResult<()> {
+    self
+      .writer
+      .start_file(format!("{NPZ_VECTORS}.npy"), Self::options())
+      .map_err(npz_error)?;
+    let header = NpyHeader {
+      descr: VECTORS_DESCR.to_string(),
+      shape: vec![count, dim],
+    };
+    Ok(header.write(&mut self.writer)?)
+  }
+
+  fn write(&mut self, id: PointIdType, vector: &[VectorElementType]) -> OperationResult<()> {
+    match (&mut self.ids, id) {
+      (Some(ids), PointIdType::NumId(id)) => ids.push(id),
+      _ => self.ids = None,
+    }
+    let bytes: Vec<u8> = vector
+      .iter()
+      .flat_map(|value| value.to_le_bytes())
+      .collect();
+    self.writer.write_all(&bytes)?;
+    Ok(())
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use std::io::Cursor;
+
+  use super::*;
+
+  #[test]
+  fn test_npy_header() {
+    // Header written by numpy.save for an array of shape (3, 2)
+    let mut bytes = NPY_MAGIC.to_vec();
+    let header = "{'descr': '<f4', 'fortran_order': False, 'shape': (3, 2), }";
+    bytes.extend([1, 0, header.len() as u8, 0]);
+    bytes.extend(header.as_bytes());
+    let read = NpyHeader::read(&mut bytes.as_slice()).unwrap();
+    assert_eq!(read.vectors_shape().unwrap(), (3, 2));
+
+    for shape in [vec![3, 2], vec![7]] {
+      let header = NpyHeader {
+        descr: "<u8".to_string(),
+        shape,
+      };
+      let mut bytes = Vec::new();
+      header.write(&mut bytes).unwrap();
+      assert_eq!(bytes.len() % 64, 0);
+      assert_eq!(NpyHeader::read(&mut bytes.as_slice()).unwrap(), header);
+    }
+    // Vectors are not valid ids
+    assert!(read_ids(&[][..], &read, 3).is_err());
+  }
+
+  #[test]
+  fn test_npz_round_trip() {
+    let mut writer = NpzWriter::new(Cursor::new(Vec::new()));
+    writer.start(2, 2).unwrap();
+    writer.write(5.into(), &[1.0, 2.0]).unwrap();
+    writer.write(9.into(), &[3.0, 4.0]).unwrap();
+    let bytes = writer.finish().unwrap();
+
+    let mut archive = ZipArchive::new(bytes).unwrap();
+    let mut vectors = archive.by_index(0).unwrap();
+    assert_eq!(vectors.name(), "vectors.npy");
+    let (rows, dim) = NpyHeader::read(&mut vectors)
+      .unwrap()
+      .vectors_shape()
+      .unwrap();
+    let read: Vec<_> = read_rows(vectors, rows, dim)
+      .collect::<OperationResult<_>>()
+      .unwrap();
+    assert_eq!(read, [[1.0, 2.0], [3.0, 4.0]]);
+    let mut ids = archive.by_name("ids.npy").unwrap();
+    let header = NpyHeader::read(&mut ids).unwrap();
+    let read: Vec<_> = read_ids(ids, &header, 2)
+      .unwrap()
+      .collect::<OperationResult<_>>()
+      .unwrap();
+    assert_eq!(read, [5.into(), 9.into()]);
+  }
+}
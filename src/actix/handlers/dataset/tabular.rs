@@ -0,0 +1,305 @@
+use std::{fs::File, path::Path, str::FromStr, sync::atomic::AtomicBool};
+
+use parquet::file::reader::{FileReader, SerializedFileReader};
+use serde_json::Value;
+
+use super::{batched, import_points};
+use crate::{
+  actix::{model::points::PointStruct, table::toc::TableOfContent},
+  common::{
+    operation_error::{OperationError, OperationResult},
+    point_id::PointIdType,
+  },
+  engine::types::types::Payload,
+};
+
+/// Columns of a CSV or Parquet file which hold the fields of the points
+pub struct ColumnMapping {
+  /// Column of the point ids, unsigned integers or UUIDs. If not set, the next numeric ids of the
+  /// collection are used.
+  pub id: Option<String>,
+  /// A single column with the vector as a JSON array (a list in Parquet), or one numeric column
+  /// per dimension
+  pub vector: Vec<String>,
+  /// Columns stored in the payload under their name
+  pub payload: Vec<String>,
+}
+
+/// Positions of the mapped columns in the rows of a file
+struct ColumnIndices {
+  id: Option<usize>,
+  vector: Vec<usize>,
+  payload: Vec<(String, usize)>,
+}
+
+impl ColumnIndices {
+  fn resolve(mapping: &ColumnMapping, columns: &[String]) -> OperationResult<Self> {
+    if mapping.vector.is_empty() {
+      return Err(OperationError::ValidationError {
+        description: "vector columns are not set".to_string(),
+      });
+    }
+    let index = |name: &str| {
+      columns
+        .iter()
+        .position(|column| column == name)
+        .ok_or_else(|| OperationError::ValidationError {
+          description: format!("column {name} does not exist"),
+        })
+    };
+    Ok(ColumnIndices {
+      id: mapping.id.as_deref().map(index).transpose()?,
+      vector: mapping
+        .vector
+        .iter()
+        .map(|name| index(name))
+        .collect::<OperationResult<_>>()?,
+      payload: mapping
+        .payload
+        .iter()
+        .map(|name| Ok((name.clone(), index(name)?)))
+        .collect::<OperationResult<_>>()?,
+    })
+  }
+
+  /// Point of the `row_number`-th row, counted from 1 in errors
+  fn point(&self, row_number: usize, mut row: Vec<Value>) -> OperationResult<PointStruct> {
+    let row_error = |description: String| OperationError::ValidationError {
+      description: format!("row {row_number}: {description}"),
+    };
+    let id = self
+      .id
+      .map(|index| {
+        match &row[index] {
+          Value::String(id) => PointIdType::from_str(id).ok(),
+          id => serde_json::from_value(id.clone()).ok(),
+        }
+        .ok_or_else(|| row_error(format!("{} is not a valid point id", row[index])))
+      })
+      .transpose()?;
+    let vector: Option<Vec<f32>> = match self.vector.as_slice() {
+      &[index] => match &row[index] {
+        Value::Array(values) => values.iter().map(vector_element).collect(),
+        value => vector_element(value).map(|value| vec![value]),
+      },
+      indices => indices
+        .iter()
+        .map(|&index| vector_element(&row[index]))
+        .collect(),
+    };
+    let vector = vector.ok_or_else(|| row_error("vector values must be numbers".to_string()))?;
+    let payload = (!self.payload.is_empty()).then(|| {
+      Payload(
+        self
+          .payload
+          .iter()
+          .map(|(name, index)| (name.clone(), row[*index].take()))
+          .collect(),
+      )
+    });
+    Ok(PointStruct {
+      id,
+      vector,
+      payload,
+    })
+  }
+}
+
+fn vector_element(value: &Value) -> Option<f32> {
+  value.as_f64().map(|value| value as f32)
+}
+
+/// CSV cells are typed as JSON, like numbers, booleans and arrays, other cells are strings and
+/// empty cells are null
+fn csv_value(cell: &str) -> Value {
+  if cell.is_empty() {
+    return Value::Null;
+  }
+  serde_json::from_str(cell).unwrap_or_else(|_| Value::String(cell.to_string()))
+}
+
+fn csv_error(err: csv::Error) -> OperationError {
+  OperationError::ValidationError {
+    description: format!("Invalid CSV file: {err}"),
+  }
+}
+
+fn parquet_error(err: parquet::errors::ParquetError) -> OperationError {
+  OperationError::ValidationError {
+    description: format!("Invalid Parquet file: {err}"),
+  }
+}
+
+/// Import a CSV file with a header row, reading `batch_size` rows at a time
+pub(super) async fn import_csv(
+  toc: &TableOfContent,
+  collection_name: &str,
+  file_path: &Path,
+  mapping: &ColumnMapping,
+  batch_size: usize,
+  stopped: &AtomicBool,
+  progress: impl FnMut(usize, Option<usize>),
+) -> OperationResult<usize> {
+  let mut reader = csv::Reader::from_path(file_path).map_err(csv_error)?;
+  let columns: Vec<String> = reader
+    .headers()
+    .map_err(csv_error)?
+    .iter()
+    .map(str::to_string)
+    .collect();
+  let indices = ColumnIndices::resolve(mapping, &columns)?;
+  let points = reader.records().enumerate().map(|(row, record)| {
+    let record = record.map_err(csv_error)?;
+    indices.point(row + 1, record.iter().map(csv_value).collect())
+  });
+  import_points(
+    toc,
+    collection_name,
+    batched(points, batch_size),
+    None,
+    stopped,
+    progress,
+  )
+  .await
+}
+
+/// Import a Parquet file, reading `batch_size` rows at a time
+///
+/// Values are converted to JSON according to the schema of the file, lists of numbers can be used
+/// as vectors.
+pub(super) async fn import_parquet(
+  toc: &TableOfContent,
+  collection_name: &str,
+  file_path: &Path,
+  mapping: &ColumnMapping,
+  batch_size: usize,
+  stopped: &AtomicBool,
+  progress: impl FnMut(usize, Option<usize>),
+) -> OperationResult<usize> {
+  let reader = SerializedFileReader::new(File::open(file_path)?).map_err(parquet_error)?;
+  let total = reader.metadata().file_metadata().num_rows() as usize;
+  let mut indices = None;
+  let points = reader
+    .get_row_iter(None)
+    .map_err(parquet_error)?
+    .enumerate()
+    .map(|(row, record)| {
+      let record = record.map_err(parquet_error)?;
+      let (columns, values): (Vec<String>, Vec<Value>) = record
+        .get_column_iter()
+        .map(|(name, field)| (name.clone(), field.to_json_value()))
+        .unzip();
+      // Rows follow the schema of the file, the columns are looked up once
+      let indices = match &mut indices {
+        Some(indices) => indices,
+        None => indices.insert(ColumnIndices::resolve(mapping, &columns)?),
+      };
+      indices.point(row + 1, values)
+    });
+  import_points(
+    toc,
+    collection_name,
+    batched(points, batch_size),
+    Some(total),
+    stopped,
+    progress,
+  )
+  .await
+}
+
+#[cfg(test)]
+mod tests {
+  use std::{io::Write, sync::Arc};
+
+  use serde_json::json;
+  use tempfile::Builder;
+
+  use super::*;
+
+  #[tokio::test]
+  async fn test_import_csv() {
+    let dir = Builder::new().prefix("csv").tempdir().unwrap();
+    let storage_config = serde_json::from_value(json!({
+      "storage_path": dir.path().join("storage"),
+      "snapshots_path": dir.path().join("snapshots"),
+    }))
+    .unwrap();
+    let toc = TableOfContent::new(Arc::new(storage_config)).unwrap();
+    let config = serde_json::from_value(json!({
+      "vector_size": 2,
+      "distance": "Euclidean",
+      "vector_storage_type": "AppendableMemmap",
+    }))
+    .unwrap();
+    toc.create_collection("test", config).await.unwrap();
+
+    let path = dir.path().join("points.csv");
+    let mut file = File::create(&path).unwrap();
+    writeln!(file, "id,x,y,embedding,name,count").unwrap();
+    writeln!(file, "1,1.0,0,\"[1.0, 0.0]\",one,10").unwrap();
+    writeln!(file, "2,2.0,0,\"[2.0, 0.0]\",,20").unwrap();
+    writeln!(file, "3,3.0,0,\"[3.0, 0.0]\",three,30").unwrap();
+    drop(file);
+
+    let stopped = AtomicBool::new(false);
+    let mapping = ColumnMapping {
+      id: Some("id".to_string()),
+      vector: vec!["x".to_string(), "y".to_string()],
+      payload: vec!["name".to_string(), "count".to_string()],
+    };
+    let mut batches = Vec::new();
+    let ingested = import_csv(&toc, "test", &path, &mapping, 2, &stopped, |ingested, _| {
+      batches.push(ingested)
+    })
+    .await
+    .unwrap();
+    assert_eq!(ingested, 3);
+    assert_eq!(batches, [2, 3]);
+    let found = toc.search("test", &[2.9, 0.0], 1).await.unwrap();
+    assert_eq!(found[0].id, 3.into());
+    assert_eq!(
+      Value::Object(found[0].payload.0.clone()),
+      json!({"name": "three", "count": 30})
+    );
+
+    // A single column holds the vector as a JSON array
+    let mapping = ColumnMapping {
+      id: Some("name".to_string()),
+      vector: vec!["embedding".to_string()],
+      payload: vec![],
+    };
+    let err = import_csv(&toc, "test", &path, &mapping, 2, &stopped, |_, _| ())
+      .await
+      .unwrap_err();
+    assert!(err
+      .to_string()
+      .contains("row 1: \"one\" is not a valid point id"));
+    let mapping = ColumnMapping {
+      id: None,
+      vector: vec!["embedding".to_string()],
+      payload: vec!["missing".to_string()],
+    };
+    let err = import_csv(&toc, "test", &path, &mapping, 2, &stopped, |_, _| ())
+      .await
+      .unwrap_err();
+    assert!(err.to_string().contains("column missing does not exist"));
+    let mapping = ColumnMapping {
+      payload: vec![],
+      ..mapping
+    };
+    let ingested = import_csv(&toc, "test", &path, &mapping, 2, &stopped, |_, _| ())
+      .await
+      .unwrap();
+    assert_eq!(ingested, 3);
+  }
+
+  #[test]
+  fn test_csv_value() {
+    assert_eq!(csv_value(""), Value::Null);
+    assert_eq!(csv_value("12"), json!(12));
+    assert_eq!(csv_value("true"), json!(true));
+    assert_eq!(csv_value("[1, 2.5]"), json!([1, 2.5]));
+    assert_eq!(csv_value("0123"), json!("0123"));
+    assert_eq!(csv_value("text"), json!("text"));
+  }
+}
@@ -0,0 +1,252 @@
+use std::{
+  fs::File,
+  io::{self, BufReader, BufWriter, Read, Seek, SeekFrom, Write},
+  path::Path,
+  sync::atomic::AtomicBool,
+};
+
+use super::{batched, import_points};
+use crate::{
+  actix::{
+    model::points::PointStruct,
+    table::{collections::VectorSink, toc::TableOfContent},
+  },
+  common::{
+    operation_error::{OperationError, OperationResult},
+    point_id::PointIdType,
+  },
+  engine::types::types::VectorElementType,
+};
+
+/// TEXMEX vector files, like the SIFT and GIST benchmarks
+///
+/// Every vector is stored as its little endian `i32` dimension followed by its values.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TexmexFormat {
+  /// `f32` values
+  Fvecs,
+  /// `i32` values
+  Ivecs,
+  /// `u8` values
+  Bvecs,
+}
+
+impl TexmexFormat {
+  fn element_size(self) -> usize {
+    match self {
+      TexmexFormat::Fvecs | TexmexFormat::Ivecs => 4,
+      TexmexFormat::Bvecs => 1,
+    }
+  }
+
+  fn decode(self, bytes: &[u8]) -> Vec<VectorElementType> {
+    match self {
+      TexmexFormat::Fvecs => bytes
+        .as_chunks::<4>()
+        .0
+        .iter()
+        .map(|value| f32::from_le_bytes(*value))
+        .collect(),
+      TexmexFormat::Ivecs => bytes
+        .as_chunks::<4>()
+        .0
+        .iter()
+        .map(|value| i32::from_le_bytes(*value) as f32)
+        .collect(),
+      TexmexFormat::Bvecs => bytes.iter().map(|&value| value as f32).collect(),
+    }
+  }
+
+  /// Values of integer formats are rounded, `bvecs` values are clamped to `0..=255`
+  fn encode(self, vector: &[VectorElementType], buffer: &mut Vec<u8>) {
+    match self {
+      TexmexFormat::Fvecs => buffer.extend(vector.iter().flat_map(|value| value.to_le_bytes())),
+      TexmexFormat::Ivecs => buffer.extend(
+        vector
+          .iter()
+          .flat_map(|value| (value.round() as i32).to_le_bytes()),
+      ),
+      TexmexFormat::Bvecs => buffer.extend(
+        vector
+          .iter()
+          .map(|value| value.round().clamp(0.0, 255.0) as u8),
+      ),
+    }
+  }
+}
+
+fn texmex_error(description: impl std::fmt::Display) -> OperationError {
+  OperationError::ValidationError {
+    description: format!("Invalid TEXMEX file: {description}"),
+  }
+}
+
+/// Dimension of the next vector, `None` at the end of the file
+fn read_dim(reader: &mut impl Read) -> OperationResult<Option<usize>> {
+  let mut bytes = [0; 4];
+  let mut filled = 0;
+  while filled < bytes.len() {
+    match reader.read(&mut bytes[filled..])? {
+      0 if filled == 0 => return Ok(None),
+      0 => return Err(texmex_error("the file ends inside a vector")),
+      read => filled += read,
+    }
+  }
+  let dim = i32::from_le_bytes(bytes);
+  if dim <= 0 {
+    return Err(texmex_error(format!("invalid dimension {dim}")));
+  }
+  Ok(Some(dim as usize))
+}
+
+/// Vectors of a TEXMEX file, which all have the dimension of the first one. Reading stops at the
+/// first error.
+fn read_vectors(
+  mut reader: impl Read,
+  format: TexmexFormat,
+  dim: usize,
+) -> impl Iterator<Item = OperationResult<Vec<VectorElementType>>> {
+  let mut values = vec![0; dim * format.element_size()];
+  let mut failed = false;
+  std::iter::from_fn(move || {
+    if failed {
+      return None;
+    }
+    let vector = match read_dim(&mut reader) {
+      Ok(None) => return None,
+      Ok(Some(vector_dim)) if vector_dim != dim => Err(texmex_error(format!(
+        "vector of dimension {vector_dim} after vectors of dimension {dim}"
+      ))),
+      Ok(Some(_)) => match reader.read_exact(&mut values) {
+        Ok(()) => Ok(format.decode(&values)),
+        Err(err) if err.kind() == io::ErrorKind::UnexpectedEof => {
+          Err(texmex_error("the file ends inside a vector"))
+        }
+        Err(err) => Err(err.into()),
+      },
+      Err(err) => Err(err),
+    };
+    failed = vector.is_err();
+    Some(vector)
+  })
+}
+
+/// Import the vectors of a TEXMEX file, the collection assigns the point ids
+pub(super) async fn import_texmex(
+  toc: &TableOfContent,
+  collection_name: &str,
+  file_path: &Path,
+  format: TexmexFormat,
+  batch_size: usize,
+  stopped: &AtomicBool,
+  progress: impl FnMut(usize, Option<usize>),
+) -> OperationResult<usize> {
+  let mut file = File::open(file_path)?;
+  let file_size = file.metadata()?.len() as usize;
+  let Some(dim) = read_dim(&mut file)? else {
+    return Ok(0);
+  };
+  file.seek(SeekFrom::Start(0))?;
+  let total = file_size / (4 + dim * format.element_size());
+  let points = read_vectors(BufReader::new(file), format, dim).map(|vector| {
+    Ok(PointStruct {
+      id: None,
+      vector: vector?,
+      payload: None,
+    })
+  });
+  import_points(
+    toc,
+    collection_name,
+    batched(points, batch_size),
+    Some(total),
+    stopped,
+    progress,
+  )
+  .await
+}
+
+/// Writes exported vectors in a TEXMEX format, point ids are not stored
+pub struct TexmexWriter<W: Write> {
+  writer: BufWriter<W>,
+  format: TexmexFormat,
+  buffer: Vec<u8>,
+}
+
+impl<W: Write> TexmexWriter<W> {
+  pub fn new(writer: W, format: TexmexFormat) -> Self {
+    TexmexWriter {
+      writer: BufWriter::new(writer),
+      format,
+      buffer: Vec::new(),
+    }
+  }
+
+  pub fn finish(self) -> OperationResult<W> {
+    self
+      .writer
+      .into_inner()
+      .map_err(|err| err.into_error().into())
+  }
+}
+
+impl<W: Write> VectorSink for TexmexWriter<W> {
+  fn start(&mut self, _count: usize, _dim: usize) -> OperationResult<()> {
+    Ok(())
+  }
+
+  fn write(&mut self, _id: PointIdType, vector: &[VectorElementType]) -> OperationResult<()> {
+    self.buffer.clear();
+    self.buffer.extend((vector.len() as i32).to_le_bytes());
+    self.format.encode(vector, &mut self.buffer);
+    self.writer.write_all(&self.buffer)?;
+    Ok(())
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_texmex_round_trip() {
+    let vectors = [vec![1.0, -2.5, 300.0], vec![0.4, 7.0, 255.0]];
+    let expected = [
+      (TexmexFormat::Fvecs, vectors.to_vec()),
+      (
+        TexmexFormat::Ivecs,
+        vec![vec![1.0, -3.0, 300.0], vec![0.0, 7.0, 255.0]],
+      ),
+      (
+        TexmexFormat::Bvecs,
+        vec![vec![1.0, 0.0, 255.0], vec![0.0, 7.0, 255.0]],
+      ),
+    ];
+    for (format, expected) in expected {
+      let mut writer = TexmexWriter::new(Vec::new(), format);
+      writer.start(vectors.len(), 3).unwrap();
+      for (id, vector) in vectors.iter().enumerate() {
+        writer.write((id as u64).into(), vector).unwrap();
+      }
+      let bytes = writer.finish().unwrap();
+      assert_eq!(bytes.len(), 2 * (4 + 3 * format.element_size()));
+      let read: Vec<_> = read_vectors(bytes.as_slice(), format, 3)
+        .collect::<OperationResult<_>>()
+        .unwrap();
+      assert_eq!(read, expected, "{format:?}");
+    }
+
+    // Vectors must have the same dimension and be complete
+    let mut bytes = TexmexWriter::new(Vec::new(), TexmexFormat::Fvecs);
+    bytes.write(0.into(), &[1.0, 2.0]).unwrap();
+    bytes.write(1.into(), &[1.0]).unwrap();
+    let bytes = bytes.finish().unwrap();
+    let read: Vec<_> = read_vectors(bytes.as_slice(), TexmexFormat::Fvecs, 2).collect();
+    assert_eq!(read.len(), 2);
+    assert!(read[0].is_ok());
+    assert!(read[1].is_err());
+    let read: Vec<_> = read_vectors(&bytes[..6], TexmexFormat::Fvecs, 2).collect();
+    assert_eq!(read.len(), 1);
+    assert!(read[0].is_err());
+  }
+}
@@ -0,0 +1,45 @@
+use std::time::Instant;
+
+use actix_web::{delete, get, web::Data, web::Path, HttpResponse};
+use uuid::Uuid;
+
+use crate::actix::{helpers::process_response, table::toc::TableOfContent};
+
+#[utoipa::path(
+  get,
+  path = "/jobs",
+  responses(
+    (status = 200, description = "Running and recently finished import jobs, the most recent first")
+  )
+)]
+#[get("/jobs")]
+pub async fn list_jobs(toc: Data<TableOfContent>) -> HttpResponse {
+  let timing = Instant::now();
+  process_response(Ok(toc.jobs().list()), timing)
+}
+
+#[utoipa::path(
+  get,
+  path = "/jobs/{id}",
+  responses(
+    (status = 200, description = "Status, progress, throughput and error of the import job")
+  )
+)]
+#[get("/jobs/{id}")]
+pub async fn get_job(toc: Data<TableOfContent>, id: Path<Uuid>) -> HttpResponse {
+  let timing = Instant::now();
+  process_response(toc.jobs().get(*id), timing)
+}
+
+#[utoipa::path(
+  delete,
+  path = "/jobs/{id}",
+  responses(
+    (status = 200, description = "The job stops after its current batch, the points imported so far are kept")
+  )
+)]
+#[delete("/jobs/{id}")]
+pub async fn cancel_job(toc: Data<TableOfContent>, id: Path<Uuid>) -> HttpResponse {
+  let timing = Instant::now();
+  process_response(toc.jobs().cancel(*id), timing)
+}
@@ -1,2 +1,6 @@
+pub mod bench;
+pub mod collection;
 pub mod dataset;
+pub mod jobs;
+pub mod service;
 pub mod vector;
@@ -0,0 +1,124 @@
+use std::time::{Duration, Instant};
+
+use actix_web::{get, http::StatusCode, web::Data, HttpResponse};
+use serde_json::json;
+
+use crate::{
+  actix::{
+    helpers::{error_response, process_response, status_response},
+    metrics::Metrics,
+    table::toc::TableOfContent,
+  },
+  utils::mem::Mem,
+};
+
+/// Time the collections may stay locked before the liveness probe fails
+const LIVENESS_TIMEOUT: Duration = Duration::from_secs(10);
+
+#[utoipa::path(
+  get,
+  path = "/metrics",
+  responses(
+    (status = 200, description = "Metrics of the service in the Prometheus text format")
+  )
+)]
+#[get("/metrics")]
+pub async fn metrics(metrics: Data<Metrics>, toc: Data<TableOfContent>) -> HttpResponse {
+  let timing = Instant::now();
+  match metrics.render(&toc).await {
+    Ok(text) => HttpResponse::Ok()
+      .content_type("text/plain; version=0.0.4")
+      .body(text),
+    Err(err) => process_response::<()>(Err(err), timing),
+  }
+}
+
+#[utoipa::path(
+  get,
+  path = "/telemetry",
+  responses(
+    (status = 200, description = "Info of all collections and the memory of the process")
+  )
+)]
+#[get("/telemetry")]
+pub async fn telemetry(toc: Data<TableOfContent>) -> HttpResponse {
+  let timing = Instant::now();
+  let result = toc.collections_info().await.map(|collections| {
+    let mem = Mem::new();
+    json!({
+      "version": env!("CARGO_PKG_VERSION"),
+      "collections": collections,
+      "memory": {
+        "total_bytes": mem.total_memory_bytes(),
+        "available_bytes": mem.available_memory_bytes(),
+      },
+    })
+  });
+  process_response(result, timing)
+}
+
+#[utoipa::path(
+  get,
+  path = "/healthz",
+  responses(
+    (status = 200, description = "The process is running")
+  )
+)]
+#[get("/healthz")]
+pub async fn healthz() -> HttpResponse {
+  let timing = Instant::now();
+  let result = json!({
+    "title": env!("CARGO_PKG_NAME"),
+    "version": env!("CARGO_PKG_VERSION"),
+  });
+  process_response(Ok(result), timing)
+}
+
+#[utoipa::path(
+  get,
+  path = "/readyz",
+  responses(
+    (status = 200, description = "All collections are loaded, or failed to load and are listed in `failed`"),
+    (status = 503, description = "Collections are loading, with the progress of each collection")
+  )
+)]
+#[get("/readyz")]
+pub async fn readyz(toc: Data<TableOfContent>) -> HttpResponse {
+  let timing = Instant::now();
+  let readiness = toc.readiness();
+  if readiness.ready {
+    process_response(Ok(readiness), timing)
+  } else {
+    status_response(
+      StatusCode::SERVICE_UNAVAILABLE,
+      "not_ready",
+      readiness,
+      timing,
+    )
+  }
+}
+
+#[utoipa::path(
+  get,
+  path = "/livez",
+  responses(
+    (status = 200, description = "The service is able to serve requests"),
+    (status = 503, description = "The collections are locked up")
+  )
+)]
+#[get("/livez")]
+pub async fn livez(toc: Data<TableOfContent>) -> HttpResponse {
+  let timing = Instant::now();
+  if toc.is_responsive(LIVENESS_TIMEOUT).await {
+    process_response(Ok(true), timing)
+  } else {
+    error_response(
+      StatusCode::SERVICE_UNAVAILABLE,
+      format!(
+        "Collections stayed locked for {} seconds",
+        LIVENESS_TIMEOUT.as_secs()
+      ),
+      timing.elapsed().as_secs_f64(),
+    )
+  }
+}
@@ -1,15 +1,20 @@
 use std::{
     borrow::BorrowMut,
-    sync::{Arc, Mutex},
+    sync::{Arc, Mutex, MutexGuard},
+    time::Instant,
 };
 
-use actix_web::{get, post, web::Data, HttpResponse, Responder};
+use actix_web::{get, post, web::Data, Responder};
 use actix_web_validator::Json;
 use hnsw_rs::hnsw::Distance;
-use serde_json::json;
 
 use crate::{
-    actix::model::vector::{AddVector, SearchVector},
+    actix::{
+        helpers::process_response,
+        model::vector::{AddVector, SearchVector},
+        table::toc::TableOfContent,
+    },
+    common::operation_error::{OperationError, OperationResult},
     engine::{
         index::hnsw::index::HNSWIndex,
         types::{types::VectorElementType, vector::VectorRef},
@@ -30,17 +35,17 @@ use crate::{
 #[post("/vector")]
 pub async fn add_vector<'a>(
     data: Data<Arc<Mutex<HNSWIndex<'a>>>>,
+    toc: Data<TableOfContent>,
     operation: Json<AddVector>,
 ) -> impl Responder {
+    let timing = Instant::now();
     let vector: &[VectorElementType] = operation.vectors.as_slice();
     let payload = operation.payload.clone();
-    match data.lock().unwrap().add(vector, payload) {
-        Ok(()) => "Vector added successfully",
-        Err(e) => {
-            log::error!("Error adding vector: {}", e);
-            "Error adding vector"
-        }
-    }
+    let result = toc
+        .check_memory()
+        .and_then(|()| lock_index(&data))
+        .and_then(|mut index| index.add(vector, payload));
+    process_response(result.map(|()| true), timing)
 }
 
 #[utoipa::path(
@@ -55,19 +60,17 @@ pub async fn search_vector<'a>(
     data: Data<Arc<Mutex<HNSWIndex<'a>>>>,
     operation: Json<SearchVector>,
 ) -> impl Responder {
+    let timing = Instant::now();
     let vector: &[VectorElementType] = operation.vector.as_slice();
     let top_k = operation.k;
+    let result = lock_index(&data).and_then(|index| index.search(vector, top_k));
+    process_response(result, timing)
+}
 
-    match data.lock().unwrap().search(vector, top_k) {
-        Ok(result) => {
-            let response = json!({
-                "result": result,
-            });
-            HttpResponse::Ok().json(response)
-        }
-        Err(e) => {
-            log::error!("Error searching vector: {}", e);
-            HttpResponse::InternalServerError().finish()
-        }
-    }
+/// A panic during an update poisons the lock, the index may be inconsistent from then on
+fn lock_index<'a, 'b>(
+    data: &'a Mutex<HNSWIndex<'b>>,
+) -> OperationResult<MutexGuard<'a, HNSWIndex<'b>>> {
+    data.lock()
+        .map_err(|_| OperationError::service_error("Index lock is poisoned"))
 }
@@ -0,0 +1,159 @@
+use std::{fmt::Display, time::Instant};
+
+use actix_web::{http::StatusCode, HttpResponse, ResponseError};
+use serde::Serialize;
+
+use crate::common::operation_error::{OperationError, OperationResult};
+
+/// Body of responses with a result
+#[derive(Serialize)]
+struct ApiSuccess<T: Serialize> {
+    status: &'static str,
+    result: T,
+    /// Time spent to process the request, in seconds
+    time: f64,
+}
+
+/// Body of failed responses
+#[derive(Serialize)]
+struct ApiError {
+    status: &'static str,
+    error: String,
+    time: f64,
+}
+
+/// `{"status": "ok", "result": ..., "time": ...}` for a result, or the error envelope with the
+/// status code of the error
+pub fn process_response<T: Serialize>(result: OperationResult<T>, timing: Instant) -> HttpResponse {
+    match result {
+        Ok(result) => status_response(StatusCode::OK, "ok", result, timing),
+        Err(err) => {
+            let time = timing.elapsed().as_secs_f64();
+            let status = err.status_code();
+            if status.is_server_error() {
+                log::error!("Request failed: {err:?}");
+            }
+            error_response(status, err, time)
+        }
+    }
+}
+
+/// `{"status": <status>, "result": ..., "time": ...}` with the given status code, for results
+/// which are not errors but are not ok either
+pub fn status_response<T: Serialize>(
+    code: StatusCode,
+    status: &'static str,
+    result: T,
+    timing: Instant,
+) -> HttpResponse {
+    HttpResponse::build(code).json(ApiSuccess {
+        status,
+        result,
+        time: timing.elapsed().as_secs_f64(),
+    })
+}
+
+/// `{"status": "error", "error": ..., "time": ...}` with the given status code
+pub fn error_response(status: StatusCode, error: impl Display, time: f64) -> HttpResponse {
+    HttpResponse::build(status).json(ApiError {
+        status: "error",
+        error: error.to_string(),
+        time,
+    })
+}
+
+impl ResponseError for OperationError {
+    fn status_code(&self) -> StatusCode {
+        match *self {
+            OperationError::WrongVector { .. } => StatusCode::BAD_REQUEST,
+            OperationError::VectorNameNotExists { .. } => StatusCode::NOT_FOUND,
+            OperationError::MissedVectorName { .. } => StatusCode::BAD_REQUEST,
+            OperationError::PointIdError { .. } => StatusCode::NOT_FOUND,
+            OperationError::TypeError { .. } => StatusCode::BAD_REQUEST,
+            OperationError::TypeInferenceError { .. } => StatusCode::BAD_REQUEST,
+            OperationError::ServiceError { .. } => StatusCode::INTERNAL_SERVER_ERROR,
+            OperationError::InconsistentStorage { .. } => StatusCode::INTERNAL_SERVER_ERROR,
+            OperationError::OutOfMemory { .. } => StatusCode::SERVICE_UNAVAILABLE,
+            OperationError::Cancelled { .. } => StatusCode::SERVICE_UNAVAILABLE,
+            OperationError::ValidationError { .. } => StatusCode::BAD_REQUEST,
+            OperationError::NotFound { .. } => StatusCode::NOT_FOUND,
+            OperationError::Unavailable { .. } => StatusCode::SERVICE_UNAVAILABLE,
+            OperationError::WrongSparse => StatusCode::BAD_REQUEST,
+        }
+    }
+    /// Errors returned without timing, handlers use [`process_response`] to report the time spent
+    fn error_response(&self) -> HttpResponse {
+        error_response(self.status_code(), self, 0.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use actix_web::body::to_bytes;
+    use serde_json::Value;
+
+    use super::*;
+
+    async fn response_json(response: HttpResponse) -> (StatusCode, Value) {
+        let status = response.status();
+        let body = to_bytes(response.into_body()).await.unwrap();
+        (status, serde_json::from_slice(&body).unwrap())
+    }
+
+    #[actix_web::test]
+    async fn test_response_envelope() {
+        let (status, body) = response_json(process_response(Ok(vec![1, 2]), Instant::now())).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["status"], "ok");
+        assert_eq!(body["result"], serde_json::json!([1, 2]));
+        assert!(body["time"].is_f64());
+
+        let errors = [
+            (
+                OperationError::WrongVector {
+                    expected_dim: 3,
+                    received_dim: 2,
+                },
+                StatusCode::BAD_REQUEST,
+            ),
+            (
+                OperationError::ValidationError {
+                    description: "k must be positive".to_string(),
+                },
+                StatusCode::BAD_REQUEST,
+            ),
+            (
+                OperationError::PointIdError {
+                    missed_point_id: 7.into(),
+                },
+                StatusCode::NOT_FOUND,
+            ),
+            (
+                OperationError::OutOfMemory {
+                    description: "no memory".to_string(),
+                    free: 0,
+                },
+                StatusCode::SERVICE_UNAVAILABLE,
+            ),
+            (
+                OperationError::Unavailable {
+                    description: "collection is still loading".to_string(),
+                },
+                StatusCode::SERVICE_UNAVAILABLE,
+            ),
+            (
+                OperationError::service_error("disk failure"),
+                StatusCode::INTERNAL_SERVER_ERROR,
+            ),
+        ];
+        for (error, expected_status) in errors {
+            let message = error.to_string();
+            let result: OperationResult<()> = Err(error);
+            let (status, body) = response_json(process_response(result, Instant::now())).await;
+            assert_eq!(status, expected_status);
+            assert_eq!(body["status"], "error");
+            assert_eq!(body["error"], message);
+            assert!(body["time"].is_f64());
+        }
+    }
+}
@@ -0,0 +1,344 @@
+use std::time::Instant;
+
+use actix_web::{
+    body::MessageBody,
+    dev::{ServiceRequest, ServiceResponse},
+    middleware::Next,
+    web::Data,
+    Error,
+};
+use prometheus::{
+    proto::MetricFamily, Encoder, HistogramOpts, HistogramVec, IntCounterVec, IntGauge,
+    IntGaugeVec, Opts, Registry, TextEncoder,
+};
+
+use crate::{
+    actix::table::{collections::CollectionMetrics, toc::TableOfContent},
+    common::operation_error::{OperationError, OperationResult},
+    engine::segments::optimizer::SegmentInfo,
+    utils::mem::Mem,
+};
+
+/// Route label of requests which did not match any route
+const UNKNOWN_ROUTE: &str = "unknown";
+
+/// Prometheus metrics of the service, rendered by the `/metrics` endpoint
+///
+/// Request metrics are recorded by [`metrics_middleware`], collection and memory metrics are
+/// read when the metrics are scraped.
+pub struct Metrics {
+    registry: Registry,
+    requests: IntCounterVec,
+    request_duration: HistogramVec,
+}
+
+impl Metrics {
+    #[allow(clippy::new_without_default)]
+    pub fn new() -> Self {
+        let registry = Registry::new();
+        let requests = IntCounterVec::new(
+            Opts::new("quantixar_rest_requests_total", "Number of REST requests"),
+            &["method", "route", "status"],
+        )
+        .expect("metric options are valid");
+        let request_duration = HistogramVec::new(
+            HistogramOpts::new(
+                "quantixar_rest_request_duration_seconds",
+                "Time spent to process REST requests",
+            ),
+            &["method", "route"],
+        )
+        .expect("metric options are valid");
+        registry
+            .register(Box::new(requests.clone()))
+            .expect("metric is registered once");
+        registry
+            .register(Box::new(request_duration.clone()))
+            .expect("metric is registered once");
+        Metrics {
+            registry,
+            requests,
+            request_duration,
+        }
+    }
+
+    fn observe_request(&self, method: &str, route: &str, status: u16, seconds: f64) {
+        self.requests
+            .with_label_values(&[method, route, &status.to_string()])
+            .inc();
+        self.request_duration
+            .with_label_values(&[method, route])
+            .observe(seconds);
+    }
+
+    /// All metrics in the Prometheus text format
+    pub async fn render(&self, toc: &TableOfContent) -> OperationResult<String> {
+        let mut families = self.registry.gather();
+        families.extend(collection_families(&toc.collections_metrics().await?)?);
+        families.extend(memory_families(&Mem::new())?);
+
+        let mut buffer = Vec::new();
+        TextEncoder::new()
+            .encode(&families, &mut buffer)
+            .map_err(metrics_error)?;
+        String::from_utf8(buffer).map_err(metrics_error)
+    }
+}
+
+fn metrics_error(err: impl std::fmt::Display) -> OperationError {
+    OperationError::service_error(format!("Failed to render metrics: {err}"))
+}
+
+fn int_gauge_vec(
+    registry: &Registry,
+    name: &str,
+    help: &str,
+    labels: &[&str],
+) -> OperationResult<IntGaugeVec> {
+    let gauge = IntGaugeVec::new(Opts::new(name, help), labels).map_err(metrics_error)?;
+    registry
+        .register(Box::new(gauge.clone()))
+        .map_err(metrics_error)?;
+    Ok(gauge)
+}
+
+/// Counters, vector counts, HNSW layers and RocksDB statistics of the collections
+fn collection_families(
+    collections: &[(String, CollectionMetrics)],
+) -> OperationResult<Vec<MetricFamily>> {
+    let registry = Registry::new();
+    let searches = IntCounterVec::new(
+        Opts::new(
+            "quantixar_collection_searches_total",
+            "Searches and recommendations served since the collection was loaded",
+        ),
+        &["collection"],
+    )
+    .map_err(metrics_error)?;
+    registry
+        .register(Box::new(searches.clone()))
+        .map_err(metrics_error)?;
+    let inserts = IntCounterVec::new(
+        Opts::new(
+            "quantixar_collection_inserted_points_total",
+            "Points upserted since the collection was loaded",
+        ),
+        &["collection"],
+    )
+    .map_err(metrics_error)?;
+    registry
+        .register(Box::new(inserts.clone()))
+        .map_err(metrics_error)?;
+    let points = int_gauge_vec(
+        &registry,
+        "quantixar_collection_points",
+        "Number of live points",
+        &["collection"],
+    )?;
+    let segments = int_gauge_vec(
+        &registry,
+        "quantixar_collection_segments",
+        "Number of segments",
+        &["collection"],
+    )?;
+    let vectors = int_gauge_vec(
+        &registry,
+        "quantixar_collection_vectors",
+        "Number of stored vectors, including deleted ones",
+        &["collection"],
+    )?;
+    let deleted = int_gauge_vec(
+        &registry,
+        "quantixar_collection_deleted_vectors",
+        "Number of deleted vectors which are not vacuumed yet",
+        &["collection"],
+    )?;
+    let layers = int_gauge_vec(
+        &registry,
+        "quantixar_hnsw_layer_points",
+        "Number of points per HNSW layer of the indexed segments",
+        &["collection", "layer"],
+    )?;
+    let rocksdb_keys = int_gauge_vec(
+        &registry,
+        "quantixar_rocksdb_estimated_keys",
+        "Estimated number of keys in the RocksDB vector columns",
+        &["collection"],
+    )?;
+    let rocksdb_sst = int_gauge_vec(
+        &registry,
+        "quantixar_rocksdb_sst_files_bytes",
+        "Size of the SST files of the RocksDB vector columns",
+        &["collection"],
+    )?;
+    let rocksdb_memtable = int_gauge_vec(
+        &registry,
+        "quantixar_rocksdb_memtable_bytes",
+        "Size of the memtables of the RocksDB vector columns",
+        &["collection"],
+    )?;
+
+    for (name, metrics) in collections {
+        let labels = [name.as_str()];
+        searches.with_label_values(&labels).inc_by(metrics.searches);
+        inserts
+            .with_label_values(&labels)
+            .inc_by(metrics.inserted_points);
+        let sum = |count: fn(&SegmentInfo) -> usize| -> i64 {
+            metrics.segments.iter().map(count).sum::<usize>() as i64
+        };
+        points
+            .with_label_values(&labels)
+            .set(sum(|segment| segment.points_count));
+        segments
+            .with_label_values(&labels)
+            .set(metrics.segments.len() as i64);
+        vectors
+            .with_label_values(&labels)
+            .set(sum(|segment| segment.total_vector_count));
+        deleted
+            .with_label_values(&labels)
+            .set(sum(|segment| segment.deleted_vector_count));
+        for (layer, count) in metrics.index_layers.iter().enumerate() {
+            layers
+                .with_label_values(&[name, &layer.to_string()])
+                .set(*count as i64);
+        }
+        rocksdb_keys
+            .with_label_values(&labels)
+            .set(metrics.rocksdb.estimated_keys as i64);
+        rocksdb_sst
+            .with_label_values(&labels)
+            .set(metrics.rocksdb.sst_files_bytes as i64);
+        rocksdb_memtable
+            .with_label_values(&labels)
+            .set(metrics.rocksdb.memtable_bytes as i64);
+    }
+    Ok(registry.gather())
+}
+
+/// Memory of the process, limited by its cgroup if there is one
+fn memory_families(mem: &Mem) -> OperationResult<Vec<MetricFamily>> {
+    let registry = Registry::new();
+    let total = IntGauge::new(
+        "quantixar_memory_total_bytes",
+        "Memory available to the process in total",
+    )
+    .map_err(metrics_error)?;
+    let available = IntGauge::new(
+        "quantixar_memory_available_bytes",
+        "Memory which is still available to the process",
+    )
+    .map_err(metrics_error)?;
+    total.set(mem.total_memory_bytes() as i64);
+    available.set(mem.available_memory_bytes() as i64);
+    registry.register(Box::new(total)).map_err(metrics_error)?;
+    registry
+        .register(Box::new(available))
+        .map_err(metrics_error)?;
+    Ok(registry.gather())
+}
+
+/// Count requests and their duration per route, if [`Metrics`] are registered as app data
+pub async fn metrics_middleware(
+    req: ServiceRequest,
+    next: Next<impl MessageBody>,
+) -> Result<ServiceResponse<impl MessageBody>, Error> {
+    let Some(metrics) = req.app_data::<Data<Metrics>>().cloned() else {
+        return next.call(req).await;
+    };
+    let timing = Instant::now();
+    let method = req.method().to_string();
+    // Route patterns keep the label set small, unlike paths with collection names and ids
+    let route = req
+        .match_pattern()
+        .unwrap_or_else(|| UNKNOWN_ROUTE.to_string());
+    let response = next.call(req).await;
+    let status = match &response {
+        Ok(response) => response.status(),
+        Err(err) => err.as_response_error().status_code(),
+    };
+    metrics.observe_request(
+        &method,
+        &route,
+        status.as_u16(),
+        timing.elapsed().as_secs_f64(),
+    );
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use actix_web::{
+        get,
+        middleware::from_fn,
+        test::{call_service, init_service, TestRequest},
+        App, HttpResponse,
+    };
+    use serde_json::json;
+    use tempfile::Builder;
+
+    use super::*;
+    use crate::actix::model::points::PointStruct;
+
+    #[get("/collections/{collection_name}")]
+    async fn collection() -> HttpResponse {
+        HttpResponse::Ok().finish()
+    }
+
+    #[actix_web::test]
+    async fn test_metrics() {
+        let dir = Builder::new().prefix("metrics").tempdir().unwrap();
+        let storage_config = serde_json::from_value(json!({
+            "storage_path": dir.path().join("storage"),
+            "snapshots_path": dir.path().join("snapshots"),
+        }))
+        .unwrap();
+        let toc = TableOfContent::new(std::sync::Arc::new(storage_config)).unwrap();
+        let config = serde_json::from_value(json!({
+            "vector_size": 2,
+            "distance": "Euclidean",
+            "vector_storage_type": "AppendableMemmap",
+        }))
+        .unwrap();
+        toc.create_collection("test", config).await.unwrap();
+        let points = (0..3)
+            .map(|id| PointStruct {
+                id: Some(id.into()),
+                vector: vec![id as f32, 0.0],
+                payload: None,
+            })
+            .collect();
+        toc.upsert_points("test", points).await.unwrap();
+        toc.delete_points("test", &[0.into()]).await.unwrap();
+        toc.search("test", &[1.0, 0.0], 1).await.unwrap();
+
+        let metrics = Data::new(Metrics::new());
+        let app = init_service(
+            App::new()
+                .app_data(metrics.clone())
+                .wrap(from_fn(metrics_middleware))
+                .service(collection),
+        )
+        .await;
+        for path in ["/collections/a", "/collections/b", "/missing"] {
+            call_service(&app, TestRequest::get().uri(path).to_request()).await;
+        }
+        let text = metrics.render(&toc).await.unwrap();
+
+        let expected = [
+            r#"quantixar_rest_requests_total{method="GET",route="/collections/{collection_name}",status="200"} 2"#,
+            r#"quantixar_rest_requests_total{method="GET",route="unknown",status="404"} 1"#,
+            r#"quantixar_rest_request_duration_seconds_count{method="GET",route="/collections/{collection_name}"} 2"#,
+            r#"quantixar_collection_searches_total{collection="test"} 1"#,
+            r#"quantixar_collection_inserted_points_total{collection="test"} 3"#,
+            r#"quantixar_collection_points{collection="test"} 2"#,
+            r#"quantixar_collection_vectors{collection="test"} 3"#,
+            r#"quantixar_collection_deleted_vectors{collection="test"} 1"#,
+            "quantixar_memory_total_bytes ",
+        ];
+        for line in expected {
+            assert!(text.contains(line), "{line} is missing in:\n{text}");
+        }
+    }
+}
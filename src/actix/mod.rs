@@ -1,25 +1,32 @@
+pub mod auth;
+pub mod certificate_helpers;
 pub mod handlers;
-mod model;
+pub mod helpers;
+pub mod metrics;
+pub(crate) mod model;
 pub mod routes;
 pub mod table;
 use std::{
-    io::Error,
-    net::{Ipv4Addr, SocketAddr},
+    io::{Error, ErrorKind},
+    path::Path,
     sync::{Arc, Mutex},
+    thread::available_parallelism,
 };
 
 use actix_cors::Cors;
+use actix_multipart::{form::MultipartFormConfig, MultipartError};
 use actix_web::{
+    error::{InternalError, JsonPayloadError, PayloadError},
     get,
-    middleware::{Compress, Logger},
-    web::Data,
-    App, HttpResponse, HttpServer, Responder,
+    http::StatusCode,
+    middleware::{from_fn, Compress, Logger},
+    web::{self, Data},
+    App, HttpRequest, HttpResponse, HttpServer, Responder,
 };
-use atomic_refcell::AtomicRefCell;
 use routes::dataset_api;
 use serde_json::json;
-use tokio::{net::TcpListener, signal};
-use tracing::info;
+use tokio::{net::TcpListener, signal, sync::watch};
+use tracing::{error, info, warn};
 use utoipa::OpenApi;
 use utoipa_rapidoc::RapiDoc;
 use utoipa_redoc::{Redoc, Servable};
@@ -27,64 +34,232 @@ use utoipa_swagger_ui::SwaggerUi;
 
 use crate::{
     actix::{
+        auth::{api_key_middleware, ApiKeys},
+        certificate_helpers::tls_server_config,
         handlers::vector,
+        helpers::error_response,
+        metrics::{metrics_middleware, Metrics},
         routes::{
-            dataset_api::config_dataset_api, swagger_api::config_swagger_ui,
+            bench_api::config_bench_api, collections_api::config_collections_api,
+            dataset_api::config_dataset_api, jobs_api::config_jobs_api,
+            service_api::config_service_api, swagger_api::config_swagger_ui,
             vector_api::config_index_api,
         },
+        table::toc::TableOfContent,
     },
     engine::{
         index::hnsw::index::HNSWIndex,
-        storage::vector::{
-            base::VectorStorageEnum, dense_vector_storage::SimpleDenseVectorStorage,
+        storage::{
+            types::VectorStorageType,
+            vector::base::{open_vector_storage, VectorStorage},
         },
+        types::distance::Distance,
     },
     setting::Settings,
 };
 
-pub async fn init(settings: Settings) -> Result<(), Error> {
-    let simple_dense_vector_stroage = SimpleDenseVectorStorage::new(
-        512,
-        crate::engine::types::distance::Distance::Cosine,
-        "quantixar",
-    );
-    let vector_storage = Arc::new(AtomicRefCell::new(VectorStorageEnum::DenseSimple(
-        simple_dense_vector_stroage,
-    )));
-
-    let index_dir = "index_dir";
-    let index_dir_path = std::path::Path::new(index_dir);
-    let data_dimension = 512;
-    let dataset_size = 10;
-    let dist_f = hnsw_rs::dist::DistCosine;
-    let engine = match HNSWIndex::new(vector_storage, index_dir_path, data_dimension, dataset_size, dist_f) {
-        Ok(mut engine) => {
-            match engine.build_graph(true) {
-                Ok(_) => Arc::new(Mutex::new(engine)),
-                Err(e) => panic!("Error building HNSWIndex: {}", e),
-            }
-        },
-        Err(e) => panic!("Error creating HNSWIndex: {}", e),
-    };
+/// Seconds given to in-flight requests to complete once the shutdown is signaled
+const SHUTDOWN_TIMEOUT_SEC: u64 = 30;
+
+/// Dimension of the vectors accepted by the `/vector` api
+const VECTOR_INDEX_DIMENSION: usize = 512;
+
+/// Distance of the vectors accepted by the `/vector` api
+const VECTOR_INDEX_DISTANCE: Distance = Distance::Cosine;
+
+/// Serve the REST api until `shutdown` reports the shutdown and in-flight requests are drained
+pub async fn init(
+    settings: Settings,
+    toc: Arc<TableOfContent>,
+    shutdown: watch::Receiver<bool>,
+) -> Result<(), Error> {
+    let engine = Arc::new(Mutex::new(open_vector_index(&settings)?));
+    let index_engine = engine.clone();
+    let toc = Data::from(toc);
+    let max_request_size = settings.service.max_request_size_mb * 1024 * 1024;
+    let max_upload_size = settings.service.max_upload_size_mb * 1024 * 1024;
+    let api_keys = ApiKeys::from_config(&settings.service).map(Data::new);
+    if api_keys.is_none() {
+        info!("No API key configured, the API is open to anyone who can reach it");
+    }
+    let metrics = Data::new(Metrics::new());
     let server = HttpServer::new(move || {
         let cors = Cors::default()
             .allow_any_header()
             .allow_any_method()
             .allow_any_origin();
         App::new()
+            .wrap(from_fn(api_key_middleware))
+            // Outside of the api key check, so rejected requests are counted too
+            .wrap(from_fn(metrics_middleware))
             .wrap(Compress::default())
             .wrap(cors)
             .wrap(Logger::default().exclude("/"))
+            .app_data(json_config(max_request_size))
+            .app_data(multipart_config(max_upload_size))
+            .app_data(web::PayloadConfig::new(max_request_size))
             .app_data(Data::new(engine.clone()))
+            .app_data(toc.clone())
+            .app_data(metrics.clone())
+            .configure(|cfg| {
+                if let Some(api_keys) = &api_keys {
+                    cfg.app_data(api_keys.clone());
+                }
+            })
             .configure(config_index_api)
+            .configure(config_collections_api)
             .configure(config_swagger_ui)
             .configure(config_dataset_api)
-    });
+            .configure(config_jobs_api)
+            .configure(config_bench_api)
+            .configure(config_service_api)
+            .default_service(web::to(not_found))
+    })
+    .workers(max_workers(&settings))
+    // Signals are handled by `shutdown_receiver`, so the gRPC api stops at the same time
+    .disable_signals()
+    .shutdown_timeout(SHUTDOWN_TIMEOUT_SEC);
     let port = settings.service.http_port;
-    let host = settings.service.host;
-    let addr = SocketAddr::new(Ipv4Addr::new(127, 0, 0, 1).into(), port);
-    info!("Starting server at http://{}", addr);
-    server.bind(addr)?.run().await
+    let host = settings.service.host.as_str();
+    let server = if settings.service.enable_tls {
+        // Settings validation guarantees the tls section when TLS is enabled
+        let tls_config = settings.tls.as_ref().expect("TLS config is validated");
+        let tls_config = tls_server_config(
+            tls_config,
+            settings.service.verify_https_client_certificate,
+        )?;
+        info!("Starting server at https://{}:{}", host, port);
+        server.bind_rustls_0_23((host, port), tls_config)?
+    } else {
+        warn!("TLS is disabled, the API is served in plaintext");
+        info!("Starting server at http://{}:{}", host, port);
+        server.bind((host, port))?
+    }
+    .run();
+
+    let server_handle = server.handle();
+    tokio::spawn(async move {
+        wait_for_shutdown(shutdown).await;
+        server_handle.stop(true).await;
+    });
+    server.await?;
+
+    info!("HTTP server stopped, flushing the index");
+    let flushed = index_engine
+        .lock()
+        .map_err(|_| "index lock is poisoned".to_string())
+        .and_then(|engine| engine.flush().map_err(|err| err.to_string()));
+    if let Err(err) = flushed {
+        error!("Failed to flush the index: {err}");
+    }
+    Ok(())
+}
+
+/// Open the index behind the `/vector` api and build its graph from the stored vectors
+///
+/// Points are added through this index, so a read-only vector storage type is rejected.
+fn open_vector_index(settings: &Settings) -> Result<HNSWIndex<'static>, Error> {
+    let storage_type = settings.storage.vector_storage_type;
+    if storage_type == VectorStorageType::Memmap {
+        return Err(Error::new(
+            ErrorKind::InvalidInput,
+            format!(
+                "vector_storage_type {storage_type:?} is read-only, the /vector api needs an appendable storage type"
+            ),
+        ));
+    }
+    let index_path = Path::new(&settings.storage.storage_path).join("quantixar");
+    let vector_storage = open_vector_storage(
+        &index_path.join("vectors"),
+        VECTOR_INDEX_DIMENSION,
+        VECTOR_INDEX_DISTANCE,
+        storage_type,
+        settings.storage.performance.async_scorer,
+    )
+    .map_err(|err| Error::other(format!("Error opening vector storage: {err}")))?;
+    let dataset_size = vector_storage.borrow().total_vector_count();
+    let mut index = HNSWIndex::new(
+        vector_storage,
+        &index_path.join("index"),
+        VECTOR_INDEX_DIMENSION,
+        dataset_size,
+        settings.storage.quantization.clone(),
+    )
+    .map_err(|err| Error::other(format!("Error creating HNSWIndex: {err}")))?;
+    index
+        .build_graph(true)
+        .map_err(|err| Error::other(format!("Error building HNSWIndex: {err}")))?;
+    Ok(index)
+}
+
+/// Number of HTTP workers: `max_workers`, or the number of search threads if it is not set.
+/// 0 means one worker per available core.
+fn max_workers(settings: &Settings) -> usize {
+    let workers = settings
+        .service
+        .max_workers
+        .unwrap_or(settings.storage.performance.max_search_threads);
+    if workers > 0 {
+        return workers;
+    }
+    available_parallelism().map(|n| n.get()).unwrap_or(1)
+}
+
+/// JSON bodies larger than `limit` bytes are rejected with 413 Payload Too Large, malformed or
+/// invalid ones with 400 Bad Request
+fn json_config(limit: usize) -> actix_web_validator::JsonConfig {
+    actix_web_validator::JsonConfig::default()
+        .limit(limit)
+        .error_handler(|err, _req| {
+            let status = match err {
+                actix_web_validator::Error::JsonPayloadError(
+                    JsonPayloadError::Overflow { .. }
+                    | JsonPayloadError::OverflowKnownLength { .. },
+                ) => StatusCode::PAYLOAD_TOO_LARGE,
+                _ => StatusCode::BAD_REQUEST,
+            };
+            let response = error_response(status, &err, 0.0);
+            InternalError::from_response(err, response).into()
+        })
+}
+
+/// Multipart forms larger than `limit` bytes are rejected with 413 Payload Too Large
+fn multipart_config(limit: usize) -> MultipartFormConfig {
+    MultipartFormConfig::default()
+        .total_limit(limit)
+        .error_handler(|err, _req| {
+            let status = match err {
+                MultipartError::Payload(PayloadError::Overflow) => StatusCode::PAYLOAD_TOO_LARGE,
+                _ => StatusCode::BAD_REQUEST,
+            };
+            let response = error_response(status, &err, 0.0);
+            InternalError::from_response(err, response).into()
+        })
+}
+
+async fn not_found(req: HttpRequest) -> HttpResponse {
+    error_response(
+        StatusCode::NOT_FOUND,
+        format!("No route for {} {}", req.method(), req.path()),
+        0.0,
+    )
+}
+
+/// Receiver which switches to `true` once SIGINT or SIGTERM is received
+pub fn shutdown_receiver() -> watch::Receiver<bool> {
+    let (sender, receiver) = watch::channel(false);
+    tokio::spawn(async move {
+        shutdown_signal().await;
+        info!("Shutdown signal received, draining in-flight requests");
+        let _ = sender.send(true);
+    });
+    receiver
+}
+
+/// Wait until the shutdown is signaled
+pub async fn wait_for_shutdown(mut shutdown: watch::Receiver<bool>) {
+    // The sender is only dropped after signaling the shutdown
+    let _ = shutdown.wait_for(|stopping| *stopping).await;
 }
 
 async fn shutdown_signal() {
@@ -110,3 +285,71 @@ async fn shutdown_signal() {
         _ = terminate => {},
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use actix_web::{post, test};
+
+    use super::*;
+    use crate::actix::model::points::PointsList;
+
+    #[post("/points")]
+    async fn count_points(operation: actix_web_validator::Json<PointsList>) -> HttpResponse {
+        HttpResponse::Ok().json(operation.points.len())
+    }
+
+    #[actix_web::test]
+    async fn test_request_size_limit() {
+        let app =
+            test::init_service(App::new().app_data(json_config(1024)).service(count_points)).await;
+        let body = |n: usize| {
+            let points: Vec<_> = (0..n)
+                .map(|id| json!({"id": id, "vector": [1.0, 2.0, 3.0]}))
+                .collect();
+            json!({ "points": points })
+        };
+
+        let request = test::TestRequest::post()
+            .uri("/points")
+            .set_json(body(2))
+            .to_request();
+        let response = test::call_service(&app, request).await;
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let request = test::TestRequest::post()
+            .uri("/points")
+            .set_json(body(100))
+            .to_request();
+        let response = test::call_service(&app, request).await;
+        assert_eq!(response.status(), StatusCode::PAYLOAD_TOO_LARGE);
+        let body: serde_json::Value = test::read_body_json(response).await;
+        assert_eq!(body["status"], "error");
+    }
+
+    #[actix_web::test]
+    async fn test_error_envelope() {
+        let app = test::init_service(
+            App::new()
+                .app_data(json_config(1024))
+                .service(count_points)
+                .default_service(web::to(not_found)),
+        )
+        .await;
+
+        let request = test::TestRequest::post()
+            .uri("/points")
+            .set_json(json!({"points": [{"id": 1, "vector": "not a vector"}]}))
+            .to_request();
+        let response = test::call_service(&app, request).await;
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        let body: serde_json::Value = test::read_body_json(response).await;
+        assert_eq!(body["status"], "error");
+        assert!(body["error"].as_str().is_some_and(|error| !error.is_empty()));
+
+        let request = test::TestRequest::get().uri("/missing").to_request();
+        let response = test::call_service(&app, request).await;
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+        let body: serde_json::Value = test::read_body_json(response).await;
+        assert_eq!(body["error"], "No route for GET /missing");
+    }
+}
@@ -1 +1,2 @@
 pub mod vector;
+pub mod points;
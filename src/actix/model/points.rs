@@ -0,0 +1,50 @@
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+use validator::Validate;
+
+use crate::{
+  common::{
+    point_id::PointIdType,
+    validation::{validate_vector_values, MAX_SEARCH_LIMIT},
+  },
+  engine::types::types::Payload,
+};
+
+#[derive(Deserialize, Serialize, Debug, Clone, Validate, JsonSchema, ToSchema)]
+pub struct PointStruct {
+  /// Id of the point. If not set, the next numeric id of the collection is used.
+  #[serde(default)]
+  #[schema(value_type = Option<u64>)]
+  pub id: Option<PointIdType>,
+  #[validate(custom = "validate_vector_values")]
+  pub vector: Vec<f32>,
+  #[serde(default)]
+  pub payload: Option<Payload>,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, Validate, JsonSchema, ToSchema)]
+pub struct PointsList {
+  #[validate]
+  pub points: Vec<PointStruct>,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, Validate, JsonSchema, ToSchema)]
+pub struct PointsSelector {
+  #[schema(value_type = Vec<u64>)]
+  pub points: Vec<PointIdType>,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, Validate, JsonSchema, ToSchema)]
+pub struct RecommendRequest {
+  /// Look for points close to these ones
+  #[schema(value_type = Vec<u64>)]
+  pub positive: Vec<PointIdType>,
+  /// Look for points far from these ones
+  #[serde(default)]
+  #[schema(value_type = Vec<u64>)]
+  pub negative: Vec<PointIdType>,
+  /// Number of results, between 1 and 10000
+  #[validate(range(min = 1, max = "MAX_SEARCH_LIMIT"))]
+  pub k: usize,
+}
@@ -3,16 +3,23 @@ use serde::Deserialize;
 use utoipa::ToSchema;
 use validator::Validate;
 
-use crate::engine::types::types::Payload;
+use crate::{
+    common::validation::{validate_vector_values, MAX_SEARCH_LIMIT},
+    engine::types::types::Payload,
+};
 
 #[derive(Deserialize, Debug, Validate, JsonSchema, ToSchema)]
 pub struct AddVector {
+    #[validate(custom = "validate_vector_values")]
     pub vectors: Vec<f32>,
     pub payload: Payload,
 }
 
 #[derive(Deserialize, Debug, Validate, JsonSchema, ToSchema)]
 pub struct SearchVector {
+    #[validate(custom = "validate_vector_values")]
     pub vector: Vec<f32>,
+    /// Number of results, between 1 and 10000
+    #[validate(range(min = 1, max = "MAX_SEARCH_LIMIT"))]
     pub k: usize,
 }
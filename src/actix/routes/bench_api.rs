@@ -0,0 +1,116 @@
+use std::time::Instant;
+
+use actix_multipart::form::{tempfile::TempFile, text::Text, MultipartForm};
+use actix_web::{post, web::Data, HttpResponse};
+use tokio::runtime::Handle;
+use utoipa::ToSchema;
+
+use crate::{
+  actix::{
+    handlers::{
+      bench::{run_bench, BenchConfig, DEFAULT_BENCH_EF, DEFAULT_BENCH_K},
+      dataset::DEFAULT_IMPORT_BATCH_SIZE,
+    },
+    helpers::process_response,
+    table::{collections::HnswConfig, toc::TableOfContent},
+  },
+  common::operation_error::OperationError,
+  engine::types::distance::Distance,
+};
+
+#[derive(Debug, ToSchema)]
+pub struct BenchUploadSw {
+  /// ann-benchmarks HDF5 file with `train`, `test` and `neighbors` datasets
+  #[schema(format = Binary)]
+  file: Vec<u8>,
+  /// Euclidean by default, Cosine for angular datasets
+  distance: Option<Distance>,
+  /// Edges per node of the HNSW graph, 16 by default
+  m: Option<usize>,
+  /// Candidates considered while building the graph, 100 by default
+  ef_construct: Option<usize>,
+  /// Number of searched neighbours the recall is measured at, 10 by default
+  k: Option<usize>,
+  /// `ef` of the test queries, the field can be repeated. 16, 32, 64, 128 and 256 by default.
+  ef: Option<Vec<usize>>,
+  /// Number of test queries to run, all of them by default
+  queries: Option<usize>,
+  /// Number of points upserted at once, 1000 by default
+  batch_size: Option<usize>,
+}
+
+#[derive(Debug, MultipartForm)]
+pub struct BenchUpload {
+  file: TempFile,
+  distance: Option<Text<Distance>>,
+  m: Option<Text<usize>>,
+  ef_construct: Option<Text<usize>>,
+  k: Option<Text<usize>>,
+  ef: Vec<Text<usize>>,
+  queries: Option<Text<usize>>,
+  batch_size: Option<Text<usize>>,
+}
+
+impl BenchUpload {
+  fn config(&self) -> BenchConfig {
+    let hnsw = HnswConfig::default();
+    let ef = match self.ef.as_slice() {
+      [] => DEFAULT_BENCH_EF.to_vec(),
+      ef => ef.iter().map(|ef| **ef).collect(),
+    };
+    BenchConfig {
+      distance: self
+        .distance
+        .as_deref()
+        .copied()
+        .unwrap_or(Distance::Euclidean),
+      m: self.m.as_deref().copied().unwrap_or(hnsw.m),
+      ef_construct: self
+        .ef_construct
+        .as_deref()
+        .copied()
+        .unwrap_or(hnsw.ef_construct),
+      k: self.k.as_deref().copied().unwrap_or(DEFAULT_BENCH_K),
+      ef,
+      queries: self.queries.as_deref().copied(),
+      batch_size: self
+        .batch_size
+        .as_deref()
+        .copied()
+        .unwrap_or(DEFAULT_IMPORT_BATCH_SIZE),
+    }
+  }
+}
+
+#[utoipa::path(
+  post,
+  path = "/bench",
+  request_body(
+    content_type = "multipart/form-data",
+    content = BenchUploadSw
+  ),
+  responses(
+    (status = 200, description = "Recall@k, queries per second and latency percentiles at every ef")
+  )
+)]
+#[post("/bench")]
+pub async fn bench(
+  toc: Data<TableOfContent>,
+  MultipartForm(form): MultipartForm<BenchUpload>,
+) -> HttpResponse {
+  let timing = Instant::now();
+  let toc = toc.into_inner();
+  let config = form.config();
+  // Readers of HDF5 files are not `Send`, the benchmark runs on a blocking thread
+  let runtime = Handle::current();
+  let result = tokio::task::spawn_blocking(move || {
+    runtime.block_on(run_bench(&toc, form.file.file.path(), &config))
+  })
+  .await
+  .unwrap_or_else(|err| Err(OperationError::service_error(err.to_string())));
+  process_response(result, timing)
+}
+
+pub fn config_bench_api(cfg: &mut actix_web::web::ServiceConfig) {
+  cfg.service(bench);
+}
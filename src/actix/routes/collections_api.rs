@@ -0,0 +1,23 @@
+use actix_web::web;
+
+use crate::actix::handlers::collection::{
+  create_collection, create_snapshot, delete_collection, delete_points, delete_snapshot,
+  get_collection, list_collections, list_snapshots, recommend_points, search_points, upsert_points,
+  vacuum_collection,
+};
+
+pub fn config_collections_api(cfg: &mut web::ServiceConfig) {
+  cfg
+    .service(list_collections)
+    .service(get_collection)
+    .service(create_collection)
+    .service(delete_collection)
+    .service(upsert_points)
+    .service(delete_points)
+    .service(search_points)
+    .service(recommend_points)
+    .service(vacuum_collection)
+    .service(create_snapshot)
+    .service(list_snapshots)
+    .service(delete_snapshot);
+}
@@ -1,24 +1,56 @@
 use std::{
-  convert::Infallible, io::{Error, Read, Write}, path::Path
+  fs::File,
+  io::{Seek, SeekFrom},
+  sync::Arc,
+  time::Instant,
 };
 
-use actix_multipart::{
-  form::{tempfile::TempFile, MultipartForm}, Multipart
+use actix_multipart::form::{tempfile::TempFile, text::Text, MultipartForm};
+use actix_web::{
+  get,
+  http::{
+    header::{ContentDisposition, ContentType, DispositionParam, DispositionType},
+    StatusCode,
+  },
+  post,
+  web::{Data, Path, Query},
+  HttpResponse,
 };
-use actix_web::{http::StatusCode, post, HttpResponse, Responder};
-use chrono::format;
-use hdf5::{File as Hdf5File, H5Type, Result};
 use serde::{Deserialize, Serialize};
+use tokio::runtime::Handle;
+use tokio_util::io::ReaderStream;
 use tracing::debug;
-use utoipa::{openapi::Components, ToSchema};
+use utoipa::{IntoParams, ToSchema};
 
-use crate::{actix::handlers::dataset::read_hdf5, common::operation_error::OperationResult};
+use crate::{
+  actix::{
+    handlers::dataset::{
+      export_dataset, import_dataset, log_progress, DatasetFields, DatasetSource, ExportFormat,
+      DEFAULT_IMPORT_BATCH_SIZE,
+    },
+    helpers::{process_response, status_response},
+    table::{jobs::JobInfo, toc::TableOfContent},
+  },
+  common::operation_error::OperationResult,
+};
 
-#[derive(Serialize, Deserialize, ToSchema, Clone)]
+/// Format of a dataset file, the names are part of the API
+#[allow(clippy::upper_case_acronyms)]
+#[derive(Serialize, Deserialize, ToSchema, Clone, Copy, Debug, PartialEq, Eq, clap::ValueEnum)]
 pub enum DataType {
   HDF5,
   CSV,
   PARQUET,
+  /// TEXMEX vectors of `f32`
+  FVECS,
+  /// TEXMEX vectors of `i32`
+  IVECS,
+  /// TEXMEX vectors of `u8`
+  BVECS,
+  /// NumPy array of `f32` vectors
+  NPY,
+  /// NumPy archive of arrays
+  NPZ,
 }
 
 #[derive(Serialize, Deserialize, ToSchema, Clone)]
@@ -45,6 +77,22 @@ impl DataSet {
 pub struct UploadedFileSw {
   #[schema(format = Binary)]
   file: Vec<u8>,
+  /// Collection to import the points into
+  collection: String,
+  /// Format of the file, HDF5 by default
+  datatype: Option<DataType>,
+  /// HDF5 path of the 2-dimensional dataset of vectors, `train` by default, or NPZ array of the
+  /// vectors, the first one by default
+  dataset: Option<String>,
+  /// CSV or Parquet vector columns: one column with an array per row, or one column per
+  /// dimension. The field can be repeated.
+  vectors: Option<Vec<String>>,
+  /// HDF5 dataset, column or NPZ array of the point ids
+  ids: Option<String>,
+  /// HDF5 datasets or columns stored in the payload, the field can be repeated
+  payload: Option<Vec<String>>,
+  /// Number of points upserted at once, 1000 by default
+  batch_size: Option<usize>,
 }
 
 #[derive(Debug, MultipartForm, ToSchema)]
@@ -52,11 +100,34 @@ pub struct UploadedFile {
   #[multipart(rename = "file")]
   #[schema(format = Binary)]
   file: TempFile,
+  #[schema(value_type = String)]
+  collection: Text<String>,
+  #[schema(value_type = Option<DataType>)]
+  datatype: Option<Text<DataType>>,
+  #[schema(value_type = Option<String>)]
+  dataset: Option<Text<String>>,
+  #[schema(value_type = Vec<String>)]
+  vectors: Vec<Text<String>>,
+  #[schema(value_type = Option<String>)]
+  ids: Option<Text<String>>,
+  #[schema(value_type = Vec<String>)]
+  payload: Vec<Text<String>>,
+  #[schema(value_type = Option<usize>)]
+  batch_size: Option<Text<usize>>,
 }
 
-pub struct HDF5FileConfig<'a> {
-  pub dataset_name: &'a str,
-  pub target_data_path: &'a str,
+impl UploadedFile {
+  /// Location of the point fields in the file, according to its format
+  fn source(&self) -> OperationResult<DatasetSource> {
+    let fields = DatasetFields {
+      dataset: self.dataset.as_ref().map(|name| name.to_string()),
+      vectors: self.vectors.iter().map(|name| name.to_string()).collect(),
+      ids: self.ids.as_ref().map(|name| name.to_string()),
+      payload: self.payload.iter().map(|name| name.to_string()).collect(),
+    };
+    let datatype = self.datatype.as_deref().unwrap_or(&DataType::HDF5);
+    DatasetSource::new(datatype, fields)
+  }
 }
 
 #[utoipa::path(
@@ -67,29 +138,112 @@ content_type = "multipart/form-data",
 content = UploadedFileSw
 ),
 responses(
-(status = 200, description = "DataSet created successfully")
+(status = 202, description = "Import job started in the background, its progress is reported by `GET /jobs/{id}`")
 )
 )]
 #[post("/dataset")]
 pub async fn create_dataset(
+  toc: Data<TableOfContent>,
   MultipartForm(form): MultipartForm<UploadedFile>,
-) -> OperationResult<HttpResponse> {
+) -> HttpResponse {
+  let timing = Instant::now();
+  match start_import(toc.into_inner(), form).await {
+    Ok(job) => status_response(StatusCode::ACCEPTED, "accepted", job, timing),
+    Err(err) => process_response::<()>(Err(err), timing),
+  }
+}
+
+/// Validate the upload and import it in a background job, the uploaded file is removed once the
+/// job is finished
+async fn start_import(toc: Arc<TableOfContent>, form: UploadedFile) -> OperationResult<JobInfo> {
+  let source = form.source()?;
+  let collection_name = form.collection.into_inner();
+  let batch_size = form
+    .batch_size
+    .map(Text::into_inner)
+    .unwrap_or(DEFAULT_IMPORT_BATCH_SIZE);
+  toc.check_collection(&collection_name).await?;
+  let (job, stopped) = toc.jobs().start(&collection_name)?;
+  let id = job.id;
   let file = form.file;
-  let file_path = file.file.path();
-  debug!("File path: {:?}", file_path);
-  read_hdf5(
-    &file_path.to_str().ok_or(Error::new(
-      std::io::ErrorKind::InvalidData,
-      "Invalid file path",
-    ))?,
-    HDF5FileConfig {
-      dataset_name: "data",
-      target_data_path: "test",
-    },
-  )?;
-  Ok(HttpResponse::Ok().body("DataSet created successfully"))
+  debug!("File path: {:?}", file.file.path());
+  // Readers of some formats are not `Send`, so the import runs on a blocking thread
+  let runtime = Handle::current();
+  tokio::task::spawn_blocking(move || {
+    let mut log = log_progress(&collection_name);
+    let progress = |ingested, total| {
+      toc.jobs().progress(id, ingested, total);
+      log(ingested, total);
+    };
+    let result = runtime.block_on(import_dataset(
+      &toc,
+      &collection_name,
+      file.file.path(),
+      &source,
+      batch_size,
+      &stopped,
+      progress,
+    ));
+    match &result {
+      Ok(count) => {
+        log::info!("Import job {id} imported {count} points into collection {collection_name}")
+      }
+      Err(err) => log::warn!("Import job {id} into collection {collection_name} stopped: {err}"),
+    }
+    toc.jobs().finish(id, &result);
+  });
+  Ok(job)
+}
+
+#[derive(Debug, Deserialize, IntoParams)]
+pub struct ExportParams {
+  /// FVECS, IVECS, BVECS, NPY or NPZ
+  datatype: DataType,
+}
+
+#[utoipa::path(
+  get,
+  path = "/collections/{collection_name}/export",
+  params(ExportParams),
+  responses(
+    (status = 200, description = "File with the vectors of the collection, NPZ archives also hold the numeric point ids")
+  )
+)]
+#[get("/collections/{collection_name}/export")]
+pub async fn export_collection(
+  toc: Data<TableOfContent>,
+  collection_name: Path<String>,
+  params: Query<ExportParams>,
+) -> HttpResponse {
+  let timing = Instant::now();
+  let result = export_to_file(&toc, &collection_name, &params.datatype).await;
+  let (file, format) = match result {
+    Ok(exported) => exported,
+    Err(err) => return process_response::<()>(Err(err), timing),
+  };
+  let file_name = format!("{}.{}", collection_name.as_str(), format.extension());
+  HttpResponse::Ok()
+    .content_type(ContentType::octet_stream())
+    .insert_header(ContentDisposition {
+      disposition: DispositionType::Attachment,
+      parameters: vec![DispositionParam::Filename(file_name)],
+    })
+    .streaming(ReaderStream::new(tokio::fs::File::from_std(file)))
+}
+
+/// Export into an unnamed temporary file, which is removed once the response is sent
+async fn export_to_file(
+  toc: &TableOfContent,
+  collection_name: &str,
+  datatype: &DataType,
+) -> OperationResult<(File, ExportFormat)> {
+  let format = ExportFormat::new(datatype)?;
+  let mut file = tempfile::tempfile()?;
+  export_dataset(toc, collection_name, format, &mut file).await?;
+  file.seek(SeekFrom::Start(0))?;
+  Ok((file, format))
 }
 
 pub fn config_dataset_api(cfg: &mut actix_web::web::ServiceConfig) {
-  cfg.service(create_dataset);
+  cfg.service(create_dataset).service(export_collection);
 }
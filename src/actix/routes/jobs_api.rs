@@ -0,0 +1,7 @@
+use actix_web::web;
+
+use crate::actix::handlers::jobs::{cancel_job, get_job, list_jobs};
+
+pub fn config_jobs_api(cfg: &mut web::ServiceConfig) {
+  cfg.service(list_jobs).service(get_job).service(cancel_job);
+}
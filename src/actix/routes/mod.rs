@@ -1,3 +1,7 @@
+pub(crate) mod bench_api;
+pub(crate) mod collections_api;
 pub(crate) mod dataset_api;
+pub(crate) mod jobs_api;
+pub(crate) mod service_api;
 pub(crate) mod swagger_api;
 pub(crate) mod vector_api;
@@ -0,0 +1,12 @@
+use actix_web::web;
+
+use crate::actix::handlers::service::{healthz, livez, metrics, readyz, telemetry};
+
+pub fn config_service_api(cfg: &mut web::ServiceConfig) {
+  cfg
+    .service(metrics)
+    .service(telemetry)
+    .service(healthz)
+    .service(readyz)
+    .service(livez);
+}
@@ -2,13 +2,47 @@ use actix_web::web;
 use utoipa::OpenApi;
 use utoipa_swagger_ui::SwaggerUi;
 
-use crate::actix::handlers::vector;
+use crate::actix::handlers::{collection, jobs, service, vector};
+use crate::actix::model::points::{PointStruct, PointsList, PointsSelector, RecommendRequest};
 
-use crate::actix::routes::{dataset_api, vector_api};
+use crate::actix::routes::{bench_api, dataset_api, vector_api};
 #[derive(OpenApi)]
 #[openapi(
-    paths(vector_api::index, dataset_api::create_dataset, vector::add_vector),
-    components(schemas(dataset_api::UploadedFileSw))
+    paths(
+        vector_api::index,
+        dataset_api::create_dataset,
+        dataset_api::export_collection,
+        jobs::list_jobs,
+        jobs::get_job,
+        jobs::cancel_job,
+        bench_api::bench,
+        vector::add_vector,
+        collection::list_collections,
+        collection::get_collection,
+        collection::create_collection,
+        collection::delete_collection,
+        collection::upsert_points,
+        collection::delete_points,
+        collection::search_points,
+        collection::recommend_points,
+        collection::vacuum_collection,
+        collection::create_snapshot,
+        collection::list_snapshots,
+        collection::delete_snapshot,
+        service::metrics,
+        service::telemetry,
+        service::healthz,
+        service::readyz,
+        service::livez
+    ),
+    components(schemas(
+        dataset_api::UploadedFileSw,
+        bench_api::BenchUploadSw,
+        PointStruct,
+        PointsList,
+        PointsSelector,
+        RecommendRequest
+    ))
 )]
 struct ApiDocs;
 
@@ -1,27 +1,857 @@
-use std::{collections::HashMap, sync::Arc};
+use std::{
+  collections::HashMap,
+  fs::{create_dir_all, read_dir, remove_dir_all},
+  path::{Path, PathBuf},
+  sync::{
+    atomic::{AtomicBool, AtomicU64, Ordering},
+    mpsc::{self, RecvTimeoutError, Sender},
+    Arc,
+  },
+  thread::{self, JoinHandle},
+  time::{Duration, Instant},
+};
 
+use io::file_operations::{atomic_save_json, read_json};
+use parking_lot::{Mutex, RwLock};
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
-use tokio::{runtime::Handle, sync::RwLock};
 use validator::Validate;
 
+use super::snapshots::{archive_dir, describe_snapshot, new_snapshot_name, SnapshotDescription};
+use crate::{
+  actix::model::points::PointStruct,
+  common::{
+    operation_error::{OperationError, OperationResult},
+    point_id::PointIdType,
+    validation::{check_search_limit, check_vector},
+  },
+  engine::{
+    index::hnsw::config::HnswGraphConfig,
+    segments::{
+      holder::{SegmentHolder, SegmentId},
+      optimizer::{optimize, recover_optimization, vacuum_plans, OptimizersConfig, SegmentInfo},
+      segment::{ScoredPoint, Segment, SegmentConfig, SegmentTelemetry},
+    },
+    storage::{
+      quantized::config::QuantizationConfig,
+      rocksdb::RocksDbStats,
+      types::{StorageConfig, VectorStorageType},
+    },
+    types::{distance::Distance, types::VectorElementType},
+  },
+};
+
 pub type CollectionId = String;
 
-pub type Collections = HashMap<CollectionId, Collection>;
+/// Collections are shared with operations running on blocking threads, like vacuum
+pub type Collections = HashMap<CollectionId, Arc<Collection>>;
+
+const COLLECTION_CONFIG_FILE: &str = "config.json";
+const SEGMENTS_PATH: &str = "segments";
+/// How often the optimizer checks the segments if there are no updates
+const OPTIMIZER_INTERVAL: Duration = Duration::from_secs(1);
 
+/// Collection of points split into segments
+///
+/// New points go to the appendable segment, the background optimizer seals it once it is big
+/// enough and rebuilds sealed segments: indexes, merges, vacuums and moves them to disk.
 pub struct Collection {
   pub(super) id: CollectionId,
-  pub(crate) collection_config: Arc<RwLock<CollectionConfig>>,
-  update_runtime: Handle,
-  // Search runtime handle.
-  search_runtime: Handle,
-  updates_lock: RwLock<()>,
+  path: PathBuf,
+  pub(crate) collection_config: CollectionConfig,
+  storage_config: Arc<StorageConfig>,
+  segments: Arc<RwLock<SegmentHolder>>,
+  /// Id for the next point inserted without id
+  next_point_id: AtomicU64,
+  /// Serializes updates, so concurrent upserts never put a point into two segments
+  updates_lock: Mutex<()>,
+  /// Wakes the optimizer up after updates, dropping it stops the optimizer
+  optimizer_notify: Option<Sender<()>>,
+  optimizer_handle: Option<JoinHandle<()>>,
+  optimizer_stopped: Arc<AtomicBool>,
+  /// Only one optimization of the collection runs at a time
+  optimization_lock: Arc<Mutex<()>>,
+  /// Searches and recommendations served since the collection was loaded
+  searches: AtomicU64,
+  /// Points upserted since the collection was loaded
+  inserted_points: AtomicU64,
 }
 
-#[derive(Debug, Deserialize, Serialize, JsonSchema, Validate, Clone, PartialEq, Eq)]
+/// State of a collection: counts, segments, storage sizes and index structure
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct CollectionInfo {
+  pub config: CollectionConfig,
+  /// HNSW parameters of sealed segments, with the storage defaults applied
+  pub hnsw_config: HnswConfig,
+  pub points_count: usize,
+  /// Stored vectors, including deleted ones
+  pub vectors_count: usize,
+  /// Live vectors of segments with HNSW index
+  pub indexed_vectors_count: usize,
+  pub deleted_vectors_count: usize,
+  /// Share of the stored vectors which are deleted and not vacuumed yet
+  pub deleted_ratio: f64,
+  pub disk_bytes: u64,
+  /// Estimated RAM usage of the vectors and indexes
+  pub ram_bytes: usize,
+  pub segments: Vec<SegmentDetails>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct SegmentDetails {
+  #[serde(flatten)]
+  pub info: SegmentInfo,
+  #[serde(flatten)]
+  pub telemetry: SegmentTelemetry,
+}
+
+/// Monitoring data of a collection
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct CollectionMetrics {
+  pub segments: Vec<SegmentInfo>,
+  /// Points per HNSW layer, summed over the indexed segments
+  pub index_layers: Vec<usize>,
+  /// Statistics of the RocksDB vector columns, summed over the segments
+  pub rocksdb: RocksDbStats,
+  pub searches: u64,
+  pub inserted_points: u64,
+}
+
+/// Receives the vectors of a collection, see [`Collection::export_vectors`]
+pub trait VectorSink {
+  /// Called once before the vectors, with the number of points and the vector size
+  fn start(&mut self, count: usize, dim: usize) -> OperationResult<()>;
+
+  fn write(&mut self, id: PointIdType, vector: &[VectorElementType]) -> OperationResult<()>;
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct VacuumReport {
+  /// Number of rewritten segments
+  pub segments: usize,
+  /// Number of reclaimed vectors of deleted points
+  pub reclaimed_vectors: usize,
+}
+
+impl Collection {
+  pub fn create(
+    id: CollectionId,
+    path: &Path,
+    collection_config: CollectionConfig,
+    storage_config: Arc<StorageConfig>,
+  ) -> OperationResult<Self> {
+    collection_config
+      .validate()
+      .map_err(|err| OperationError::ValidationError {
+        description: err.to_string(),
+      })?;
+    create_dir_all(path.join(SEGMENTS_PATH))?;
+    atomic_save_json(&path.join(COLLECTION_CONFIG_FILE), &collection_config)?;
+    Self::load(id, path, storage_config)
+  }
+
+  pub fn load(
+    id: CollectionId,
+    path: &Path,
+    storage_config: Arc<StorageConfig>,
+  ) -> OperationResult<Self> {
+    Self::load_with_progress(id, path, storage_config, |_, _| {})
+  }
+
+  /// Load the collection, `progress` is called with the number of opened segments and the
+  /// total number of segments after each segment
+  pub fn load_with_progress(
+    id: CollectionId,
+    path: &Path,
+    storage_config: Arc<StorageConfig>,
+    mut progress: impl FnMut(usize, usize),
+  ) -> OperationResult<Self> {
+    let collection_config: CollectionConfig = read_json(&path.join(COLLECTION_CONFIG_FILE))?;
+    let segments_path = path.join(SEGMENTS_PATH);
+    create_dir_all(&segments_path)?;
+    recover_optimization(&segments_path)?;
+
+    let with_async_io = storage_config.performance.async_scorer;
+    let mut segment_paths = Vec::new();
+    for entry in read_dir(&segments_path)? {
+      let segment_path = entry?.path();
+      let Some(segment_id) = segment_path
+        .file_name()
+        .and_then(|name| name.to_str())
+        .and_then(|name| name.parse::<SegmentId>().ok())
+      else {
+        continue;
+      };
+      if !Segment::exists(&segment_path) {
+        // Segment creation was interrupted
+        remove_dir_all(&segment_path)?;
+        continue;
+      }
+      segment_paths.push((segment_id, segment_path));
+    }
+    let mut segments = SegmentHolder::default();
+    progress(0, segment_paths.len());
+    for (opened, (segment_id, segment_path)) in segment_paths.iter().enumerate() {
+      segments.add(*segment_id, Segment::open(segment_path, with_async_io)?);
+      progress(opened + 1, segment_paths.len());
+    }
+    if segments.appendable_segment().is_none() {
+      let segment_id = segments.reserve_id();
+      let segment = Segment::create(
+        &segments_path.join(segment_id.to_string()),
+        collection_config.appendable_segment_config(&storage_config),
+        with_async_io,
+      )?;
+      segments.add(segment_id, segment);
+    }
+    let next_point_id = segments
+      .iter()
+      .filter_map(|(_, segment)| segment.read().max_num_id())
+      .max()
+      .map_or(0, |max_id| max_id + 1);
+
+    let mut collection = Collection {
+      id,
+      path: path.to_owned(),
+      collection_config,
+      storage_config,
+      segments: Arc::new(RwLock::new(segments)),
+      next_point_id: AtomicU64::new(next_point_id),
+      updates_lock: Mutex::new(()),
+      optimizer_notify: None,
+      optimizer_handle: None,
+      optimizer_stopped: Arc::new(AtomicBool::new(false)),
+      optimization_lock: Arc::new(Mutex::new(())),
+      searches: AtomicU64::new(0),
+      inserted_points: AtomicU64::new(0),
+    };
+    collection.start_optimizer()?;
+    Ok(collection)
+  }
+
+  pub fn path(&self) -> &Path {
+    &self.path
+  }
+
+  fn start_optimizer(&mut self) -> OperationResult<()> {
+    let (sender, receiver) = mpsc::channel();
+    let segments = self.segments.clone();
+    let segments_path = self.path.join(SEGMENTS_PATH);
+    let collection_config = self.collection_config.clone();
+    let storage_config = self.storage_config.clone();
+    let stopped = self.optimizer_stopped.clone();
+    let optimization_lock = self.optimization_lock.clone();
+    let collection_id = self.id.clone();
+
+    let handle = thread::Builder::new()
+      .name(format!("optimizer-{}", self.id))
+      .spawn(move || {
+        let optimizers = collection_config.optimizers_config(&storage_config);
+        let flush_interval = Duration::from_secs(optimizers.flush_interval_sec);
+        let mut last_flush = Instant::now();
+        // Failed optimizations are retried after the next update
+        let mut failed = false;
+        loop {
+          match receiver.recv_timeout(OPTIMIZER_INTERVAL) {
+            Ok(()) => {
+              receiver.try_iter().for_each(drop);
+              failed = false;
+            }
+            Err(RecvTimeoutError::Timeout) => {}
+            Err(RecvTimeoutError::Disconnected) => break,
+          }
+
+          while optimizers.max_optimization_threads > 0 && !failed {
+            let _optimization_guard = optimization_lock.lock();
+            let Some(plan) = optimizers.plan(&segments.read().segment_infos()) else {
+              break;
+            };
+            let result = optimize(
+              &segments,
+              &segments_path,
+              &plan,
+              |size_kb| collection_config.optimized_segment_config(&storage_config, size_kb),
+              storage_config.performance.async_scorer,
+              &stopped,
+            );
+            if let Err(err) = result {
+              if !matches!(err, OperationError::Cancelled { .. }) {
+                log::error!("Optimization of collection {collection_id} failed: {err}");
+              }
+              failed = true;
+            }
+          }
+
+          if last_flush.elapsed() >= flush_interval {
+            if let Err(err) = flush_segments(&segments.read()) {
+              log::error!("Flush of collection {collection_id} failed: {err}");
+            }
+            last_flush = Instant::now();
+          }
+        }
+      })?;
+
+    self.optimizer_notify = Some(sender);
+    self.optimizer_handle = Some(handle);
+    Ok(())
+  }
+
+  fn notify_optimizer(&self) {
+    if let Some(sender) = &self.optimizer_notify {
+      // The optimizer only stops when the collection is dropped
+      let _ = sender.send(());
+    }
+  }
+
+  /// Insert points or replace existing ones, returns ids of the points
+  ///
+  /// Segments are persisted by the periodic flush of the optimizer and on close, not by every
+  /// upsert, so bulk loads do not rewrite the payloads and ids of the segment per batch.
+  pub fn upsert_points(&self, mut points: Vec<PointStruct>) -> OperationResult<Vec<PointIdType>> {
+    for point in &points {
+      check_vector(&point.vector, self.collection_config.vector_size)?;
+    }
+    if self.collection_config.normalizes_vectors() {
+      for point in &mut points {
+        normalize(&mut point.vector);
+      }
+    }
+
+    let _update_guard = self.updates_lock.lock();
+    let segments = self.segments.read();
+    let appendable = segments
+      .appendable_segment()
+      .ok_or_else(|| OperationError::service_error("collection has no appendable segment"))?;
+
+    let ids: Vec<PointIdType> = points
+      .iter()
+      .map(|point| {
+        let id = match point.id {
+          Some(id) => id,
+          None => self.next_point_id.fetch_add(1, Ordering::Relaxed).into(),
+        };
+        if let PointIdType::NumId(num_id) = id {
+          self.next_point_id.fetch_max(num_id + 1, Ordering::Relaxed);
+        }
+        id
+      })
+      .collect();
+
+    // Sealed segments are not updated, the previous versions of the points are deleted instead,
+    // in one batch per segment so an indexed segment repairs its graph once
+    for (_, segment) in segments.iter() {
+      if Arc::ptr_eq(segment, appendable) {
+        continue;
+      }
+      let segment_ids: Vec<_> = {
+        let segment = segment.read();
+        ids
+          .iter()
+          .copied()
+          .filter(|id| segment.has_point(*id))
+          .collect()
+      };
+      if !segment_ids.is_empty() {
+        segment.write().delete_points(&segment_ids)?;
+      }
+    }
+
+    let mut appendable = appendable.write();
+    for (point, id) in points.into_iter().zip(&ids) {
+      appendable.upsert_point(*id, &point.vector, point.payload.unwrap_or_default())?;
+    }
+    drop(appendable);
+    drop(segments);
+
+    self
+      .inserted_points
+      .fetch_add(ids.len() as u64, Ordering::Relaxed);
+    self.notify_optimizer();
+    Ok(ids)
+  }
+
+  /// Returns the number of deleted points
+  pub fn delete_points(&self, ids: &[PointIdType]) -> OperationResult<usize> {
+    let _update_guard = self.updates_lock.lock();
+    let segments = self.segments.read();
+    let mut deleted = 0;
+    for (_, segment) in segments.iter() {
+      // Segments are locked for writing only if they have some of the points
+      let segment_ids: Vec<_> = {
+        let segment = segment.read();
+        ids
+          .iter()
+          .copied()
+          .filter(|id| segment.has_point(*id))
+          .collect()
+      };
+      if !segment_ids.is_empty() {
+        deleted += segment.write().delete_points(&segment_ids)?;
+      }
+    }
+    drop(segments);
+
+    self.notify_optimizer();
+    Ok(deleted)
+  }
+
+  pub fn search(
+    &self,
+    vector: &[VectorElementType],
+    top: usize,
+  ) -> OperationResult<Vec<ScoredPoint>> {
+    self.search_with_ef(vector, top, None)
+  }
+
+  /// Search with `ef` candidates in the HNSW graphs, instead of the `ef_construct` of the index
+  pub fn search_with_ef(
+    &self,
+    vector: &[VectorElementType],
+    top: usize,
+    ef: Option<usize>,
+  ) -> OperationResult<Vec<ScoredPoint>> {
+    check_vector(vector, self.collection_config.vector_size)?;
+    check_search_limit(top)?;
+    self.searches.fetch_add(1, Ordering::Relaxed);
+    self.search_segments(vector, top, ef)
+  }
+
+  fn search_segments(
+    &self,
+    vector: &[VectorElementType],
+    top: usize,
+    ef: Option<usize>,
+  ) -> OperationResult<Vec<ScoredPoint>> {
+    let segments = self.segments.read();
+    let mut result = Vec::new();
+    for (_, segment) in segments.iter() {
+      result.extend(segment.read().search(vector, top, ef)?);
+    }
+    drop(segments);
+
+    result.sort_by(|a, b| a.distance.total_cmp(&b.distance));
+    let mut seen = std::collections::HashSet::with_capacity(result.len());
+    result.retain(|point| seen.insert(point.id));
+    result.truncate(top);
+    Ok(result)
+  }
+
+  /// Points closest to the positive examples and far from the negative ones
+  ///
+  /// The query vector is `avg(positive) + (avg(positive) - avg(negative))`, the examples
+  /// themselves are left out of the result.
+  pub fn recommend(
+    &self,
+    positive: &[PointIdType],
+    negative: &[PointIdType],
+    top: usize,
+  ) -> OperationResult<Vec<ScoredPoint>> {
+    check_search_limit(top)?;
+    if positive.is_empty() {
+      return Err(OperationError::ValidationError {
+        description: "At least one positive example is required".to_string(),
+      });
+    }
+    let average = |ids: &[PointIdType]| -> OperationResult<Vec<VectorElementType>> {
+      let mut sum = vec![0.0; self.collection_config.vector_size];
+      for id in ids {
+        for (acc, value) in sum.iter_mut().zip(self.point_vector(*id)?) {
+          *acc += value;
+        }
+      }
+      Ok(sum.into_iter().map(|x| x / ids.len() as f32).collect())
+    };
+    let positive_avg = average(positive)?;
+    let query = if negative.is_empty() {
+      positive_avg
+    } else {
+      let negative_avg = average(negative)?;
+      positive_avg
+        .iter()
+        .zip(negative_avg)
+        .map(|(pos, neg)| pos + (pos - neg))
+        .collect()
+    };
+
+    let examples: std::collections::HashSet<_> = positive.iter().chain(negative).collect();
+    self.searches.fetch_add(1, Ordering::Relaxed);
+    let mut result = self.search_segments(&query, top + examples.len(), None)?;
+    result.retain(|point| !examples.contains(&point.id));
+    result.truncate(top);
+    Ok(result)
+  }
+
+  fn point_vector(&self, id: PointIdType) -> OperationResult<Vec<VectorElementType>> {
+    self
+      .segments
+      .read()
+      .iter()
+      .find_map(|(_, segment)| segment.read().vector(id))
+      .ok_or(OperationError::PointIdError {
+        missed_point_id: id,
+      })
+  }
+
+  /// Archive the collection into a new snapshot in `snapshots_dir`
+  ///
+  /// Updates and optimizations wait until the snapshot is written, so the archived segments are
+  /// consistent with each other.
+  pub fn create_snapshot(&self, snapshots_dir: &Path) -> OperationResult<SnapshotDescription> {
+    create_dir_all(snapshots_dir)?;
+    let _update_guard = self.updates_lock.lock();
+    let _optimization_guard = self.optimization_lock.lock();
+    self.flush()?;
+    let snapshot_path = snapshots_dir.join(new_snapshot_name(&self.id));
+    archive_dir(&self.path, &snapshot_path)?;
+    describe_snapshot(&snapshot_path)
+  }
+
+  /// Write the vectors of the live points into `sink`, returns the number of points
+  ///
+  /// Updates and optimizations wait until the vectors are written, so the count given to the
+  /// sink stays exact.
+  pub fn export_vectors(&self, sink: &mut impl VectorSink) -> OperationResult<usize> {
+    let _update_guard = self.updates_lock.lock();
+    let _optimization_guard = self.optimization_lock.lock();
+    let segments = self.segments.read();
+    let count = segments
+      .iter()
+      .map(|(_, segment)| segment.read().points_count())
+      .sum();
+    sink.start(count, self.collection_config.vector_size)?;
+    for (_, segment) in segments.iter() {
+      let segment = segment.read();
+      for id in segment.point_ids() {
+        let vector = segment.vector(id).ok_or_else(|| {
+          OperationError::service_error(format!("vector of point {id} is missing"))
+        })?;
+        sink.write(id, &vector)?;
+      }
+    }
+    Ok(count)
+  }
+
+  pub fn points_count(&self) -> usize {
+    self
+      .segments
+      .read()
+      .iter()
+      .map(|(_, segment)| segment.read().points_count())
+      .sum()
+  }
+
+  pub fn segment_infos(&self) -> Vec<SegmentInfo> {
+    self.segments.read().segment_infos()
+  }
+
+  pub fn info(&self) -> OperationResult<CollectionInfo> {
+    let segments = self
+      .segments
+      .read()
+      .iter()
+      .map(|(id, segment)| {
+        let segment = segment.read();
+        Ok(SegmentDetails {
+          info: SegmentInfo::new(id, &segment),
+          telemetry: segment.telemetry()?,
+        })
+      })
+      .collect::<OperationResult<Vec<_>>>()?;
+    let sum = |count: fn(&SegmentDetails) -> usize| segments.iter().map(count).sum::<usize>();
+    let vectors_count = sum(|segment| segment.info.total_vector_count);
+    let deleted_vectors_count = sum(|segment| segment.info.deleted_vector_count);
+    Ok(CollectionInfo {
+      config: self.collection_config.clone(),
+      hnsw_config: self.collection_config.hnsw_config(&self.storage_config),
+      points_count: sum(|segment| segment.info.points_count),
+      vectors_count,
+      indexed_vectors_count: sum(|segment| {
+        if segment.info.indexed {
+          segment.info.total_vector_count - segment.info.deleted_vector_count
+        } else {
+          0
+        }
+      }),
+      deleted_vectors_count,
+      deleted_ratio: if vectors_count == 0 {
+        0.0
+      } else {
+        deleted_vectors_count as f64 / vectors_count as f64
+      },
+      disk_bytes: segments
+        .iter()
+        .map(|segment| segment.telemetry.disk_bytes)
+        .sum(),
+      ram_bytes: sum(|segment| segment.telemetry.ram_bytes),
+      segments,
+    })
+  }
+
+  /// Counters and storage statistics of the collection, for monitoring
+  pub fn metrics(&self) -> OperationResult<CollectionMetrics> {
+    let segments = self.segments.read();
+    let mut index_layers: Vec<usize> = Vec::new();
+    let mut rocksdb = RocksDbStats::default();
+    for (_, segment) in segments.iter() {
+      let segment = segment.read();
+      for (layer, points) in segment.index_layer_sizes().into_iter().enumerate() {
+        match index_layers.get_mut(layer) {
+          Some(total) => *total += points,
+          None => index_layers.push(points),
+        }
+      }
+      if let Some(stats) = segment.rocksdb_stats()? {
+        rocksdb.estimated_keys += stats.estimated_keys;
+        rocksdb.sst_files_bytes += stats.sst_files_bytes;
+        rocksdb.memtable_bytes += stats.memtable_bytes;
+      }
+    }
+    Ok(CollectionMetrics {
+      segments: segments.segment_infos(),
+      index_layers,
+      rocksdb,
+      searches: self.searches.load(Ordering::Relaxed),
+      inserted_points: self.inserted_points.load(Ordering::Relaxed),
+    })
+  }
+
+  pub fn flush(&self) -> OperationResult<()> {
+    flush_segments(&self.segments.read())
+  }
+
+  /// Stop the optimizer, flush all segments and dump their HNSW graphs
+  ///
+  /// Storages are closed once the collection is dropped. Indexed segments load the dumped graphs
+  /// when they are opened, instead of building them again.
+  pub fn close(mut self) -> OperationResult<()> {
+    self.stop_optimizer();
+    // Segments are taken out, so dropping the collection does not flush them again after the dump
+    let segments = std::mem::take(&mut *self.segments.write());
+    flush_segments(&segments)?;
+    for (_, segment) in segments.iter() {
+      segment.read().dump_index()?;
+    }
+    Ok(())
+  }
+
+  /// Cancel running optimizations and vacuums, the collection is about to be closed
+  pub fn cancel_optimizations(&self) {
+    self.optimizer_stopped.store(true, Ordering::Relaxed);
+  }
+
+  /// Cancel a running optimization and wait for the optimizer thread to exit
+  fn stop_optimizer(&mut self) {
+    self.optimizer_stopped.store(true, Ordering::Relaxed);
+    self.optimizer_notify.take();
+    if let Some(handle) = self.optimizer_handle.take() {
+      if handle.join().is_err() {
+        log::error!("Optimizer of collection {} panicked", self.id);
+      }
+    }
+  }
+
+  /// Rewrite all segments with deleted points, so the space of deleted vectors is reclaimed and
+  /// deleted nodes are dropped from the index graphs
+  ///
+  /// Segments are vacuumed automatically once the deleted ratio crosses `deleted_threshold`.
+  pub fn vacuum(&self) -> OperationResult<VacuumReport> {
+    let _optimization_guard = self.optimization_lock.lock();
+    let infos = self.segment_infos();
+    let mut report = VacuumReport::default();
+    for plan in vacuum_plans(&infos) {
+      optimize(
+        &self.segments,
+        &self.path.join(SEGMENTS_PATH),
+        &plan,
+        |size_kb| {
+          self
+            .collection_config
+            .optimized_segment_config(&self.storage_config, size_kb)
+        },
+        self.storage_config.performance.async_scorer,
+        &self.optimizer_stopped,
+      )?;
+      report.segments += 1;
+      report.reclaimed_vectors += infos
+        .iter()
+        .filter(|info| plan.segments.contains(&info.id))
+        .map(|info| info.deleted_vector_count)
+        .sum::<usize>();
+    }
+    Ok(report)
+  }
+
+  /// Run the pending optimizations in the calling thread, so all segments are indexed as
+  /// configured once it returns. Returns the number of optimizations.
+  pub fn optimize(&self) -> OperationResult<usize> {
+    let _optimization_guard = self.optimization_lock.lock();
+    let optimizers = self
+      .collection_config
+      .optimizers_config(&self.storage_config);
+    let mut count = 0;
+    while let Some(plan) = optimizers.plan(&self.segment_infos()) {
+      optimize(
+        &self.segments,
+        &self.path.join(SEGMENTS_PATH),
+        &plan,
+        |size_kb| {
+          self
+            .collection_config
+            .optimized_segment_config(&self.storage_config, size_kb)
+        },
+        self.storage_config.performance.async_scorer,
+        &self.optimizer_stopped,
+      )?;
+      count += 1;
+    }
+    Ok(count)
+  }
+}
+
+impl Drop for Collection {
+  fn drop(&mut self) {
+    self.stop_optimizer();
+    if let Err(err) = self.flush() {
+      log::error!("Flush of collection {} failed: {err}", self.id);
+    }
+  }
+}
+
+/// Scale the vector to unit length, zero vectors are left as they are
+fn normalize(vector: &mut [VectorElementType]) {
+  let length = vector.iter().map(|x| x * x).sum::<f32>().sqrt();
+  if length > 0.0 {
+    vector.iter_mut().for_each(|x| *x /= length);
+  }
+}
+
+fn flush_segments(segments: &SegmentHolder) -> OperationResult<()> {
+  for (_, segment) in segments.iter() {
+    segment.read().flush()?;
+  }
+  Ok(())
+}
+
+#[derive(Debug, Deserialize, Serialize, JsonSchema, Validate, Clone, PartialEq)]
 pub struct CollectionConfig {
+  /// Size of the vectors of the collection
+  #[validate(range(min = 1))]
+  pub vector_size: usize,
+  pub distance: Distance,
+  /// HNSW index of sealed segments. If not set, the storage default is used.
+  #[serde(default, skip_serializing_if = "Option::is_none")]
   #[validate]
-  pub hnsw_config: HnswConfig,
+  pub hnsw_config: Option<HnswConfig>,
+  /// Quantization of indexed vectors. If not set, the storage default is used.
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  #[validate]
+  pub quantization_config: Option<QuantizationConfig>,
+  /// Type of vector storage. If not set, the storage default is used.
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub vector_storage_type: Option<VectorStorageType>,
+  /// Segment optimization parameters. If not set, the storage default is used.
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  #[validate]
+  pub optimizers_config: Option<OptimizersConfig>,
+  /// Scale vectors of cosine collections to unit length when they are inserted, so stored
+  /// vectors can also be compared with dot product. Ignored for other distances.
+  #[serde(default)]
+  pub normalize: bool,
+}
+
+impl CollectionConfig {
+  pub fn normalizes_vectors(&self) -> bool {
+    self.normalize && self.distance == Distance::Cosine
+  }
+
+  pub fn vector_storage_type(&self, storage_config: &StorageConfig) -> VectorStorageType {
+    self
+      .vector_storage_type
+      .unwrap_or(storage_config.vector_storage_type)
+  }
+
+  pub fn quantization_config(&self, storage_config: &StorageConfig) -> Option<QuantizationConfig> {
+    self
+      .quantization_config
+      .clone()
+      .or_else(|| storage_config.quantization.clone())
+  }
+
+  pub fn hnsw_config(&self, storage_config: &StorageConfig) -> HnswConfig {
+    self
+      .hnsw_config
+      .clone()
+      .unwrap_or_else(|| storage_config.hnsw_index.clone())
+  }
+
+  pub fn optimizers_config(&self, storage_config: &StorageConfig) -> OptimizersConfig {
+    self
+      .optimizers_config
+      .clone()
+      .unwrap_or_else(|| storage_config.optimizers.clone())
+  }
+
+  /// Config of the segment which takes new points
+  pub fn appendable_segment_config(&self, storage_config: &StorageConfig) -> SegmentConfig {
+    let storage_type = match self.vector_storage_type(storage_config) {
+      // Plain memmap storage is read-only
+      VectorStorageType::Memmap => VectorStorageType::AppendableMemmap,
+      storage_type => storage_type,
+    };
+    SegmentConfig {
+      vector_size: self.vector_size,
+      distance: self.distance,
+      storage_type,
+      index: None,
+      quantization: None,
+      appendable: true,
+    }
+  }
+
+  /// Config of a sealed segment built by the optimizer, by the size of its vectors
+  pub fn optimized_segment_config(
+    &self,
+    storage_config: &StorageConfig,
+    size_kb: usize,
+  ) -> SegmentConfig {
+    let optimizers = self.optimizers_config(storage_config);
+    let storage_type = if self.vector_storage_type(storage_config) != VectorStorageType::Dense
+      || optimizers.should_memmap(size_kb)
+    {
+      VectorStorageType::Memmap
+    } else {
+      VectorStorageType::Dense
+    };
+    let index = optimizers.should_index(size_kb).then(|| {
+      let hnsw_config = self.hnsw_config(storage_config);
+      let points = size_kb * 1024 / (self.vector_size * std::mem::size_of::<VectorElementType>());
+      // `m` of the graph config is the expected number of elements, edges are `max_nb_connection`
+      HnswGraphConfig::new(
+        points.max(1000),
+        hnsw_config.ef_construct,
+        hnsw_config.ef_construct,
+        hnsw_config.m,
+        false,
+        16,
+        self.vector_size,
+        false,
+        false,
+        points,
+        100,
+        10,
+      )
+    });
+    SegmentConfig {
+      vector_size: self.vector_size,
+      distance: self.distance,
+      storage_type,
+      quantization: index
+        .as_ref()
+        .and_then(|_| self.quantization_config(storage_config)),
+      index,
+      appendable: false,
+    }
+  }
 }
 
 #[derive(Debug, Deserialize, Serialize, JsonSchema, Validate, Clone, PartialEq, Eq)]
@@ -49,6 +879,372 @@ pub struct HnswConfig {
   pub payload_m: Option<usize>,
 }
 
+impl Default for HnswConfig {
+  fn default() -> Self {
+    HnswConfig {
+      m: 16,
+      ef_construct: 100,
+      full_scan_threshold: 10_000,
+      max_indexing_threads: default_max_indexing_threads(),
+      on_disk: None,
+      payload_m: None,
+    }
+  }
+}
+
 const fn default_max_indexing_threads() -> usize {
   0
 }
+
+#[cfg(test)]
+mod tests {
+  use tempfile::Builder;
+
+  use super::*;
+  use crate::{common::validation::MAX_SEARCH_LIMIT, engine::storage::types::PerformanceConfig};
+
+  fn storage_config(path: &Path) -> Arc<StorageConfig> {
+    Arc::new(StorageConfig {
+      storage_path: path.to_string_lossy().into_owned(),
+      snapshots_path: path.join("snapshots").to_string_lossy().into_owned(),
+      temp_path: None,
+      on_disk_payload: false,
+      vector_storage_type: VectorStorageType::Dense,
+      performance: PerformanceConfig::default(),
+      min_available_memory_mb: None,
+      quantization: None,
+      hnsw_index: HnswConfig::default(),
+      optimizers: OptimizersConfig {
+        vacuum_min_vector_number: 10,
+        default_segment_number: 1,
+        indexing_threshold_kb: Some(1),
+        flush_interval_sec: 1,
+        ..Default::default()
+      },
+    })
+  }
+
+  fn point(id: u64, vector: Vec<f32>) -> PointStruct {
+    PointStruct {
+      id: Some(id.into()),
+      vector,
+      payload: None,
+    }
+  }
+
+  #[test]
+  fn test_optimized_collection_search() {
+    let dir = Builder::new().prefix("collection").tempdir().unwrap();
+    let storage_config = storage_config(dir.path());
+    let config = CollectionConfig {
+      vector_size: 4,
+      distance: Distance::Euclidean,
+      hnsw_config: None,
+      quantization_config: None,
+      vector_storage_type: None,
+      optimizers_config: None,
+      normalize: false,
+    };
+    let collection_path = dir.path().join("test");
+
+    {
+      let collection = Collection::create(
+        "test".to_string(),
+        &collection_path,
+        config,
+        storage_config.clone(),
+      )
+      .unwrap();
+      // 1 Kb of vectors makes a segment indexed
+      for batch in 0..4u64 {
+        let points = (0..100)
+          .map(|i| {
+            let id = batch * 100 + i;
+            point(id, vec![id as f32, 0.0, 0.0, 1.0])
+          })
+          .collect();
+        collection.upsert_points(points).unwrap();
+      }
+      assert!(matches!(
+        collection.upsert_points(vec![point(0, vec![1.0])]),
+        Err(OperationError::WrongVector { .. })
+      ));
+      assert_eq!(
+        collection.delete_points(&[41.into(), 1000.into()]).unwrap(),
+        1
+      );
+
+      // Wait for the optimizer to seal and index the segments
+      let deadline = Instant::now() + Duration::from_secs(30);
+      while !collection
+        .segment_infos()
+        .iter()
+        .any(|info| info.indexed && info.points_count > 0)
+      {
+        assert!(Instant::now() < deadline, "segments were not optimized");
+        thread::sleep(Duration::from_millis(50));
+      }
+
+      let ids = collection
+        .upsert_points(vec![PointStruct {
+          id: None,
+          vector: vec![41.0, 0.0, 0.0, 1.0],
+          payload: None,
+        }])
+        .unwrap();
+      assert_eq!(ids, vec![400.into()]);
+      assert_eq!(collection.points_count(), 400);
+      collection.close().unwrap();
+    }
+    let dumped_graph = std::fs::read_dir(collection_path.join(SEGMENTS_PATH))
+      .unwrap()
+      .any(|entry| {
+        let index_path = entry.unwrap().path().join("index");
+        index_path.join("graph.hnsw.graph").exists() && index_path.join("graph.hnsw.data").exists()
+      });
+    assert!(dumped_graph);
+
+    let collection =
+      Collection::load("test".to_string(), &collection_path, storage_config).unwrap();
+    assert_eq!(collection.points_count(), 400);
+    let info = collection.info().unwrap();
+    assert_eq!(info.points_count, 400);
+    assert_eq!(
+      info.deleted_ratio,
+      info.deleted_vectors_count as f64 / info.vectors_count as f64
+    );
+    assert!(info.indexed_vectors_count > 0);
+    let index = info
+      .segments
+      .iter()
+      .find_map(|segment| segment.telemetry.index.as_ref())
+      .unwrap();
+    assert_eq!(index.layer_points.len(), index.max_level + 1);
+    assert!(index.layer_points[0] > 0 && index.ram_bytes > 0);
+    assert!(info.ram_bytes >= index.ram_bytes);
+    let result = collection.search(&[41.2, 0.0, 0.0, 1.0], 3).unwrap();
+    let ids: Vec<_> = result.iter().map(|point| point.id).collect();
+    assert_eq!(ids, vec![400.into(), 42.into(), 40.into()]);
+  }
+
+  #[test]
+  fn test_collection_vacuum() {
+    let dir = Builder::new().prefix("collection").tempdir().unwrap();
+    let storage_config = storage_config(dir.path());
+    let config = CollectionConfig {
+      vector_size: 4,
+      distance: Distance::Euclidean,
+      hnsw_config: None,
+      quantization_config: None,
+      vector_storage_type: None,
+      optimizers_config: Some(OptimizersConfig {
+        max_optimization_threads: 0,
+        ..Default::default()
+      }),
+      normalize: false,
+    };
+    let collection_path = dir.path().join("test");
+
+    {
+      let collection = Collection::create(
+        "test".to_string(),
+        &collection_path,
+        config,
+        storage_config.clone(),
+      )
+      .unwrap();
+      let points = (0..20u64)
+        .map(|id| point(id, vec![id as f32, 0.0, 0.0, 1.0]))
+        .collect();
+      collection.upsert_points(points).unwrap();
+      let deleted: Vec<PointIdType> = (0..20u64).step_by(2).map(Into::into).collect();
+      assert_eq!(collection.delete_points(&deleted).unwrap(), 10);
+
+      let report = collection.vacuum().unwrap();
+      assert_eq!(
+        report,
+        VacuumReport {
+          segments: 1,
+          reclaimed_vectors: 10,
+        }
+      );
+      let infos = collection.segment_infos();
+      assert!(infos.iter().all(|info| info.deleted_vector_count == 0));
+      assert_eq!(
+        infos
+          .iter()
+          .map(|info| info.total_vector_count)
+          .sum::<usize>(),
+        10
+      );
+      assert_eq!(collection.points_count(), 10);
+      assert_eq!(collection.vacuum().unwrap(), VacuumReport::default());
+    }
+
+    let collection =
+      Collection::load("test".to_string(), &collection_path, storage_config).unwrap();
+    assert_eq!(collection.points_count(), 10);
+    let result = collection.search(&[4.2, 0.0, 0.0, 1.0], 2).unwrap();
+    let ids: Vec<_> = result.iter().map(|point| point.id).collect();
+    assert_eq!(ids, vec![5.into(), 3.into()]);
+  }
+
+  #[test]
+  fn test_upsert_replaces_indexed_points() {
+    let dir = Builder::new().prefix("collection").tempdir().unwrap();
+    let config = CollectionConfig {
+      vector_size: 4,
+      distance: Distance::Euclidean,
+      hnsw_config: None,
+      quantization_config: None,
+      vector_storage_type: None,
+      optimizers_config: Some(OptimizersConfig {
+        max_optimization_threads: 0,
+        indexing_threshold_kb: Some(1),
+        ..Default::default()
+      }),
+      normalize: false,
+    };
+    let collection = Collection::create(
+      "test".to_string(),
+      &dir.path().join("test"),
+      config,
+      storage_config(dir.path()),
+    )
+    .unwrap();
+    let points = (0..100u64)
+      .map(|id| point(id, vec![id as f32, 0.0, 0.0, 1.0]))
+      .collect();
+    collection.upsert_points(points).unwrap();
+    assert!(collection.optimize().unwrap() > 0);
+    assert!(collection
+      .segment_infos()
+      .iter()
+      .any(|info| info.indexed && info.points_count == 100));
+
+    // Move a batch of indexed points far away
+    let points = (10..30u64)
+      .map(|id| point(id, vec![id as f32 + 1000.0, 0.0, 0.0, 1.0]))
+      .collect();
+    collection.upsert_points(points).unwrap();
+    assert_eq!(collection.points_count(), 100);
+    let infos = collection.segment_infos();
+    let indexed = infos.iter().find(|info| info.indexed).unwrap();
+    assert_eq!(indexed.deleted_vector_count, 20);
+    assert_eq!(indexed.points_count, 80);
+
+    let result = collection.search(&[20.2, 0.0, 0.0, 1.0], 2).unwrap();
+    let ids: Vec<_> = result.iter().map(|point| point.id).collect();
+    assert_eq!(ids, vec![30.into(), 31.into()]);
+    let result = collection.search(&[1020.2, 0.0, 0.0, 1.0], 2).unwrap();
+    let ids: Vec<_> = result.iter().map(|point| point.id).collect();
+    assert_eq!(ids, vec![20.into(), 21.into()]);
+  }
+
+  #[test]
+  fn test_vector_validation_and_normalization() {
+    let dir = Builder::new().prefix("collection").tempdir().unwrap();
+    let config = CollectionConfig {
+      vector_size: 2,
+      distance: Distance::Cosine,
+      hnsw_config: None,
+      quantization_config: None,
+      vector_storage_type: None,
+      optimizers_config: None,
+      normalize: true,
+    };
+    let collection = Collection::create(
+      "test".to_string(),
+      &dir.path().join("test"),
+      config,
+      storage_config(dir.path()),
+    )
+    .unwrap();
+    collection
+      .upsert_points(vec![point(1, vec![3.0, 4.0]), point(2, vec![0.0, 0.0])])
+      .unwrap();
+    assert_eq!(collection.point_vector(1.into()).unwrap(), vec![0.6, 0.8]);
+    assert_eq!(collection.point_vector(2.into()).unwrap(), vec![0.0, 0.0]);
+
+    assert!(matches!(
+      collection.upsert_points(vec![point(3, vec![1.0, f32::NAN])]),
+      Err(OperationError::ValidationError { .. })
+    ));
+    assert!(matches!(
+      collection.search(&[1.0, 2.0, 3.0], 1),
+      Err(OperationError::WrongVector {
+        expected_dim: 2,
+        received_dim: 3
+      })
+    ));
+    assert!(matches!(
+      collection.search(&[f32::INFINITY, 0.0], 1),
+      Err(OperationError::ValidationError { .. })
+    ));
+    for k in [0, MAX_SEARCH_LIMIT + 1] {
+      assert!(matches!(
+        collection.search(&[1.0, 0.0], k),
+        Err(OperationError::ValidationError { .. })
+      ));
+      assert!(matches!(
+        collection.recommend(&[1.into()], &[], k),
+        Err(OperationError::ValidationError { .. })
+      ));
+    }
+  }
+
+  #[test]
+  fn test_recommend_and_snapshot() {
+    let dir = Builder::new().prefix("collection").tempdir().unwrap();
+    let storage_config = storage_config(dir.path());
+    let config = CollectionConfig {
+      vector_size: 2,
+      distance: Distance::Euclidean,
+      hnsw_config: None,
+      quantization_config: None,
+      // Memory mapped vectors are plain files, archived as they are
+      vector_storage_type: Some(VectorStorageType::AppendableMemmap),
+      optimizers_config: None,
+      normalize: false,
+    };
+    let collection = Collection::create(
+      "test".to_string(),
+      &dir.path().join("test"),
+      config,
+      storage_config.clone(),
+    )
+    .unwrap();
+    let points = (0..10u64)
+      .map(|id| point(id, vec![id as f32, 0.0]))
+      .collect();
+    collection.upsert_points(points).unwrap();
+
+    // Query is 4 + (4 - 2) = 6
+    let result = collection.recommend(&[4.into()], &[2.into()], 2).unwrap();
+    let ids: Vec<_> = result.iter().map(|point| point.id).collect();
+    assert_eq!(ids, vec![6.into(), 5.into()]);
+    // Examples are not recommended
+    let result = collection.recommend(&[4.into(), 6.into()], &[], 2).unwrap();
+    let ids: Vec<_> = result.iter().map(|point| point.id).collect();
+    assert_eq!(ids[0], 5.into());
+    assert!(!ids.contains(&4.into()) && !ids.contains(&6.into()));
+    assert!(matches!(
+      collection.recommend(&[42.into()], &[], 2),
+      Err(OperationError::PointIdError { .. })
+    ));
+    assert!(matches!(
+      collection.recommend(&[], &[1.into()], 2),
+      Err(OperationError::ValidationError { .. })
+    ));
+
+    let snapshots_dir = dir.path().join("snapshots").join("test");
+    let snapshot = collection.create_snapshot(&snapshots_dir).unwrap();
+    assert!(snapshot.size > 0);
+    let restored = dir.path().join("restored");
+    tar::Archive::new(std::fs::File::open(snapshots_dir.join(&snapshot.name)).unwrap())
+      .unpack(&restored)
+      .unwrap();
+    let restored = Collection::load("restored".to_string(), &restored, storage_config).unwrap();
+    assert_eq!(restored.points_count(), 10);
+  }
+}
@@ -0,0 +1,296 @@
+use std::{
+  cmp::Reverse,
+  collections::BTreeMap,
+  path::{Path, PathBuf},
+  sync::{
+    atomic::{AtomicBool, Ordering},
+    Arc,
+  },
+  time::{Duration, Instant},
+};
+
+use chrono::{DateTime, Utc};
+use io::file_operations::{atomic_save_json, read_json};
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use super::collections::CollectionId;
+use crate::common::operation_error::{OperationError, OperationResult};
+
+const JOBS_FILE: &str = "jobs.json";
+
+/// Finished jobs kept for their status, older ones are forgotten
+const MAX_FINISHED_JOBS: usize = 100;
+
+/// Progress of running jobs is saved at most this often, state changes are saved right away
+const PROGRESS_SAVE_INTERVAL: Duration = Duration::from_secs(5);
+
+const INTERRUPTED_MESSAGE: &str = "interrupted by a restart of the service";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum JobStatus {
+  Running,
+  Completed,
+  Failed,
+  Cancelled,
+}
+
+/// State of an import running in the background, as reported by `GET /jobs/{id}`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct JobInfo {
+  pub id: Uuid,
+  pub collection: CollectionId,
+  pub status: JobStatus,
+  /// Points upserted so far
+  pub ingested: usize,
+  /// Points of the dataset, if the format tells it before the end of the file
+  pub total: Option<usize>,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub error: Option<String>,
+  pub started_at: DateTime<Utc>,
+  pub finished_at: Option<DateTime<Utc>>,
+  /// Ingested points per second, from the start to the last progress
+  pub points_per_second: f64,
+}
+
+impl JobInfo {
+  fn update_throughput(&mut self, now: DateTime<Utc>) {
+    let seconds = (now - self.started_at).num_milliseconds() as f64 / 1000.0;
+    self.points_per_second = if seconds > 0.0 {
+      self.ingested as f64 / seconds
+    } else {
+      0.0
+    };
+  }
+}
+
+struct Job {
+  info: JobInfo,
+  /// Stop flag checked by the import between batches
+  stopped: Arc<AtomicBool>,
+  /// Last time the job was saved, to throttle the saves of its progress
+  saved_at: Instant,
+}
+
+/// Background jobs of the service, persisted in the storage directory
+///
+/// Jobs still running when the service stops are reported as failed after a restart.
+pub struct Jobs {
+  path: PathBuf,
+  jobs: Mutex<BTreeMap<Uuid, Job>>,
+}
+
+impl Jobs {
+  pub fn load(storage_path: &Path) -> OperationResult<Self> {
+    let path = storage_path.join(JOBS_FILE);
+    let saved: Vec<JobInfo> = if path.exists() {
+      read_json(&path)?
+    } else {
+      Vec::new()
+    };
+    let now = Utc::now();
+    let jobs = saved
+      .into_iter()
+      .map(|mut info| {
+        if info.status == JobStatus::Running {
+          log::warn!("Import job {} was {INTERRUPTED_MESSAGE}", info.id);
+          info.status = JobStatus::Failed;
+          info.error = Some(INTERRUPTED_MESSAGE.to_string());
+          info.finished_at = Some(now);
+        }
+        let job = Job {
+          info,
+          stopped: Arc::new(AtomicBool::new(true)),
+          saved_at: Instant::now(),
+        };
+        (job.info.id, job)
+      })
+      .collect();
+    let jobs = Jobs {
+      path,
+      jobs: Mutex::new(jobs),
+    };
+    jobs.save(&jobs.jobs.lock())?;
+    Ok(jobs)
+  }
+
+  fn save(&self, jobs: &BTreeMap<Uuid, Job>) -> OperationResult<()> {
+    let infos: Vec<_> = jobs.values().map(|job| &job.info).collect();
+    atomic_save_json(&self.path, &infos)?;
+    Ok(())
+  }
+
+  /// Progress is kept in memory if it can not be saved, the job goes on
+  fn save_or_log(&self, jobs: &BTreeMap<Uuid, Job>) {
+    if let Err(err) = self.save(jobs) {
+      log::error!("Failed to save the state of the jobs: {err}");
+    }
+  }
+
+  /// Register a running job, returns it with the flag which stops it
+  pub fn start(&self, collection_name: &str) -> OperationResult<(JobInfo, Arc<AtomicBool>)> {
+    let info = JobInfo {
+      id: Uuid::new_v4(),
+      collection: collection_name.to_string(),
+      status: JobStatus::Running,
+      ingested: 0,
+      total: None,
+      error: None,
+      started_at: Utc::now(),
+      finished_at: None,
+      points_per_second: 0.0,
+    };
+    let stopped = Arc::new(AtomicBool::new(false));
+    let mut jobs = self.jobs.lock();
+    jobs.insert(
+      info.id,
+      Job {
+        info: info.clone(),
+        stopped: stopped.clone(),
+        saved_at: Instant::now(),
+      },
+    );
+    Self::prune(&mut jobs);
+    self.save(&jobs)?;
+    Ok((info, stopped))
+  }
+
+  fn prune(jobs: &mut BTreeMap<Uuid, Job>) {
+    let mut finished: Vec<_> = jobs
+      .values()
+      .filter_map(|job| Some((job.info.finished_at?, job.info.id)))
+      .collect();
+    if finished.len() <= MAX_FINISHED_JOBS {
+      return;
+    }
+    finished.sort();
+    for (_, id) in &finished[..finished.len() - MAX_FINISHED_JOBS] {
+      jobs.remove(id);
+    }
+  }
+
+  /// Update the progress of a running job, a restart reports the last saved progress
+  pub fn progress(&self, id: Uuid, ingested: usize, total: Option<usize>) {
+    let mut jobs = self.jobs.lock();
+    let Some(job) = jobs.get_mut(&id) else {
+      return;
+    };
+    job.info.ingested = ingested;
+    job.info.total = total;
+    job.info.update_throughput(Utc::now());
+    if job.saved_at.elapsed() < PROGRESS_SAVE_INTERVAL {
+      return;
+    }
+    job.saved_at = Instant::now();
+    self.save_or_log(&jobs);
+  }
+
+  /// Record the result of the job, imports stopped by [`Self::cancel`] are cancelled
+  pub fn finish(&self, id: Uuid, result: &OperationResult<usize>) {
+    let mut jobs = self.jobs.lock();
+    if let Some(job) = jobs.get_mut(&id) {
+      let now = Utc::now();
+      match result {
+        Ok(ingested) => {
+          job.info.status = JobStatus::Completed;
+          job.info.ingested = *ingested;
+        }
+        Err(OperationError::Cancelled { .. }) => job.info.status = JobStatus::Cancelled,
+        Err(err) => {
+          job.info.status = JobStatus::Failed;
+          job.info.error = Some(err.to_string());
+        }
+      }
+      job.info.finished_at = Some(now);
+      job.info.update_throughput(now);
+    }
+    self.save_or_log(&jobs);
+  }
+
+  pub fn get(&self, id: Uuid) -> OperationResult<JobInfo> {
+    self
+      .jobs
+      .lock()
+      .get(&id)
+      .map(|job| job.info.clone())
+      .ok_or_else(|| job_not_found(id))
+  }
+
+  /// All known jobs, the most recent first
+  pub fn list(&self) -> Vec<JobInfo> {
+    let mut infos: Vec<_> = self
+      .jobs
+      .lock()
+      .values()
+      .map(|job| job.info.clone())
+      .collect();
+    infos.sort_by_key(|info| Reverse(info.started_at));
+    infos
+  }
+
+  /// Ask a running job to stop after its current batch, finished jobs are left as they are
+  pub fn cancel(&self, id: Uuid) -> OperationResult<JobInfo> {
+    let jobs = self.jobs.lock();
+    let job = jobs.get(&id).ok_or_else(|| job_not_found(id))?;
+    job.stopped.store(true, Ordering::Relaxed);
+    Ok(job.info.clone())
+  }
+}
+
+fn job_not_found(id: Uuid) -> OperationError {
+  OperationError::NotFound {
+    description: format!("Job {id} does not exist"),
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use tempfile::Builder;
+
+  use super::*;
+  use crate::common::operation_error::check_process_stopped;
+
+  #[test]
+  fn test_jobs() {
+    let dir = Builder::new().prefix("jobs").tempdir().unwrap();
+    let jobs = Jobs::load(dir.path()).unwrap();
+    let (done, _) = jobs.start("test").unwrap();
+    jobs.progress(done.id, 10, Some(20));
+    let info = jobs.get(done.id).unwrap();
+    assert_eq!(info.status, JobStatus::Running);
+    assert_eq!((info.ingested, info.total), (10, Some(20)));
+    jobs.finish(done.id, &Ok(20));
+    assert_eq!(jobs.get(done.id).unwrap().status, JobStatus::Completed);
+
+    let (cancelled, stopped) = jobs.start("test").unwrap();
+    assert!(check_process_stopped(&stopped).is_ok());
+    jobs.cancel(cancelled.id).unwrap();
+    let result = check_process_stopped(&stopped).map(|()| 0);
+    jobs.finish(cancelled.id, &result);
+    assert_eq!(jobs.get(cancelled.id).unwrap().status, JobStatus::Cancelled);
+
+    let (interrupted, _) = jobs.start("test").unwrap();
+    jobs.progress(interrupted.id, 5, None);
+    // Saved as the interval since the last save is over
+    jobs.jobs.lock().get_mut(&interrupted.id).unwrap().saved_at -= PROGRESS_SAVE_INTERVAL;
+    jobs.progress(interrupted.id, 7, None);
+    // Kept in memory only, until the next save
+    jobs.progress(interrupted.id, 9, None);
+    assert_eq!(jobs.get(interrupted.id).unwrap().ingested, 9);
+    assert!(jobs.get(Uuid::new_v4()).is_err());
+    assert_eq!(jobs.list().len(), 3);
+    assert_eq!(jobs.list()[0].id, interrupted.id);
+    drop(jobs);
+
+    // Jobs running when the service stopped have failed
+    let jobs = Jobs::load(dir.path()).unwrap();
+    assert_eq!(jobs.get(done.id).unwrap().status, JobStatus::Completed);
+    let info = jobs.get(interrupted.id).unwrap();
+    assert_eq!(info.status, JobStatus::Failed);
+    assert_eq!(info.ingested, 7);
+    assert_eq!(info.error.as_deref(), Some(INTERRUPTED_MESSAGE));
+    assert!(info.finished_at.is_some());
+  }
+}
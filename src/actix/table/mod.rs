@@ -1,2 +1,4 @@
 pub mod collections;
-mod toc;
+pub mod jobs;
+pub mod snapshots;
+pub mod toc;
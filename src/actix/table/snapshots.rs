@@ -0,0 +1,156 @@
+use std::{
+  fs::{self, File},
+  io::{BufWriter, Write},
+  path::{Path, PathBuf},
+};
+
+use chrono::{DateTime, NaiveDateTime, Utc};
+use schemars::JsonSchema;
+use serde::Serialize;
+
+use crate::common::operation_error::{OperationError, OperationResult};
+
+const SNAPSHOT_EXTENSION: &str = "snapshot";
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, JsonSchema)]
+pub struct SnapshotDescription {
+  pub name: String,
+  pub creation_time: Option<NaiveDateTime>,
+  /// Size of the snapshot file in bytes
+  pub size: u64,
+}
+
+/// Unique name of a new snapshot of the collection
+pub fn new_snapshot_name(collection_name: &str) -> String {
+  let time = Utc::now().format("%Y-%m-%d-%H-%M-%S-%3f");
+  format!("{collection_name}-{time}.{SNAPSHOT_EXTENSION}")
+}
+
+/// Archive the content of `source` into a tar file at `snapshot_path`
+///
+/// The archive is written to a temporary file first, so partial snapshots are never listed.
+pub fn archive_dir(source: &Path, snapshot_path: &Path) -> OperationResult<()> {
+  let tmp_path = snapshot_path.with_extension("tmp");
+  let result = (|| -> OperationResult<()> {
+    let mut builder = tar::Builder::new(BufWriter::new(File::create(&tmp_path)?));
+    builder.follow_symlinks(false);
+    builder.append_dir_all(".", source)?;
+    let mut writer = builder.into_inner()?;
+    writer.flush()?;
+    writer.get_ref().sync_all()?;
+    Ok(())
+  })();
+  if let Err(err) = result {
+    let _ = fs::remove_file(&tmp_path);
+    return Err(err);
+  }
+  fs::rename(&tmp_path, snapshot_path)?;
+  Ok(())
+}
+
+pub fn describe_snapshot(path: &Path) -> OperationResult<SnapshotDescription> {
+  let metadata = fs::metadata(path)?;
+  let creation_time = metadata
+    .created()
+    .or_else(|_| metadata.modified())
+    .ok()
+    .map(|time| DateTime::<Utc>::from(time).naive_utc());
+  Ok(SnapshotDescription {
+    name: path
+      .file_name()
+      .map(|name| name.to_string_lossy().into_owned())
+      .unwrap_or_default(),
+    creation_time,
+    size: metadata.len(),
+  })
+}
+
+/// Snapshots in `snapshots_dir`, sorted by name
+pub fn list_snapshots(snapshots_dir: &Path) -> OperationResult<Vec<SnapshotDescription>> {
+  if !snapshots_dir.exists() {
+    return Ok(Vec::new());
+  }
+  let mut snapshots = Vec::new();
+  for entry in fs::read_dir(snapshots_dir)? {
+    let path = entry?.path();
+    if path.is_file()
+      && path
+        .extension()
+        .is_some_and(|ext| ext == SNAPSHOT_EXTENSION)
+    {
+      snapshots.push(describe_snapshot(&path)?);
+    }
+  }
+  snapshots.sort_by(|a, b| a.name.cmp(&b.name));
+  Ok(snapshots)
+}
+
+/// Path of an existing snapshot, names pointing outside of `snapshots_dir` are rejected
+pub fn snapshot_path(snapshots_dir: &Path, snapshot_name: &str) -> OperationResult<PathBuf> {
+  let valid_name = !snapshot_name.contains(['/', '\\'])
+    && Path::new(snapshot_name)
+      .extension()
+      .is_some_and(|ext| ext == SNAPSHOT_EXTENSION);
+  if !valid_name {
+    return Err(OperationError::ValidationError {
+      description: format!("Invalid snapshot name {snapshot_name:?}"),
+    });
+  }
+  let path = snapshots_dir.join(snapshot_name);
+  if !path.is_file() {
+    return Err(OperationError::NotFound {
+      description: format!("Snapshot {snapshot_name} does not exist"),
+    });
+  }
+  Ok(path)
+}
+
+#[cfg(test)]
+mod tests {
+  use tempfile::Builder;
+
+  use super::*;
+
+  #[test]
+  fn test_snapshot_files() {
+    let dir = Builder::new().prefix("snapshots").tempdir().unwrap();
+    let source = dir.path().join("source");
+    fs::create_dir_all(source.join("segments")).unwrap();
+    fs::write(source.join("config.json"), "{}").unwrap();
+    fs::write(source.join("segments").join("data"), [1u8; 100]).unwrap();
+    let snapshots_dir = dir.path().join("snapshots");
+    fs::create_dir_all(&snapshots_dir).unwrap();
+
+    let name = new_snapshot_name("test");
+    archive_dir(&source, &snapshots_dir.join(&name)).unwrap();
+    let snapshots = list_snapshots(&snapshots_dir).unwrap();
+    assert_eq!(snapshots.len(), 1);
+    assert_eq!(snapshots[0].name, name);
+
+    let path = snapshot_path(&snapshots_dir, &name).unwrap();
+    let mut archive = tar::Archive::new(File::open(path).unwrap());
+    let mut files: Vec<_> = archive
+      .entries()
+      .unwrap()
+      .map(|entry| entry.unwrap().path().unwrap().into_owned())
+      .filter(|path| path.extension().is_some() || path.ends_with("data"))
+      .collect();
+    files.sort();
+    assert_eq!(
+      files,
+      [PathBuf::from("config.json"), PathBuf::from("segments/data")]
+    );
+
+    assert!(matches!(
+      snapshot_path(&snapshots_dir, "../source/config.json"),
+      Err(OperationError::ValidationError { .. })
+    ));
+    assert!(matches!(
+      snapshot_path(&snapshots_dir, "missing.snapshot"),
+      Err(OperationError::NotFound { .. })
+    ));
+    assert!(list_snapshots(&dir.path().join("missing"))
+      .unwrap()
+      .is_empty());
+  }
+}
@@ -1,31 +1,701 @@
-use std::sync::Arc;
+use std::{
+  collections::BTreeMap,
+  fs::{create_dir_all, read_dir, remove_dir_all, remove_file},
+  path::{Path, PathBuf},
+  sync::Arc,
+  time::Duration,
+};
 
-use tokio::{runtime::Runtime, sync::RwLock};
+use parking_lot::Mutex;
+use serde::Serialize;
+use tokio::sync::RwLock;
 
-use crate::{common::operation_error::OperationResult, engine::storage::types::StorageConfig};
+use crate::{
+  actix::model::points::PointStruct,
+  common::{
+    memory_guard::MemoryGuard,
+    operation_error::{OperationError, OperationResult},
+    point_id::PointIdType,
+  },
+  engine::{
+    segments::segment::ScoredPoint, storage::types::StorageConfig, types::types::VectorElementType,
+  },
+};
 
-use super::collections::Collections;
+use super::{
+  collections::{
+    Collection, CollectionConfig, CollectionId, CollectionInfo, CollectionMetrics, Collections,
+    VacuumReport, VectorSink,
+  },
+  jobs::Jobs,
+  snapshots::{list_snapshots, snapshot_path, SnapshotDescription},
+};
 
+const COLLECTIONS_DIR: &str = "collections";
+
+/// Loading state of a collection found in the storage directory
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LoadingStatus {
+  Pending,
+  Loading,
+  Loaded,
+  Failed,
+}
+
+/// Loading progress of a collection, reported by the readiness probe
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct CollectionLoading {
+  pub status: LoadingStatus,
+  /// Segments whose storages and HNSW graphs are restored
+  pub loaded_segments: usize,
+  pub total_segments: usize,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub error: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Readiness {
+  /// No collection of the storage directory is still loading
+  pub ready: bool,
+  /// Collections which failed to load, they are reported but do not block the readiness
+  pub failed: Vec<CollectionId>,
+  pub collections: BTreeMap<CollectionId, CollectionLoading>,
+}
+
+/// All collections of the service
 pub struct TableOfContent {
-  // collections: Arc<RwLock<Collections>>,
+  collections: Arc<RwLock<Collections>>,
   pub(super) storage_config: Arc<StorageConfig>,
+  /// Collections found in the storage directory on startup, with their loading progress
+  loading: Mutex<BTreeMap<CollectionId, CollectionLoading>>,
+  memory_guard: Option<MemoryGuard>,
+  jobs: Jobs,
 }
 
 impl TableOfContent {
-  pub fn new(collections: Arc<RwLock<Collections>>, storage_config: Arc<StorageConfig>) -> Self {
-    todo!()
+  /// Load all collections from the storage directory
+  pub fn new(storage_config: Arc<StorageConfig>) -> OperationResult<Self> {
+    let toc = Self::open(storage_config)?;
+    let mut collections = Collections::new();
+    for name in toc.pending_collections() {
+      let collection = toc.load_collection(&name)?;
+      collections.insert(name.clone(), Arc::new(collection));
+      toc.update_loading(&name, |loading| loading.status = LoadingStatus::Loaded);
+    }
+    Ok(TableOfContent {
+      collections: Arc::new(RwLock::new(collections)),
+      ..toc
+    })
+  }
+
+  /// Find the collections of the storage directory without loading them, see
+  /// [`Self::load_collections`]
+  pub fn open(storage_config: Arc<StorageConfig>) -> OperationResult<Self> {
+    let collections_path = Path::new(&storage_config.storage_path).join(COLLECTIONS_DIR);
+    create_dir_all(&collections_path)?;
+
+    let mut loading = BTreeMap::new();
+    for entry in read_dir(&collections_path)? {
+      let collection_path = entry?.path();
+      if !collection_path.is_dir() {
+        continue;
+      }
+      let Some(name) = collection_path.file_name().and_then(|name| name.to_str()) else {
+        continue;
+      };
+      let pending = CollectionLoading {
+        status: LoadingStatus::Pending,
+        loaded_segments: 0,
+        total_segments: 0,
+        error: None,
+      };
+      loading.insert(name.to_string(), pending);
+    }
+
+    Ok(TableOfContent {
+      collections: Default::default(),
+      memory_guard: storage_config.min_available_memory_mb.map(MemoryGuard::new),
+      jobs: Jobs::load(Path::new(&storage_config.storage_path))?,
+      storage_config,
+      loading: Mutex::new(loading),
+    })
+  }
+
+  /// Load the collections found by [`Self::open`] one by one, each collection becomes
+  /// available once it is loaded
+  ///
+  /// Collections which fail to load are reported by [`Self::readiness`], the other ones are
+  /// loaded anyway.
+  pub async fn load_collections(self: Arc<Self>) {
+    for name in self.pending_collections() {
+      let toc = self.clone();
+      let loading_name = name.clone();
+      let loaded = tokio::task::spawn_blocking(move || toc.load_collection(&loading_name))
+        .await
+        .unwrap_or_else(|err| Err(OperationError::service_error(err.to_string())));
+      match loaded {
+        Ok(collection) => {
+          self
+            .collections
+            .write()
+            .await
+            .insert(name.clone(), Arc::new(collection));
+          self.update_loading(&name, |loading| loading.status = LoadingStatus::Loaded);
+        }
+        Err(err) => {
+          log::error!("Failed to load collection {name}: {err}");
+          self.update_loading(&name, |loading| {
+            loading.status = LoadingStatus::Failed;
+            loading.error = Some(err.to_string());
+          });
+        }
+      }
+    }
+    log::info!("Loading of collections finished");
+  }
+
+  fn pending_collections(&self) -> Vec<CollectionId> {
+    self
+      .loading
+      .lock()
+      .iter()
+      .filter(|(_, loading)| loading.status == LoadingStatus::Pending)
+      .map(|(name, _)| name.clone())
+      .collect()
+  }
+
+  fn update_loading(&self, collection_name: &str, update: impl FnOnce(&mut CollectionLoading)) {
+    if let Some(loading) = self.loading.lock().get_mut(collection_name) {
+      update(loading);
+    }
+  }
+
+  fn load_collection(&self, collection_name: &str) -> OperationResult<Collection> {
+    log::info!("Loading collection {collection_name}");
+    self.update_loading(collection_name, |loading| {
+      loading.status = LoadingStatus::Loading
+    });
+    Collection::load_with_progress(
+      collection_name.to_string(),
+      &self.collection_path(collection_name),
+      self.storage_config.clone(),
+      |loaded, total| {
+        self.update_loading(collection_name, |loading| {
+          loading.loaded_segments = loaded;
+          loading.total_segments = total;
+        })
+      },
+    )
+  }
+
+  /// Loading progress of the collections found on startup
+  pub fn readiness(&self) -> Readiness {
+    let collections = self.loading.lock().clone();
+    let failed = collections
+      .iter()
+      .filter(|(_, loading)| loading.status == LoadingStatus::Failed)
+      .map(|(name, _)| name.clone())
+      .collect();
+    Readiness {
+      ready: collections.values().all(|loading| {
+        matches!(
+          loading.status,
+          LoadingStatus::Loaded | LoadingStatus::Failed
+        )
+      }),
+      failed,
+      collections,
+    }
+  }
+
+  /// The collections are not locked up by a stuck operation
+  pub async fn is_responsive(&self, timeout: Duration) -> bool {
+    tokio::time::timeout(timeout, self.collections.read())
+      .await
+      .is_ok()
+  }
+
+  /// Error of collections which are not loaded yet, or failed to load
+  ///
+  /// Loading collections are unavailable until they are loaded, the error of collections which
+  /// failed to load is built by `failed`.
+  fn loading_error(
+    &self,
+    collection_name: &str,
+    failed: impl FnOnce(String) -> OperationError,
+  ) -> Option<OperationError> {
+    match self.loading.lock().get(collection_name)?.status {
+      LoadingStatus::Pending | LoadingStatus::Loading => Some(OperationError::Unavailable {
+        description: format!("Collection {collection_name} is still loading"),
+      }),
+      LoadingStatus::Failed => Some(failed(format!(
+        "Collection {collection_name} failed to load"
+      ))),
+      LoadingStatus::Loaded => None,
+    }
+  }
+
+  /// Collections which are not loaded yet, or failed to load, can not be created or deleted
+  fn check_loaded(&self, collection_name: &str) -> OperationResult<()> {
+    let error = self.loading_error(collection_name, |description| {
+      OperationError::ValidationError { description }
+    });
+    match error {
+      Some(error) => Err(error),
+      None => Ok(()),
+    }
   }
 
-  pub fn insert_vector(
+  fn collection_path(&self, collection_name: &str) -> PathBuf {
+    Path::new(&self.storage_config.storage_path)
+      .join(COLLECTIONS_DIR)
+      .join(collection_name)
+  }
+
+  fn snapshots_dir(&self, collection_name: &str) -> PathBuf {
+    Path::new(&self.storage_config.snapshots_path).join(collection_name)
+  }
+
+  fn get_collection<'a>(
     &self,
+    collections: &'a Collections,
     collection_name: &str,
-    vector_id: u64,
-    vector: Vec<f32>,
+  ) -> OperationResult<&'a Arc<Collection>> {
+    let not_found = |description| OperationError::NotFound { description };
+    collections.get(collection_name).ok_or_else(|| {
+      self
+        .loading_error(collection_name, not_found)
+        .unwrap_or_else(|| not_found(format!("Collection {collection_name} does not exist")))
+    })
+  }
+
+  pub async fn list_collections(&self) -> Vec<CollectionId> {
+    let mut names: Vec<_> = self.collections.read().await.keys().cloned().collect();
+    names.sort();
+    names
+  }
+
+  pub async fn create_collection(
+    &self,
+    collection_name: &str,
+    config: CollectionConfig,
   ) -> OperationResult<()> {
-    todo!()
+    let valid_name = !collection_name.is_empty()
+      && collection_name
+        .chars()
+        .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-');
+    if !valid_name {
+      return Err(OperationError::ValidationError {
+        description: format!(
+          "Collection name {collection_name:?} may only contain letters, digits, '_' and '-'"
+        ),
+      });
+    }
+
+    self.check_loaded(collection_name)?;
+    let mut collections = self.collections.write().await;
+    if collections.contains_key(collection_name) {
+      return Err(OperationError::ValidationError {
+        description: format!("Collection {collection_name} already exists"),
+      });
+    }
+    let collection = Collection::create(
+      collection_name.to_string(),
+      &self.collection_path(collection_name),
+      config,
+      self.storage_config.clone(),
+    )?;
+    collections.insert(collection_name.to_string(), Arc::new(collection));
+    Ok(())
+  }
+
+  /// Returns false if there was no such collection
+  ///
+  /// Collections which failed to load are deleted as well, so they can be created again.
+  pub async fn delete_collection(&self, collection_name: &str) -> OperationResult<bool> {
+    let failed = self
+      .loading
+      .lock()
+      .get(collection_name)
+      .is_some_and(|loading| loading.status == LoadingStatus::Failed);
+    if failed {
+      remove_dir_all(self.collection_path(collection_name))?;
+      self.loading.lock().remove(collection_name);
+      return Ok(true);
+    }
+    self.check_loaded(collection_name)?;
+    let removed = self.collections.write().await.remove(collection_name);
+    let Some(collection) = removed else {
+      return Ok(false);
+    };
+    self.loading.lock().remove(collection_name);
+    let collection = Self::release(collection).await;
+    let path = collection.path().to_owned();
+    // Stop the optimizer and close the storages before removing files
+    drop(collection);
+    remove_dir_all(path)?;
+    Ok(true)
+  }
+
+  /// Close all collections, so their storages are flushed and released before the process exits
+  ///
+  /// Requests arriving afterwards do not find any collection.
+  pub async fn shutdown(&self) -> OperationResult<()> {
+    let collections = std::mem::take(&mut *self.collections.write().await);
+    let mut result = Ok(());
+    for (name, collection) in collections {
+      log::info!("Closing collection {name}");
+      if let Err(err) = Self::release(collection).await.close() {
+        log::error!("Failed to close collection {name}: {err}");
+        result = Err(err);
+      }
+    }
+    result
+  }
+
+  /// Take the collection out of the operations still sharing it, which are cancelled
+  async fn release(mut collection: Arc<Collection>) -> Collection {
+    loop {
+      match Arc::try_unwrap(collection) {
+        Ok(collection) => return collection,
+        Err(shared) => {
+          shared.cancel_optimizations();
+          collection = shared;
+          tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+      }
+    }
+  }
+
+  /// Run a blocking operation on the collection on a blocking thread, so async workers are not
+  /// held up by it
+  async fn spawn_blocking<T: Send + 'static>(
+    &self,
+    collection_name: &str,
+    operation: impl FnOnce(&Collection) -> OperationResult<T> + Send + 'static,
+  ) -> OperationResult<T> {
+    let collection = {
+      let collections = self.collections.read().await;
+      self.get_collection(&collections, collection_name)?.clone()
+    };
+    tokio::task::spawn_blocking(move || operation(&collection))
+      .await
+      .unwrap_or_else(|err| Err(OperationError::service_error(err.to_string())))
+  }
+
+  pub async fn collection_info(&self, collection_name: &str) -> OperationResult<CollectionInfo> {
+    let collections = self.collections.read().await;
+    self.get_collection(&collections, collection_name)?.info()
+  }
+
+  /// Info of all collections, by name
+  pub async fn collections_info(&self) -> OperationResult<BTreeMap<CollectionId, CollectionInfo>> {
+    let collections = self.collections.read().await;
+    collections
+      .iter()
+      .map(|(name, collection)| Ok((name.clone(), collection.info()?)))
+      .collect()
+  }
+
+  /// Metrics of all collections, sorted by name
+  pub async fn collections_metrics(
+    &self,
+  ) -> OperationResult<Vec<(CollectionId, CollectionMetrics)>> {
+    let collections = self.collections.read().await;
+    let mut metrics = collections
+      .iter()
+      .map(|(name, collection)| Ok((name.clone(), collection.metrics()?)))
+      .collect::<OperationResult<Vec<_>>>()?;
+    metrics.sort_by(|(a, _), (b, _)| a.cmp(b));
+    Ok(metrics)
+  }
+
+  /// Fails with `NotFound` if the collection does not exist, `Unavailable` while it is loading
+  pub async fn check_collection(&self, collection_name: &str) -> OperationResult<()> {
+    let collections = self.collections.read().await;
+    self
+      .get_collection(&collections, collection_name)
+      .map(|_| ())
+  }
+
+  /// Imports running in the background
+  pub fn jobs(&self) -> &Jobs {
+    &self.jobs
+  }
+
+  /// Fails with `OutOfMemory` if inserts should be refused to keep the process alive
+  pub fn check_memory(&self) -> OperationResult<()> {
+    match &self.memory_guard {
+      Some(guard) => guard.check(),
+      None => Ok(()),
+    }
+  }
+
+  pub async fn upsert_points(
+    &self,
+    collection_name: &str,
+    points: Vec<PointStruct>,
+  ) -> OperationResult<Vec<PointIdType>> {
+    self.check_memory()?;
+    self
+      .spawn_blocking(collection_name, move |collection| {
+        collection.upsert_points(points)
+      })
+      .await
+  }
+
+  pub async fn delete_points(
+    &self,
+    collection_name: &str,
+    ids: &[PointIdType],
+  ) -> OperationResult<usize> {
+    let ids = ids.to_vec();
+    self
+      .spawn_blocking(collection_name, move |collection| {
+        collection.delete_points(&ids)
+      })
+      .await
+  }
+
+  pub async fn search(
+    &self,
+    collection_name: &str,
+    vector: &[VectorElementType],
+    top: usize,
+  ) -> OperationResult<Vec<ScoredPoint>> {
+    let collections = self.collections.read().await;
+    self
+      .get_collection(&collections, collection_name)?
+      .search(vector, top)
+  }
+
+  pub async fn search_with_ef(
+    &self,
+    collection_name: &str,
+    vector: &[VectorElementType],
+    top: usize,
+    ef: Option<usize>,
+  ) -> OperationResult<Vec<ScoredPoint>> {
+    let collections = self.collections.read().await;
+    self
+      .get_collection(&collections, collection_name)?
+      .search_with_ef(vector, top, ef)
+  }
+
+  pub async fn export_vectors(
+    &self,
+    collection_name: &str,
+    sink: &mut impl VectorSink,
+  ) -> OperationResult<usize> {
+    let collections = self.collections.read().await;
+    self
+      .get_collection(&collections, collection_name)?
+      .export_vectors(sink)
+  }
+
+  /// Run the pending optimizations of the collection, see [`Collection::optimize`]
+  pub async fn optimize_collection(&self, collection_name: &str) -> OperationResult<usize> {
+    self
+      .spawn_blocking(collection_name, Collection::optimize)
+      .await
+  }
+
+  /// Vacuum of the collection, see [`Collection::vacuum`]
+  pub async fn vacuum_collection(&self, collection_name: &str) -> OperationResult<VacuumReport> {
+    self
+      .spawn_blocking(collection_name, Collection::vacuum)
+      .await
+  }
+
+  pub async fn recommend(
+    &self,
+    collection_name: &str,
+    positive: &[PointIdType],
+    negative: &[PointIdType],
+    top: usize,
+  ) -> OperationResult<Vec<ScoredPoint>> {
+    let collections = self.collections.read().await;
+    self
+      .get_collection(&collections, collection_name)?
+      .recommend(positive, negative, top)
+  }
+
+  pub async fn create_snapshot(
+    &self,
+    collection_name: &str,
+  ) -> OperationResult<SnapshotDescription> {
+    let snapshots_dir = self.snapshots_dir(collection_name);
+    self
+      .spawn_blocking(collection_name, move |collection| {
+        collection.create_snapshot(&snapshots_dir)
+      })
+      .await
+  }
+
+  pub async fn list_snapshots(
+    &self,
+    collection_name: &str,
+  ) -> OperationResult<Vec<SnapshotDescription>> {
+    let collections = self.collections.read().await;
+    self.get_collection(&collections, collection_name)?;
+    list_snapshots(&self.snapshots_dir(collection_name))
+  }
+
+  pub async fn delete_snapshot(
+    &self,
+    collection_name: &str,
+    snapshot_name: &str,
+  ) -> OperationResult<()> {
+    let collections = self.collections.read().await;
+    self.get_collection(&collections, collection_name)?;
+    remove_file(snapshot_path(
+      &self.snapshots_dir(collection_name),
+      snapshot_name,
+    )?)?;
+    Ok(())
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use serde_json::json;
+  use tempfile::Builder;
+
+  use super::*;
+
+  #[tokio::test]
+  async fn test_background_loading() {
+    let dir = Builder::new().prefix("toc").tempdir().unwrap();
+    let storage_config: Arc<StorageConfig> = Arc::new(
+      serde_json::from_value(json!({
+        "storage_path": dir.path().join("storage"),
+        "snapshots_path": dir.path().join("snapshots"),
+      }))
+      .unwrap(),
+    );
+    let config: CollectionConfig = serde_json::from_value(json!({
+      "vector_size": 2,
+      "distance": "Euclidean",
+      "vector_storage_type": "AppendableMemmap",
+    }))
+    .unwrap();
+    let toc = TableOfContent::new(storage_config.clone()).unwrap();
+    toc.create_collection("test", config.clone()).await.unwrap();
+    toc.shutdown().await.unwrap();
+    drop(toc);
+    // Not a collection directory, it can not be loaded
+    create_dir_all(
+      dir
+        .path()
+        .join("storage")
+        .join(COLLECTIONS_DIR)
+        .join("broken"),
+    )
+    .unwrap();
+
+    let toc = Arc::new(TableOfContent::open(storage_config).unwrap());
+    let readiness = toc.readiness();
+    assert!(!readiness.ready);
+    assert_eq!(readiness.collections["test"].status, LoadingStatus::Pending);
+    let err = toc.search("test", &[1.0, 0.0], 1).await.unwrap_err();
+    assert!(matches!(err, OperationError::Unavailable { .. }));
+    assert_eq!(
+      err.to_string(),
+      "Service unavailable: Collection test is still loading"
+    );
+    let err = toc
+      .create_collection("test", config.clone())
+      .await
+      .unwrap_err();
+    assert!(matches!(err, OperationError::Unavailable { .. }));
+
+    toc.clone().load_collections().await;
+    let readiness = toc.readiness();
+    assert!(readiness.ready);
+    assert_eq!(readiness.failed, ["broken"]);
+    let loaded = &readiness.collections["test"];
+    assert_eq!(loaded.status, LoadingStatus::Loaded);
+    assert_eq!(loaded.loaded_segments, loaded.total_segments);
+    assert_eq!(
+      readiness.collections["broken"].status,
+      LoadingStatus::Failed
+    );
+    assert!(readiness.collections["broken"].error.is_some());
+    toc.search("test", &[1.0, 0.0], 1).await.unwrap();
+
+    // A collection which failed to load is deleted to make room for a new one
+    assert!(toc.delete_collection("broken").await.unwrap());
+    assert!(toc.readiness().failed.is_empty());
+    toc.create_collection("broken", config).await.unwrap();
+    assert!(toc.is_responsive(Duration::from_secs(1)).await);
+  }
+
+  #[tokio::test]
+  async fn test_corrupt_collection_does_not_block_readiness() {
+    let dir = Builder::new().prefix("toc").tempdir().unwrap();
+    let storage_config: Arc<StorageConfig> = Arc::new(
+      serde_json::from_value(json!({
+        "storage_path": dir.path().join("storage"),
+        "snapshots_path": dir.path().join("snapshots"),
+      }))
+      .unwrap(),
+    );
+    let config: CollectionConfig = serde_json::from_value(json!({
+      "vector_size": 2,
+      "distance": "Euclidean",
+    }))
+    .unwrap();
+    let toc = TableOfContent::new(storage_config.clone()).unwrap();
+    toc.create_collection("test", config.clone()).await.unwrap();
+    toc.create_collection("corrupt", config).await.unwrap();
+    toc.shutdown().await.unwrap();
+    drop(toc);
+    // The config of the collection can not be parsed
+    let collection_path = dir
+      .path()
+      .join("storage")
+      .join(COLLECTIONS_DIR)
+      .join("corrupt");
+    std::fs::write(collection_path.join("config.json"), "{not json").unwrap();
+
+    let toc = Arc::new(TableOfContent::open(storage_config).unwrap());
+    toc.clone().load_collections().await;
+    let readiness = toc.readiness();
+    assert!(readiness.ready);
+    assert_eq!(readiness.failed, ["corrupt"]);
+    assert_eq!(readiness.collections["test"].status, LoadingStatus::Loaded);
+    assert!(readiness.collections["corrupt"].error.is_some());
+    toc.search("test", &[1.0, 0.0], 1).await.unwrap();
+    assert!(toc.search("corrupt", &[1.0, 0.0], 1).await.is_err());
   }
 
-  pub fn search_vector(&self, collection_name: &str, vector: Vec<f32>, top: usize) -> Vec<u64> {
-    todo!()
+  #[tokio::test]
+  async fn test_memory_guard() {
+    let dir = Builder::new().prefix("toc").tempdir().unwrap();
+    let storage_config = serde_json::from_value(json!({
+      "storage_path": dir.path().join("storage"),
+      "snapshots_path": dir.path().join("snapshots"),
+      // More memory than any machine has
+      "min_available_memory_mb": u64::MAX / 1024 / 1024,
+    }))
+    .unwrap();
+    let config = serde_json::from_value(json!({
+      "vector_size": 2,
+      "distance": "Euclidean",
+      "vector_storage_type": "AppendableMemmap",
+    }))
+    .unwrap();
+    let toc = TableOfContent::new(Arc::new(storage_config)).unwrap();
+    toc.create_collection("test", config).await.unwrap();
+    let point = PointStruct {
+      id: Some(1.into()),
+      vector: vec![1.0, 0.0],
+      payload: None,
+    };
+    let err = toc.upsert_points("test", vec![point]).await.unwrap_err();
+    assert!(matches!(err, OperationError::OutOfMemory { .. }));
+    // Reads keep working
+    assert!(toc.search("test", &[1.0, 0.0], 1).await.unwrap().is_empty());
   }
 }
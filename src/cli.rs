@@ -1,8 +1,169 @@
-use clap::Parser;
+use std::{
+    fs::File,
+    path::PathBuf,
+    sync::{atomic::AtomicBool, Arc},
+};
+
+use clap::{Parser, Subcommand};
+
+use crate::{
+    actix::{
+        handlers::{
+            bench::{run_bench, BenchConfig, DEFAULT_BENCH_EF, DEFAULT_BENCH_K},
+            dataset::{
+                export_dataset, import_dataset, log_progress, DatasetFields, DatasetSource,
+                ExportFormat, DEFAULT_IMPORT_BATCH_SIZE,
+            },
+        },
+        routes::dataset_api::DataType,
+        table::toc::TableOfContent,
+    },
+    common::operation_error::OperationResult,
+    engine::{storage::types::StorageConfig, types::distance::Distance},
+};
 
 #[derive(Parser, Debug)]
 #[command(version, about)]
 pub struct Args {
     #[arg(long, value_name = "PATH")]
     pub config_path: Option<String>,
+    /// Without a command, the service is started
+    #[command(subcommand)]
+    pub command: Option<Command>,
+}
+
+/// Commands on the storage of the config, while the service is stopped
+#[derive(Subcommand, Debug)]
+pub enum Command {
+    /// Import a dataset file into an existing collection
+    Import {
+        collection: String,
+        file: PathBuf,
+        #[arg(long, value_enum, default_value = "hdf5")]
+        datatype: DataType,
+        /// HDF5 dataset of the vectors, `train` by default, or NPZ array, the first one by default
+        #[arg(long)]
+        dataset: Option<String>,
+        /// CSV or Parquet vector column, repeated for one column per dimension
+        #[arg(long = "vector")]
+        vectors: Vec<String>,
+        /// HDF5 dataset, column or NPZ array of the point ids
+        #[arg(long)]
+        ids: Option<String>,
+        /// HDF5 dataset or column stored in the payload, can be repeated
+        #[arg(long)]
+        payload: Vec<String>,
+        #[arg(long, default_value_t = DEFAULT_IMPORT_BATCH_SIZE)]
+        batch_size: usize,
+    },
+    /// Export the vectors of a collection into a file
+    Export {
+        collection: String,
+        file: PathBuf,
+        /// fvecs, ivecs, bvecs, npy or npz
+        #[arg(long, value_enum)]
+        datatype: DataType,
+    },
+    /// Measure recall, queries per second and latencies on an ann-benchmarks HDF5 file, the
+    /// report is printed as JSON
+    Bench {
+        file: PathBuf,
+        /// `cosine` for angular datasets
+        #[arg(long, value_enum, default_value = "euclidean")]
+        distance: Distance,
+        #[arg(long, default_value_t = 16)]
+        m: usize,
+        #[arg(long, default_value_t = 100)]
+        ef_construct: usize,
+        /// Number of searched neighbours the recall is measured at
+        #[arg(long, default_value_t = DEFAULT_BENCH_K)]
+        k: usize,
+        /// `ef` of the test queries, can be repeated
+        #[arg(long, default_values_t = DEFAULT_BENCH_EF)]
+        ef: Vec<usize>,
+        /// Number of test queries to run, all of them by default
+        #[arg(long)]
+        queries: Option<usize>,
+        #[arg(long, default_value_t = DEFAULT_IMPORT_BATCH_SIZE)]
+        batch_size: usize,
+    },
+}
+
+impl Command {
+    /// Run the command, the collections are closed before returning
+    pub async fn run(self, storage_config: StorageConfig) -> OperationResult<()> {
+        let toc = TableOfContent::new(Arc::new(storage_config))?;
+        let result = self.run_with(&toc).await;
+        toc.shutdown().await?;
+        result
+    }
+
+    async fn run_with(self, toc: &TableOfContent) -> OperationResult<()> {
+        match self {
+            Command::Import {
+                collection,
+                file,
+                datatype,
+                dataset,
+                vectors,
+                ids,
+                payload,
+                batch_size,
+            } => {
+                let fields = DatasetFields {
+                    dataset,
+                    vectors,
+                    ids,
+                    payload,
+                };
+                let source = DatasetSource::new(&datatype, fields)?;
+                // The import runs until the end of the file or the first error
+                let stopped = AtomicBool::new(false);
+                let progress = log_progress(&collection);
+                let count = import_dataset(
+                    toc,
+                    &collection,
+                    &file,
+                    &source,
+                    batch_size,
+                    &stopped,
+                    progress,
+                )
+                .await?;
+                log::info!("Imported {count} points into collection {collection}");
+            }
+            Command::Export {
+                collection,
+                file,
+                datatype,
+            } => {
+                let format = ExportFormat::new(&datatype)?;
+                let count = export_dataset(toc, &collection, format, File::create(&file)?).await?;
+                log::info!("Exported {count} points of collection {collection}");
+            }
+            Command::Bench {
+                file,
+                distance,
+                m,
+                ef_construct,
+                k,
+                ef,
+                queries,
+                batch_size,
+            } => {
+                let config = BenchConfig {
+                    distance,
+                    m,
+                    ef_construct,
+                    k,
+                    ef,
+                    queries,
+                    batch_size,
+                };
+                let report = run_bench(toc, &file, &config).await?;
+                println!("{}", serde_json::to_string_pretty(&report)?);
+            }
+        }
+        Ok(())
+    }
 }
@@ -0,0 +1,74 @@
+use std::time::{Duration, Instant};
+
+use parking_lot::Mutex;
+
+use crate::{
+    common::operation_error::{OperationError, OperationResult},
+    utils::mem::Mem,
+};
+
+/// Memory figures older than this are refreshed before a check
+const REFRESH_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Refuses inserts while the memory available to the process, within its cgroup limit if there
+/// is one, is below a threshold
+///
+/// Reads keep working, so the service stays usable while the memory is freed.
+pub struct MemoryGuard {
+    min_available_bytes: u64,
+    /// Memory figures with the time of their last refresh
+    mem: Mutex<(Mem, Instant)>,
+}
+
+impl MemoryGuard {
+    pub fn new(min_available_mb: u64) -> Self {
+        MemoryGuard {
+            min_available_bytes: min_available_mb * 1024 * 1024,
+            mem: Mutex::new((Mem::new(), Instant::now())),
+        }
+    }
+
+    /// Fails with `OutOfMemory` if less than the threshold is available
+    pub fn check(&self) -> OperationResult<()> {
+        let available = {
+            let mut mem = self.mem.lock();
+            let (mem, refreshed) = &mut *mem;
+            if refreshed.elapsed() >= REFRESH_INTERVAL {
+                mem.refresh();
+                *refreshed = Instant::now();
+            }
+            mem.available_memory_bytes()
+        };
+        if available < self.min_available_bytes {
+            return Err(OperationError::OutOfMemory {
+                description: format!(
+                    "inserts are refused while less than {} MiB are available",
+                    self.min_available_bytes / 1024 / 1024
+                ),
+                free: available,
+            });
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_memory_guard() {
+        MemoryGuard::new(0).check().unwrap();
+        let err = MemoryGuard::new(u64::MAX / 1024 / 1024)
+            .check()
+            .unwrap_err();
+        assert!(matches!(err, OperationError::OutOfMemory { .. }));
+
+        // Failed allocations of vector storages are reported the same way
+        let err = Vec::<u8>::new().try_reserve(usize::MAX).unwrap_err();
+        assert!(matches!(
+            OperationError::from(err),
+            OperationError::OutOfMemory { .. }
+        ));
+    }
+}
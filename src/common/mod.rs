@@ -1,5 +1,6 @@
+pub(crate) mod memory_guard;
 pub mod operation_error;
-mod point_id;
+pub mod point_id;
 mod types;
 pub(crate) mod validation;
 pub(crate) mod mmap_type;
@@ -2,7 +2,6 @@ use std::{
     backtrace::Backtrace, collections::TryReserveError, fmt::Display, io::{Error as IoError, ErrorKind}, sync::atomic::{AtomicBool, Ordering}
 };
 
-use actix_web::{http::StatusCode, HttpResponse, ResponseError};
 use atomicwrites::Error as AtomicIoError;
 use hdf5::Error;
 use io::file_operations::FileStorageError;
@@ -53,6 +52,11 @@ pub enum OperationError {
     Cancelled { description: String },
     #[error("Validation failed: {description}")]
     ValidationError { description: String },
+    #[error("Not found: {description}")]
+    NotFound { description: String },
+    /// The resource exists but can not be used for now, like a collection which is still loading
+    #[error("Service unavailable: {description}")]
+    Unavailable { description: String },
     #[error("Wrong usage of sparse vectors")]
     WrongSparse,
 }
@@ -168,28 +172,4 @@ impl From<hdf5::Error> for OperationError {
     }
 }
 
-impl ResponseError for OperationError {
-    fn status_code(&self) -> StatusCode {
-        match *self {
-            OperationError::WrongVector { .. } => StatusCode::BAD_REQUEST,
-            OperationError::VectorNameNotExists { .. } => StatusCode::NOT_FOUND,
-            OperationError::MissedVectorName { .. } => StatusCode::BAD_REQUEST,
-            OperationError::PointIdError { .. } => StatusCode::NOT_FOUND,
-            OperationError::TypeError { .. } => StatusCode::BAD_REQUEST,
-            OperationError::TypeInferenceError { .. } => StatusCode::BAD_REQUEST,
-            OperationError::ServiceError { .. } => StatusCode::INTERNAL_SERVER_ERROR,
-            OperationError::InconsistentStorage { .. } => StatusCode::INTERNAL_SERVER_ERROR,
-            OperationError::OutOfMemory { .. } => StatusCode::INSUFFICIENT_STORAGE,
-            OperationError::Cancelled { .. } => StatusCode::SERVICE_UNAVAILABLE,
-            OperationError::ValidationError { .. } => StatusCode::BAD_REQUEST,
-            OperationError::WrongSparse => StatusCode::BAD_REQUEST,
-        }
-    }
-    fn error_response(&self) -> HttpResponse {
-        HttpResponse::build(self.status_code())
-            .content_type("application/json")
-            .body(self.to_string())
-    }
-}
-
 pub type OperationResult<T> = Result<T, OperationError>;
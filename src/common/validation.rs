@@ -1,7 +1,9 @@
 use std::borrow::Cow;
 
 use serde::Serialize;
-use validator::{Validate, ValidationError, ValidationErrors};
+use validator::{Validate, ValidationError, ValidationErrors, ValidationErrorsKind};
+
+use crate::common::operation_error::{OperationError, OperationResult};
 
 #[allow(clippy::manual_try_fold)] // `try_fold` can't be used because it shortcuts on Err
 pub fn validate_iter<T: Validate>(iter: impl Iterator<Item = T>) -> Result<(), ValidationErrors> {
@@ -14,6 +16,50 @@ pub fn validate_iter<T: Validate>(iter: impl Iterator<Item = T>) -> Result<(), V
     errors.errors().is_empty().then_some(()).ok_or(errors)
 }
 
+/// Flatten nested validation errors into one `path: code (param: value, ...)` line per error
+pub fn describe_errors(errors: &ValidationErrors) -> Vec<String> {
+    fn collect(errors: &ValidationErrors, prefix: &str, messages: &mut Vec<String>) {
+        let mut fields: Vec<_> = errors.errors().iter().collect();
+        fields.sort_by_key(|(field, _)| *field);
+        for (field, kind) in fields {
+            let path = if prefix.is_empty() {
+                field.to_string()
+            } else {
+                format!("{prefix}.{field}")
+            };
+            match kind {
+                ValidationErrorsKind::Struct(errors) => collect(errors, &path, messages),
+                ValidationErrorsKind::List(items) => {
+                    for (index, errors) in items {
+                        collect(errors, &format!("{path}[{index}]"), messages);
+                    }
+                }
+                ValidationErrorsKind::Field(field_errors) => {
+                    for error in field_errors {
+                        let mut params: Vec<_> = error
+                            .params
+                            .iter()
+                            .filter(|(name, _)| *name != "value")
+                            .map(|(name, value)| format!("{name}: {value}"))
+                            .collect();
+                        params.sort();
+                        let message = match &error.message {
+                            Some(message) => message.to_string(),
+                            None if params.is_empty() => error.code.to_string(),
+                            None => format!("{} ({})", error.code, params.join(", ")),
+                        };
+                        messages.push(format!("{path}: {message}"));
+                    }
+                }
+            }
+        }
+    }
+
+    let mut messages = Vec::new();
+    collect(errors, "", &mut messages);
+    messages
+}
+
 /// Validate the value is in `[min, max]`
 #[inline]
 pub fn validate_range_generic<N>(
@@ -41,6 +87,51 @@ where
     Err(err)
 }
 
+/// Largest number of results a search may ask for
+pub const MAX_SEARCH_LIMIT: usize = 10_000;
+
+/// Validate all components of the vector are finite numbers
+pub fn validate_vector_values(vector: &[f32]) -> Result<(), ValidationError> {
+    match vector.iter().position(|value| !value.is_finite()) {
+        Some(index) => {
+            let mut err = ValidationError::new("finite");
+            err.add_param(Cow::from("index"), &index);
+            err.message.replace(
+                format!(
+                    "vector component {index} is {}, only finite values are allowed",
+                    vector[index]
+                )
+                .into(),
+            );
+            Err(err)
+        }
+        None => Ok(()),
+    }
+}
+
+/// Check a vector given to an operation has `dim` finite components
+pub fn check_vector(vector: &[f32], dim: usize) -> OperationResult<()> {
+    if vector.len() != dim {
+        return Err(OperationError::WrongVector {
+            expected_dim: dim,
+            received_dim: vector.len(),
+        });
+    }
+    validate_vector_values(vector).map_err(|err| OperationError::ValidationError {
+        description: err.message.unwrap_or_default().into_owned(),
+    })
+}
+
+/// Check the number of requested results is in `1..=MAX_SEARCH_LIMIT`
+pub fn check_search_limit(limit: usize) -> OperationResult<()> {
+    if limit == 0 || limit > MAX_SEARCH_LIMIT {
+        return Err(OperationError::ValidationError {
+            description: format!("k must be between 1 and {MAX_SEARCH_LIMIT}, got {limit}"),
+        });
+    }
+    Ok(())
+}
+
 /// Validate that `value` is a non-empty string or `None`.
 pub fn validate_not_empty(value: &Option<String>) -> Result<(), ValidationError> {
     match value {
@@ -160,6 +251,66 @@ mod tests {
         assert!(validate_range_generic(3.0, Some(2.0), Some(1.0)).is_err());
     }
 
+    #[test]
+    fn test_describe_errors() {
+        #[derive(Validate)]
+        struct Inner {
+            #[validate(range(min = 1))]
+            size: usize,
+        }
+
+        #[derive(Validate)]
+        struct Outer {
+            #[validate(length(min = 1))]
+            name: String,
+            #[validate]
+            inner: Inner,
+        }
+
+        let errors = Outer {
+            name: String::new(),
+            inner: Inner { size: 0 },
+        }
+        .validate()
+        .unwrap_err();
+        assert_eq!(
+            describe_errors(&errors),
+            vec!["inner.size: range (min: 1.0)", "name: length (min: 1)"]
+        );
+    }
+
+    #[test]
+    fn test_validate_vector_values() {
+        assert!(validate_vector_values(&[]).is_ok());
+        assert!(validate_vector_values(&[0.0, -1.5, f32::MAX]).is_ok());
+        let err = validate_vector_values(&[1.0, f32::NAN]).unwrap_err();
+        assert_eq!(
+            err.message.unwrap(),
+            "vector component 1 is NaN, only finite values are allowed"
+        );
+        assert!(validate_vector_values(&[f32::NEG_INFINITY]).is_err());
+    }
+
+    #[test]
+    fn test_check_vector() {
+        assert!(check_vector(&[1.0, 2.0], 2).is_ok());
+        assert!(matches!(
+            check_vector(&[1.0], 2),
+            Err(OperationError::WrongVector {
+                expected_dim: 2,
+                received_dim: 1
+            })
+        ));
+        assert!(matches!(
+            check_vector(&[1.0, f32::INFINITY], 2),
+            Err(OperationError::ValidationError { .. })
+        ));
+        assert!(check_search_limit(1).is_ok());
+        assert!(check_search_limit(MAX_SEARCH_LIMIT).is_ok());
+        assert!(check_search_limit(0).is_err());
+        assert!(check_search_limit(MAX_SEARCH_LIMIT + 1).is_err());
+    }
+
     #[test]
     fn test_validate_not_empty() {
         assert!(validate_not_empty(&None).is_ok());
@@ -1,5 +1,6 @@
 use std::{
-    fs::create_dir_all,
+    fs::{create_dir_all, remove_file, rename},
+    mem::size_of,
     path::{Path, PathBuf},
     sync::Arc,
     time::SystemTime,
@@ -8,9 +9,12 @@ use std::{
 use atomic_refcell::AtomicRefCell;
 
 use hnsw_rs::{
-    dist::{DistCosine, DistL2, Distance},
+    api::AnnT,
+    dist::Distance as HnswDistance,
     hnsw::{self, Hnsw, Neighbour},
+    hnswio::HnswIo,
 };
+use serde::{de::DeserializeOwned, Serialize};
 use serde_json::{Map, Value};
 
 use crate::{
@@ -18,22 +22,59 @@ use crate::{
     engine::{
         storage::vector::base::VectorStorage,
         types::{
+            distance::Distance,
             types::{Payload, PointOffsetType, VectorElementType},
             vector::VectorRef,
         },
     },
 };
 use crate::{
-    common::operation_error::OperationResult,
-    engine::{index::hnsw::config::HnswGraphConfig, storage::vector::base::VectorStorageEnum},
+    common::{
+        operation_error::{OperationError, OperationResult},
+        validation::{check_search_limit, check_vector},
+    },
+    engine::{
+        index::hnsw::{config::HnswGraphConfig, quantized::QuantizedIndex},
+        storage::{
+            quantized::{
+                binary::BinaryQuantizer, config::QuantizationConfig, product::ProductQuantizer,
+                scalar::ScalarQuantizer,
+            },
+            vector::base::{DenseVectorStorage, VectorStorageEnum},
+        },
+    },
 };
 
+/// Base name of the `.hnsw.graph` and `.hnsw.data` files of dumped graphs
+const GRAPH_DUMP_NAME: &str = "graph";
+/// Graphs are dumped under this name, then renamed, so a partial dump is never loaded
+const GRAPH_DUMP_TMP_NAME: &str = "graph.tmp";
+/// Approximate bytes of a link between two points of the graph: the neighbour pointer, its shared
+/// allocation with the distance and the reference counts
+const GRAPH_LINK_BYTES: usize = 40;
+
+/// Structure and memory usage of an HNSW index
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct IndexTelemetry {
+    pub config: HnswGraphConfig,
+    pub quantization: Option<QuantizationConfig>,
+    /// Highest layer of the graph which has points
+    pub max_level: usize,
+    /// Number of points in each layer, starting at layer 0
+    pub layer_points: Vec<usize>,
+    /// Estimated size of the graph links and the vectors copied into the index
+    pub ram_bytes: usize,
+}
+
 #[derive(Clone)]
 pub struct HNSWIndex<'b> {
     vector_storage: Arc<AtomicRefCell<VectorStorageEnum>>,
     config: HnswGraphConfig,
     path: PathBuf,
-    hnsw: Hnsw<'b, f32, DistCosine>,
+    hnsw: Hnsw<'b, f32, Distance>,
+    quantization_config: Option<QuantizationConfig>,
+    /// Index on quantized vectors, replaces `hnsw` if quantization is configured
+    quantized: Option<QuantizedIndex<'b>>,
 }
 
 impl<'b> HNSWIndex<'b> {
@@ -42,9 +83,8 @@ impl<'b> HNSWIndex<'b> {
         path: &Path,
         data_dimension: usize,
         dataset_size: usize,
-        dist_f: DistCosine,
+        quantization_config: Option<QuantizationConfig>,
     ) -> OperationResult<Self> {
-        create_dir_all(path)?;
         let config_path = HnswGraphConfig::get_config_path(path);
         let config = if config_path.exists() {
             HnswGraphConfig::load(&config_path)?
@@ -64,25 +104,39 @@ impl<'b> HNSWIndex<'b> {
                 10,
             )
         };
+        Self::with_config(vector_storage, path, config, quantization_config)
+    }
 
-        let max_nb_connection = config.m;
-        let nb_elem = config.dataset_size;
-        let nb_layer = 16.min((nb_elem as f32).ln().trunc() as usize);
-        let ef_c = config.ef_construct;
-        let hnsw = Hnsw::<f32, DistCosine>::new(
+    /// Create an index with the given graph parameters
+    ///
+    /// The graph uses the distance of the vector storage.
+    pub fn with_config(
+        vector_storage: Arc<AtomicRefCell<VectorStorageEnum>>,
+        path: &Path,
+        config: HnswGraphConfig,
+        quantization_config: Option<QuantizationConfig>,
+    ) -> OperationResult<Self> {
+        create_dir_all(path)?;
+        let distance = vector_storage.borrow().distance();
+        let mut hnsw = Hnsw::<f32, Distance>::new(
             config.max_nb_connection,
             config.m,
             config.max_layer,
             config.ef_construct,
-            dist_f,
+            distance,
         );
+        // Points are deleted from the graph, keep it connected after many deletions
+        hnsw.set_keeping_pruned_on_repair(true);
 
-        let hnsw_index = HNSWIndex {
+        let mut hnsw_index = HNSWIndex {
             vector_storage,
             config,
             path: path.to_owned(),
             hnsw,
+            quantization_config,
+            quantized: None,
         };
+        hnsw_index.quantized = hnsw_index.init_quantized_index(true)?;
 
         Ok(hnsw_index)
     }
@@ -94,24 +148,165 @@ impl<'b> HNSWIndex<'b> {
 
     pub fn save(&self) -> OperationResult<()> {
         self.save_config()?;
+        if let Some(quantized) = &self.quantized {
+            quantized.save(&self.path)?;
+        }
+        Ok(())
+    }
+
+    /// Persist the vectors and config of the index, the graph is persisted by [`Self::dump_graph`]
+    pub fn flush(&self) -> OperationResult<()> {
+        self.vector_storage.borrow().flusher()()?;
+        self.save()
+    }
+
+    /// Dump the graph into the index directory, replacing the previous dump
+    pub fn dump_graph(&self) -> OperationResult<()> {
+        match &self.quantized {
+            Some(quantized) => quantized.dump_graph(&self.path),
+            None => dump_hnsw_graph(&self.hnsw, &self.path),
+        }
+    }
+
+    /// Time the graph was dumped, `None` if there is no complete dump
+    pub fn graph_dump_modified(&self) -> Option<SystemTime> {
+        graph_dump_files(&self.path, GRAPH_DUMP_NAME)
+            .iter()
+            .map(|file| {
+                file.metadata()
+                    .and_then(|metadata| metadata.modified())
+                    .ok()
+            })
+            .min()
+            .flatten()
+    }
+
+    /// Load the graph dumped by [`Self::dump_graph`] instead of building it
+    ///
+    /// Fails if the dump does not link exactly the live vectors of the storage.
+    pub fn load_graph(&mut self) -> OperationResult<()> {
+        let vector_storage = self.vector_storage.borrow();
+        let live_points = stored_points(&vector_storage);
+        match &mut self.quantized {
+            Some(quantized) => {
+                quantized.load_graph(&self.path, &live_points)?;
+                quantized.set_extend_candidates(self.config.extend_candidates);
+            }
+            None => {
+                let mut hnsw =
+                    load_hnsw_graph(&self.path, vector_storage.distance(), &live_points)?;
+                hnsw.set_extend_candidates(self.config.extend_candidates);
+                hnsw.set_keeping_pruned_on_repair(true);
+                self.hnsw = hnsw;
+            }
+        }
         Ok(())
     }
 
+    /// Number of points in each layer of the graph, starting at layer 0
+    pub fn layer_sizes(&self) -> Vec<usize> {
+        match &self.quantized {
+            Some(quantized) => quantized.layer_sizes(),
+            None => graph_layer_sizes(&self.hnsw),
+        }
+    }
+
+    /// Number of deleted points keeping their slot in the graph until it is built again
+    pub fn deleted_points(&self) -> usize {
+        match &self.quantized {
+            Some(quantized) => quantized.deleted_points(),
+            None => self.hnsw.get_point_indexation().get_nb_deleted(),
+        }
+    }
+
+    /// Rough size of the index in RAM, see [`graph_ram_bytes`]
+    pub fn ram_bytes_estimate(&self) -> usize {
+        match &self.quantized {
+            Some(quantized) => quantized.ram_bytes_estimate(self.config.max_nb_connection),
+            None => graph_ram_bytes(
+                &self.layer_sizes(),
+                self.config.max_nb_connection,
+                self.vector_storage.borrow().vector_dim() * size_of::<VectorElementType>(),
+            ),
+        }
+    }
+
+    pub fn telemetry(&self) -> IndexTelemetry {
+        let layer_points = self.layer_sizes();
+        IndexTelemetry {
+            config: self.config,
+            quantization: self.quantization_config.clone(),
+            max_level: layer_points.len().saturating_sub(1),
+            layer_points,
+            ram_bytes: self.ram_bytes_estimate(),
+        }
+    }
+
+    /// Create an empty index for quantized vectors, if quantization is configured
+    ///
+    /// The quantizer is trained on the vectors currently in the storage, unless
+    /// `reuse_saved` is set and a trained quantizer was saved before.
+    fn init_quantized_index(
+        &self,
+        reuse_saved: bool,
+    ) -> OperationResult<Option<QuantizedIndex<'b>>> {
+        let Some(quantization_config) = &self.quantization_config else {
+            return Ok(None);
+        };
+        let vector_storage = self.vector_storage.borrow();
+        let vector_storage = &*vector_storage;
+        let stored_vectors = || {
+            (0..vector_storage.total_vector_count() as PointOffsetType)
+                .filter(move |id| !vector_storage.is_deleted_vector(*id))
+                .map(move |id| vector_storage.get_dense(id))
+        };
+        let index = match quantization_config {
+            QuantizationConfig::Scalar(scalar_config) => {
+                let quantizer_path = ScalarQuantizer::get_path(&self.path);
+                let quantizer = if reuse_saved && quantizer_path.exists() {
+                    ScalarQuantizer::load(&quantizer_path)?
+                } else {
+                    ScalarQuantizer::train(
+                        stored_vectors(),
+                        vector_storage.vector_dim(),
+                        vector_storage.distance(),
+                        scalar_config,
+                    )?
+                };
+                QuantizedIndex::new_scalar(quantizer, &self.config)
+            }
+            QuantizationConfig::Product(product_config) => {
+                let quantizer_path = ProductQuantizer::get_path(&self.path);
+                let quantizer = if reuse_saved && quantizer_path.exists() {
+                    ProductQuantizer::load(&quantizer_path)?
+                } else {
+                    ProductQuantizer::train(
+                        stored_vectors(),
+                        vector_storage.vector_dim(),
+                        vector_storage.distance(),
+                        product_config,
+                    )?
+                };
+                QuantizedIndex::new_product(quantizer)
+            }
+            QuantizationConfig::Binary(_) => QuantizedIndex::new_binary(
+                BinaryQuantizer::new(vector_storage.vector_dim()),
+                &self.config,
+            ),
+        };
+        Ok(Some(index))
+    }
+
     pub fn build_graph(&mut self, parallel_insertion: bool) -> OperationResult<()> {
+        if self.quantization_config.is_some() {
+            return self.build_quantized_index(parallel_insertion);
+        }
         log::info!("Building HNSW graph");
-        let vector_storage = self.vector_storage.borrow();
         self.hnsw
             .set_extend_candidates(self.config.extend_candidates);
         let vector_storage = self.vector_storage.borrow();
-        let total_vector_count = vector_storage.total_vector_count();
-        let deleted_bitslice = vector_storage.deleted_vector_bitslice();
+        let data_for_insertion = stored_points(&vector_storage);
 
-        let data_for_insertion = vector_storage
-            .get_dense_storage()
-            .vectors()
-            .get_all_vectors();
-
-        dbg!(total_vector_count);
         if parallel_insertion {
             log::info!("Performing parallel insertion");
             self.hnsw.parallel_insert_slice(&data_for_insertion);
@@ -125,14 +320,37 @@ impl<'b> HNSWIndex<'b> {
         Ok(())
     }
 
+    fn build_quantized_index(&mut self, parallel_insertion: bool) -> OperationResult<()> {
+        log::info!("Building quantized index");
+        let mut quantized = self
+            .init_quantized_index(false)?
+            .expect("quantization is configured");
+        quantized.set_extend_candidates(self.config.extend_candidates);
+
+        let vector_storage = self.vector_storage.borrow();
+        let data_for_insertion = stored_points(&vector_storage);
+
+        if parallel_insertion {
+            quantized.parallel_insert(&data_for_insertion);
+        } else {
+            for (vector, id) in data_for_insertion {
+                quantized.insert(vector, id);
+            }
+        }
+        drop(vector_storage);
+
+        self.quantized = Some(quantized);
+        Ok(())
+    }
+
     pub fn add(&mut self, vector: &[VectorElementType], payload: Payload) -> OperationResult<()> {
         log::info!("Adding vector to hnsw index");
-        let key = SystemTime::now()
-            .duration_since(SystemTime::UNIX_EPOCH)
-            .unwrap()
-            .as_nanos() as usize;
         let vector_ref = VectorRef::Dense(vector);
         let mut vector_storage = self.vector_storage.borrow_mut();
+        // Storages copy the vector into fixed size slots
+        check_vector(vector, vector_storage.vector_dim())?;
+        // Storages are indexed by dense offsets, append after the last stored vector
+        let key = vector_storage.total_vector_count();
 
         match vector_storage.insert_vector(key as PointOffsetType, vector_ref, payload) {
             Ok(_) => {
@@ -147,13 +365,88 @@ impl<'b> HNSWIndex<'b> {
             }
         };
 
-        let data_with_id: (&[VectorElementType], usize) = (vector, key);
-        self.hnsw.insert_slice(data_with_id);
+        match &mut self.quantized {
+            Some(quantized) => quantized.insert(vector, key),
+            None => {
+                let data_with_id: (&[VectorElementType], usize) = (vector, key);
+                self.hnsw.insert_slice(data_with_id);
+            }
+        }
         Ok(())
     }
 
+    /// Link the vector stored at `id` into the graph, replacing the vector previously linked there
+    pub fn upsert(&mut self, vector: &[VectorElementType], id: PointOffsetType) {
+        match &mut self.quantized {
+            Some(quantized) => quantized.replace(vector, id as usize),
+            None => self.hnsw.replace_slice((vector, id as usize)),
+        }
+    }
+
+    /// Unlink deleted vectors from the graph, so searches do not return them
+    ///
+    /// Each call repairs the whole graph, delete in batches.
+    pub fn delete(&mut self, ids: &[PointOffsetType]) {
+        if ids.is_empty() {
+            return;
+        }
+        let ids: Vec<usize> = ids.iter().map(|id| *id as usize).collect();
+        match &mut self.quantized {
+            Some(quantized) => quantized.delete(&ids),
+            None => {
+                self.hnsw.delete_points(&ids);
+            }
+        }
+    }
+
+    /// Search on quantized vectors, then re-rank the oversampled candidates with original vectors
+    fn search_quantized(
+        &self,
+        quantized: &QuantizedIndex<'b>,
+        query: &[VectorElementType],
+        k: usize,
+        ef: usize,
+    ) -> Vec<Neighbour> {
+        let quantization_config = self
+            .quantization_config
+            .as_ref()
+            .expect("quantized graph requires quantization config");
+        let top = quantization_config.oversampled_top(k);
+        let mut neighbours = quantized.search(query, top, ef.max(top));
+
+        if quantization_config.rescore() {
+            let vector_storage = self.vector_storage.borrow();
+            let distance = vector_storage.distance();
+            for neighbour in neighbours.iter_mut() {
+                let stored = vector_storage.get_dense(neighbour.d_id as PointOffsetType);
+                neighbour.distance = distance.eval(query, stored);
+            }
+            neighbours.sort_by(|a, b| a.distance.total_cmp(&b.distance));
+        }
+        neighbours.truncate(k);
+        neighbours
+    }
+
+    /// Closest stored points to the query, `d_id` of the neighbours is the storage offset
+    ///
+    /// `ef` is the size of the candidate list of the search, `ef_construct` if not set.
+    pub fn search_neighbours(
+        &self,
+        query: &[VectorElementType],
+        k: usize,
+        ef: Option<usize>,
+    ) -> Vec<Neighbour> {
+        let ef = ef.unwrap_or(self.config.ef_construct);
+        match &self.quantized {
+            Some(quantized) => self.search_quantized(quantized, query, k, ef),
+            None => self.hnsw.search(query, k, ef.max(k)),
+        }
+    }
+
     pub fn search(&self, query: &[f32], k: usize) -> OperationResult<Vec<Map<String, Value>>> {
-        let neighbours: Vec<Neighbour> = self.hnsw.search(&query, k, self.config.ef_construct);
+        check_vector(query, self.vector_storage.borrow().vector_dim())?;
+        check_search_limit(k)?;
+        let neighbours = self.search_neighbours(query, k, None);
 
         let payloads = neighbours
             .iter()
@@ -180,6 +473,122 @@ impl<'b> HNSWIndex<'b> {
     }
 }
 
+/// Number of points in each layer of `hnsw`, up to the highest layer with points
+pub(crate) fn graph_layer_sizes<T, D>(hnsw: &Hnsw<T, D>) -> Vec<usize>
+where
+    T: Clone + Send + Sync,
+    D: HnswDistance<T> + Send + Sync,
+{
+    if hnsw.get_nb_point() == 0 {
+        return Vec::new();
+    }
+    let indexation = hnsw.get_point_indexation();
+    (0..=hnsw.get_max_level_observed() as usize)
+        .map(|layer| indexation.get_layer_nb_point(layer))
+        .collect()
+}
+
+/// Rough size of a graph in RAM: the links of every layer and the copy of each vector
+///
+/// Layer 0 keeps up to twice as many links per point as the upper layers.
+fn graph_dump_files(path: &Path, name: &str) -> [PathBuf; 2] {
+    [
+        path.join(format!("{name}.hnsw.graph")),
+        path.join(format!("{name}.hnsw.data")),
+    ]
+}
+
+/// Remove the dumped graph from the index directory
+pub(crate) fn remove_graph_dump(path: &Path) -> OperationResult<()> {
+    for file in graph_dump_files(path, GRAPH_DUMP_NAME) {
+        if file.exists() {
+            remove_file(file)?;
+        }
+    }
+    Ok(())
+}
+
+/// Dump the graph into the index directory, graphs without live points are not dumped
+pub(crate) fn dump_hnsw_graph<T, D>(hnsw: &Hnsw<T, D>, path: &Path) -> OperationResult<()>
+where
+    T: Serialize + DeserializeOwned + Clone + Send + Sync,
+    D: HnswDistance<T> + Send + Sync,
+{
+    // A failed dump must not leave the previous one behind, it would not match the storage
+    remove_graph_dump(path)?;
+    let indexation = hnsw.get_point_indexation();
+    if indexation.get_nb_point() == indexation.get_nb_deleted() {
+        return Ok(());
+    }
+    create_dir_all(path)?;
+    hnsw.file_dump_in(path, GRAPH_DUMP_TMP_NAME)
+        .map_err(|err| {
+            OperationError::service_error(format!("Failed to dump HNSW graph: {err}"))
+        })?;
+    let dumped = graph_dump_files(path, GRAPH_DUMP_TMP_NAME);
+    for (dumped, file) in dumped.iter().zip(graph_dump_files(path, GRAPH_DUMP_NAME)) {
+        rename(dumped, file)?;
+    }
+    Ok(())
+}
+
+/// Load the graph dumped in the index directory, it must link exactly the `live_points`
+pub(crate) fn load_hnsw_graph<'b, T, D>(
+    path: &Path,
+    distance: D,
+    live_points: &[(&[VectorElementType], usize)],
+) -> OperationResult<Hnsw<'b, T, D>>
+where
+    T: 'static + Serialize + DeserializeOwned + Clone + Send + Sync + std::fmt::Debug,
+    D: HnswDistance<T> + Send + Sync,
+{
+    let hnsw = HnswIo::new(path.to_owned(), GRAPH_DUMP_NAME.to_string())
+        .load_hnsw_with_dist(distance)
+        .map_err(|err| {
+            OperationError::service_error(format!("Failed to load HNSW graph: {err}"))
+        })?;
+    let indexation = hnsw.get_point_indexation();
+    let linked_points = indexation.get_nb_point() - indexation.get_nb_deleted();
+    if linked_points != live_points.len()
+        || live_points
+            .iter()
+            .any(|(_, id)| indexation.get_origin_point_id(*id).is_none())
+    {
+        return Err(OperationError::service_error(
+            "Dumped HNSW graph does not match the stored vectors",
+        ));
+    }
+    Ok(hnsw)
+}
+
+pub(crate) fn graph_ram_bytes(
+    layer_sizes: &[usize],
+    max_nb_connection: usize,
+    vector_bytes: usize,
+) -> usize {
+    let links: usize = layer_sizes
+        .iter()
+        .enumerate()
+        .map(|(layer, points)| {
+            let max_links = if layer == 0 {
+                2 * max_nb_connection
+            } else {
+                max_nb_connection
+            };
+            points * max_links * GRAPH_LINK_BYTES
+        })
+        .sum();
+    links + layer_sizes.first().copied().unwrap_or_default() * vector_bytes
+}
+
+/// Non-deleted vectors of the storage with their offsets
+fn stored_points(vector_storage: &VectorStorageEnum) -> Vec<(&[VectorElementType], usize)> {
+    (0..vector_storage.total_vector_count() as PointOffsetType)
+        .filter(|id| !vector_storage.is_deleted_vector(*id))
+        .map(|id| (vector_storage.get_dense(id), id as usize))
+        .collect()
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
@@ -191,16 +600,24 @@ mod test {
         let dim = 3;
 
         let coloumn_name = "test";
-        use hnsw_rs::dist::DistCosine;
 
         // Assuming `dim` is the dimension of your vectors and `path` is a valid path
         let vector_storage = Arc::new(AtomicRefCell::new(VectorStorageEnum::DenseSimple(
             SimpleDenseVectorStorage::new(dim, Distance::Euclidean, "test"),
         )));
         let path = Path::new("test");
-        let mut hnsw_index = HNSWIndex::new(vector_storage, path, dim, 10, DistCosine).unwrap();
+        let mut hnsw_index =
+            HNSWIndex::new(vector_storage, path, dim, 10, None).unwrap();
         hnsw_index.build_graph(false).unwrap();
         hnsw_index.save_config().unwrap();
+        assert!(matches!(
+            hnsw_index.add(&[1.0, 2.0], Payload::default()),
+            Err(OperationError::WrongVector { .. })
+        ));
+        assert!(matches!(
+            hnsw_index.search(&[0.0, 0.0, 0.0], 0),
+            Err(OperationError::ValidationError { .. })
+        ));
 
         let query = vec![0.0, 0.0, 0.0];
         let k = 3;
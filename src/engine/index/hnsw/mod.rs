@@ -1,2 +1,3 @@
-mod config;
+pub mod config;
 pub mod index;
+mod quantized;
@@ -0,0 +1,406 @@
+use std::collections::{BinaryHeap, HashSet};
+use std::mem::size_of;
+use std::path::Path;
+
+use hnsw_rs::hnsw::{Hnsw, Neighbour, PointId};
+use ordered_float::OrderedFloat;
+use rayon::prelude::*;
+
+use crate::common::operation_error::{OperationError, OperationResult};
+use crate::engine::index::hnsw::config::HnswGraphConfig;
+use crate::engine::index::hnsw::index::{
+    dump_hnsw_graph, graph_layer_sizes, graph_ram_bytes, load_hnsw_graph, remove_graph_dump,
+};
+use crate::engine::storage::quantized::binary::{BinaryHammingDistance, BinaryQuantizer};
+use crate::engine::storage::quantized::product::ProductQuantizer;
+use crate::engine::storage::quantized::scalar::{ScalarQuantizedDistance, ScalarQuantizer};
+use crate::engine::types::types::VectorElementType;
+
+/// Index on quantized copies of the stored vectors
+///
+/// The index only keeps the quantized vectors, original vectors stay in the vector storage and
+/// are used for rescoring.
+#[derive(Clone)]
+pub enum QuantizedIndex<'b> {
+    /// HNSW graph on scalar quantized vectors
+    Scalar {
+        quantizer: ScalarQuantizer,
+        hnsw: Hnsw<'b, u8, ScalarQuantizedDistance>,
+    },
+    /// Product quantization codes of every point, scanned with asymmetric distance computation
+    Product {
+        quantizer: ProductQuantizer,
+        ids: Vec<usize>,
+        /// Codes of `ids[i]` are `codes[i * subspaces..(i + 1) * subspaces]`
+        codes: Vec<u8>,
+    },
+    /// HNSW graph on binary quantized vectors packed into `u64` words
+    Binary {
+        quantizer: BinaryQuantizer,
+        hnsw: Hnsw<'b, u64, BinaryHammingDistance>,
+    },
+}
+
+impl<'b> QuantizedIndex<'b> {
+    pub fn new_scalar(quantizer: ScalarQuantizer, config: &HnswGraphConfig) -> Self {
+        let mut hnsw = Hnsw::new(
+            config.max_nb_connection,
+            config.m,
+            config.max_layer,
+            config.ef_construct,
+            quantizer.quantized_distance(),
+        );
+        hnsw.set_keeping_pruned_on_repair(true);
+        QuantizedIndex::Scalar { quantizer, hnsw }
+    }
+
+    pub fn new_binary(quantizer: BinaryQuantizer, config: &HnswGraphConfig) -> Self {
+        let mut hnsw = Hnsw::new(
+            config.max_nb_connection,
+            config.m,
+            config.max_layer,
+            config.ef_construct,
+            BinaryHammingDistance,
+        );
+        hnsw.set_keeping_pruned_on_repair(true);
+        QuantizedIndex::Binary { quantizer, hnsw }
+    }
+
+    pub fn new_product(quantizer: ProductQuantizer) -> Self {
+        QuantizedIndex::Product {
+            quantizer,
+            ids: Vec::new(),
+            codes: Vec::new(),
+        }
+    }
+
+    pub fn set_extend_candidates(&mut self, flag: bool) {
+        match self {
+            QuantizedIndex::Scalar { hnsw, .. } => hnsw.set_extend_candidates(flag),
+            QuantizedIndex::Binary { hnsw, .. } => hnsw.set_extend_candidates(flag),
+            QuantizedIndex::Product { .. } => {}
+        }
+    }
+
+    /// Points per graph layer, product quantization has no graph
+    pub fn layer_sizes(&self) -> Vec<usize> {
+        match self {
+            QuantizedIndex::Scalar { hnsw, .. } => graph_layer_sizes(hnsw),
+            QuantizedIndex::Binary { hnsw, .. } => graph_layer_sizes(hnsw),
+            QuantizedIndex::Product { .. } => Vec::new(),
+        }
+    }
+
+    /// Deleted points left in the graph, deleted codes of product quantization are removed
+    pub fn deleted_points(&self) -> usize {
+        match self {
+            QuantizedIndex::Scalar { hnsw, .. } => hnsw.get_point_indexation().get_nb_deleted(),
+            QuantizedIndex::Binary { hnsw, .. } => hnsw.get_point_indexation().get_nb_deleted(),
+            QuantizedIndex::Product { .. } => 0,
+        }
+    }
+
+    /// Rough size of the quantized vectors and their graph in RAM
+    pub fn ram_bytes_estimate(&self, max_nb_connection: usize) -> usize {
+        match self {
+            QuantizedIndex::Scalar { quantizer, hnsw } => {
+                graph_ram_bytes(&graph_layer_sizes(hnsw), max_nb_connection, quantizer.dim())
+            }
+            QuantizedIndex::Binary { quantizer, hnsw } => graph_ram_bytes(
+                &graph_layer_sizes(hnsw),
+                max_nb_connection,
+                quantizer.words() * size_of::<u64>(),
+            ),
+            QuantizedIndex::Product { ids, codes, .. } => {
+                ids.len() * size_of::<usize>() + codes.len()
+            }
+        }
+    }
+
+    pub fn insert(&mut self, vector: &[VectorElementType], id: usize) {
+        match self {
+            QuantizedIndex::Scalar { quantizer, hnsw } => {
+                hnsw.insert_slice((&quantizer.encode(vector), id))
+            }
+            QuantizedIndex::Product {
+                quantizer,
+                ids,
+                codes,
+            } => {
+                ids.push(id);
+                codes.extend(quantizer.encode(vector));
+            }
+            QuantizedIndex::Binary { quantizer, hnsw } => {
+                hnsw.insert_slice((&quantizer.encode(vector), id))
+            }
+        }
+    }
+
+    /// Insert the vector, or replace the vector previously inserted with the same id
+    pub fn replace(&mut self, vector: &[VectorElementType], id: usize) {
+        match self {
+            QuantizedIndex::Scalar { quantizer, hnsw } => {
+                hnsw.replace_slice((&quantizer.encode(vector), id))
+            }
+            QuantizedIndex::Product { .. } => {
+                self.delete(&[id]);
+                self.insert(vector, id);
+            }
+            QuantizedIndex::Binary { quantizer, hnsw } => {
+                hnsw.replace_slice((&quantizer.encode(vector), id))
+            }
+        }
+    }
+
+    /// Remove the ids from the index, searches do not return them anymore
+    pub fn delete(&mut self, deleted: &[usize]) {
+        match self {
+            QuantizedIndex::Scalar { hnsw, .. } => {
+                hnsw.delete_points(deleted);
+            }
+            QuantizedIndex::Product {
+                quantizer,
+                ids,
+                codes,
+            } => {
+                let deleted: HashSet<usize> = deleted.iter().copied().collect();
+                let keep: Vec<bool> = ids.iter().map(|id| !deleted.contains(id)).collect();
+                let subspaces = quantizer.subspaces();
+                let mut position = 0;
+                codes.retain(|_| {
+                    position += 1;
+                    keep[(position - 1) / subspaces]
+                });
+                let mut keep = keep.into_iter();
+                ids.retain(|_| keep.next().unwrap_or_default());
+            }
+            QuantizedIndex::Binary { hnsw, .. } => {
+                hnsw.delete_points(deleted);
+            }
+        }
+    }
+
+    pub fn parallel_insert(&mut self, vectors: &[(&[VectorElementType], usize)]) {
+        match self {
+            QuantizedIndex::Scalar { quantizer, hnsw } => {
+                let encoded: Vec<(Vec<u8>, usize)> = vectors
+                    .iter()
+                    .map(|(vector, id)| (quantizer.encode(vector), *id))
+                    .collect();
+                let data: Vec<(&[u8], usize)> = encoded
+                    .iter()
+                    .map(|(codes, id)| (codes.as_slice(), *id))
+                    .collect();
+                hnsw.parallel_insert_slice(&data);
+            }
+            QuantizedIndex::Product {
+                quantizer,
+                ids,
+                codes,
+            } => {
+                let encoded: Vec<Vec<u8>> = vectors
+                    .par_iter()
+                    .map(|(vector, _)| quantizer.encode(vector))
+                    .collect();
+                ids.extend(vectors.iter().map(|(_, id)| *id));
+                codes.extend(encoded.into_iter().flatten());
+            }
+            QuantizedIndex::Binary { quantizer, hnsw } => {
+                let encoded: Vec<(Vec<u64>, usize)> = vectors
+                    .iter()
+                    .map(|(vector, id)| (quantizer.encode(vector), *id))
+                    .collect();
+                let data: Vec<(&[u64], usize)> = encoded
+                    .iter()
+                    .map(|(codes, id)| (codes.as_slice(), *id))
+                    .collect();
+                hnsw.parallel_insert_slice(&data);
+            }
+        }
+    }
+
+    /// Search candidates on the quantized vectors, distances are approximate
+    pub fn search(&self, query: &[VectorElementType], top: usize, ef: usize) -> Vec<Neighbour> {
+        match self {
+            QuantizedIndex::Scalar { quantizer, hnsw } => {
+                hnsw.search(&quantizer.encode(query), top, ef)
+            }
+            QuantizedIndex::Product {
+                quantizer,
+                ids,
+                codes,
+            } => {
+                if top == 0 {
+                    return Vec::new();
+                }
+                let table = quantizer.lookup_table(query);
+                codes
+                    .par_chunks_exact(quantizer.subspaces())
+                    .zip(ids.par_iter())
+                    .fold(BinaryHeap::new, |mut heap, (codes, id)| {
+                        push_bounded(&mut heap, top, (OrderedFloat(table.eval(codes)), *id));
+                        heap
+                    })
+                    .reduce(BinaryHeap::new, |mut heap, other| {
+                        for candidate in other {
+                            push_bounded(&mut heap, top, candidate);
+                        }
+                        heap
+                    })
+                    .into_sorted_vec()
+                    .into_iter()
+                    .map(|(distance, id)| Neighbour::new(id, distance.0, PointId::default()))
+                    .collect()
+            }
+            QuantizedIndex::Binary { quantizer, hnsw } => {
+                hnsw.search(&quantizer.encode(query), top, ef)
+            }
+        }
+    }
+
+    /// Dump the graph into the index directory, product quantization has no graph to dump
+    pub fn dump_graph(&self, path: &Path) -> OperationResult<()> {
+        match self {
+            QuantizedIndex::Scalar { hnsw, .. } => dump_hnsw_graph(hnsw, path),
+            QuantizedIndex::Binary { hnsw, .. } => dump_hnsw_graph(hnsw, path),
+            QuantizedIndex::Product { .. } => remove_graph_dump(path),
+        }
+    }
+
+    /// Load the graph dumped on the `live_points`, codes of product quantization are encoded again
+    ///
+    /// The graph is only valid with the quantizer it was built with, which must have been saved.
+    pub fn load_graph(
+        &mut self,
+        path: &Path,
+        live_points: &[(&[VectorElementType], usize)],
+    ) -> OperationResult<()> {
+        match self {
+            QuantizedIndex::Scalar { quantizer, hnsw } => {
+                if !ScalarQuantizer::get_path(path).exists() {
+                    return Err(OperationError::service_error(
+                        "Scalar quantizer of the dumped graph is not saved",
+                    ));
+                }
+                *hnsw = load_hnsw_graph(path, quantizer.quantized_distance(), live_points)?;
+                hnsw.set_keeping_pruned_on_repair(true);
+            }
+            QuantizedIndex::Product { .. } => {
+                if !ProductQuantizer::get_path(path).exists() {
+                    return Err(OperationError::service_error(
+                        "Product quantizer of the index is not saved",
+                    ));
+                }
+                self.parallel_insert(live_points);
+            }
+            QuantizedIndex::Binary { hnsw, .. } => {
+                *hnsw = load_hnsw_graph(path, BinaryHammingDistance, live_points)?;
+                hnsw.set_keeping_pruned_on_repair(true);
+            }
+        }
+        Ok(())
+    }
+
+    pub fn save(&self, path: &Path) -> OperationResult<()> {
+        match self {
+            QuantizedIndex::Scalar { quantizer, .. } => {
+                quantizer.save(&ScalarQuantizer::get_path(path))
+            }
+            QuantizedIndex::Product { quantizer, .. } => {
+                quantizer.save(&ProductQuantizer::get_path(path))
+            }
+            // Binary quantization has no trained parameters
+            QuantizedIndex::Binary { .. } => Ok(()),
+        }
+    }
+}
+
+/// Keep the `top` closest candidates in a max-heap
+fn push_bounded(
+    heap: &mut BinaryHeap<(OrderedFloat<f32>, usize)>,
+    top: usize,
+    candidate: (OrderedFloat<f32>, usize),
+) {
+    if heap.len() < top {
+        heap.push(candidate);
+    } else if heap.peek().is_some_and(|worst| candidate < *worst) {
+        heap.pop();
+        heap.push(candidate);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::rngs::StdRng;
+    use rand::{Rng, SeedableRng};
+
+    use super::*;
+    use crate::engine::storage::quantized::config::ProductQuantizationConfig;
+    use crate::engine::types::distance::Distance;
+
+    #[test]
+    fn test_product_search_finds_nearest() {
+        let mut rng = StdRng::seed_from_u64(42);
+        let vectors: Vec<Vec<f32>> = (0..300)
+            .map(|_| (0..8).map(|_| rng.gen_range(-1.0..1.0)).collect())
+            .collect();
+        let config = ProductQuantizationConfig {
+            subspaces: 4,
+            centroids: Some(64),
+            rescore: None,
+            oversampling: None,
+        };
+        let quantizer = ProductQuantizer::train(
+            vectors.iter().map(|v| v.as_slice()),
+            8,
+            Distance::Euclidean,
+            &config,
+        )
+        .unwrap();
+
+        let mut index = QuantizedIndex::new_product(quantizer);
+        let data: Vec<(&[f32], usize)> = vectors
+            .iter()
+            .enumerate()
+            .map(|(id, v)| (v.as_slice(), id + 1000))
+            .collect();
+        index.parallel_insert(&data);
+
+        let result = index.search(&vectors[17], 10, 0);
+        assert_eq!(result.len(), 10);
+        assert!(result.windows(2).all(|w| w[0].distance <= w[1].distance));
+        assert!(result.iter().any(|n| n.d_id == 1017));
+
+        // Deleted and replaced codes are not found anymore
+        index.delete(&[1017]);
+        index.replace(&vectors[18], 1019);
+        let result = index.search(&vectors[17], 300, 0);
+        assert_eq!(result.len(), 299);
+        assert!(result.iter().all(|n| n.d_id != 1017));
+        let result = index.search(&vectors[18], 2, 0);
+        assert_eq!(result[0].distance, result[1].distance);
+    }
+
+    #[test]
+    fn test_binary_search_finds_exact_match() {
+        let mut rng = StdRng::seed_from_u64(42);
+        let dim = 96;
+        let vectors: Vec<Vec<f32>> = (0..200)
+            .map(|_| (0..dim).map(|_| rng.gen_range(-1.0..1.0)).collect())
+            .collect();
+        let config =
+            HnswGraphConfig::new(16, 100, 24, 16, false, 16, dim, false, false, 200, 100, 10);
+
+        let mut index = QuantizedIndex::new_binary(BinaryQuantizer::new(dim), &config);
+        let data: Vec<(&[f32], usize)> = vectors
+            .iter()
+            .enumerate()
+            .map(|(id, v)| (v.as_slice(), id))
+            .collect();
+        index.parallel_insert(&data);
+
+        let result = index.search(&vectors[42], 5, 100);
+        assert_eq!(result[0].d_id, 42);
+        assert_eq!(result[0].distance, 0.0);
+    }
+}
@@ -1,5 +1,5 @@
 mod base;
 pub mod hnsw;
 pub mod index;
-mod plain;
+pub mod plain;
 mod retrieval;
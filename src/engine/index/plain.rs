@@ -0,0 +1,78 @@
+use std::collections::BinaryHeap;
+
+use hnsw_rs::dist::Distance as HnswDistance;
+
+use crate::engine::storage::vector::base::{DenseVectorStorage, VectorStorage, VectorStorageEnum};
+use crate::engine::types::types::{PointOffsetType, ScoredPointOffset, VectorElementType};
+
+/// Exact search, scores every non-deleted vector of the storage
+///
+/// Scores are distances, so results are sorted from the closest point.
+pub fn plain_search(
+    vector_storage: &VectorStorageEnum,
+    query: &[VectorElementType],
+    top: usize,
+) -> Vec<ScoredPointOffset> {
+    if top == 0 {
+        return Vec::new();
+    }
+    let distance = vector_storage.distance();
+    // Max-heap on the distance, the worst of the kept candidates is on top
+    let mut heap: BinaryHeap<ScoredPointOffset> = BinaryHeap::with_capacity(top + 1);
+    for idx in 0..vector_storage.total_vector_count() as PointOffsetType {
+        if vector_storage.is_deleted_vector(idx) {
+            continue;
+        }
+        let score = distance.eval(query, vector_storage.get_dense(idx));
+        if heap.len() < top {
+            heap.push(ScoredPointOffset { idx, score });
+        } else if heap.peek().is_some_and(|worst| score < worst.score) {
+            heap.pop();
+            heap.push(ScoredPointOffset { idx, score });
+        }
+    }
+    heap.into_sorted_vec()
+}
+
+#[cfg(test)]
+mod tests {
+    use tempfile::Builder;
+
+    use super::*;
+    use crate::engine::storage::types::VectorStorageType;
+    use crate::engine::storage::vector::base::open_vector_storage;
+    use crate::engine::types::distance::Distance;
+    use crate::engine::types::types::Payload;
+
+    #[test]
+    fn test_plain_search_skips_deleted() {
+        let dir = Builder::new().prefix("plain_search").tempdir().unwrap();
+        let storage = open_vector_storage(
+            dir.path(),
+            2,
+            Distance::Euclidean,
+            VectorStorageType::Dense,
+            false,
+        )
+        .unwrap();
+        let mut storage = storage.borrow_mut();
+        for (key, vector) in [[0.0, 0.0], [1.0, 0.0], [2.0, 0.0], [3.0, 0.0]]
+            .iter()
+            .enumerate()
+        {
+            storage
+                .insert_vector(
+                    key as PointOffsetType,
+                    vector.as_slice().into(),
+                    Payload::default(),
+                )
+                .unwrap();
+        }
+        storage.delete_vector(1).unwrap();
+
+        let result = plain_search(&storage, &[0.9, 0.0], 2);
+        let ids: Vec<_> = result.iter().map(|scored| scored.idx).collect();
+        assert_eq!(ids, vec![0, 2]);
+        assert!(plain_search(&storage, &[0.0, 0.0], 0).is_empty());
+    }
+}
@@ -1,5 +1,6 @@
 pub mod index;
 mod search;
+pub mod segments;
 pub mod storage;
 pub mod types;
 pub mod utils;
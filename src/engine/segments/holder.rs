@@ -0,0 +1,72 @@
+use std::collections::BTreeMap;
+use std::sync::Arc;
+
+use parking_lot::RwLock;
+
+use crate::engine::segments::optimizer::SegmentInfo;
+use crate::engine::segments::segment::Segment;
+
+pub type SegmentId = u64;
+
+pub type LockedSegment = Arc<RwLock<Segment>>;
+
+/// Segments of a collection
+///
+/// Segment ids are never reused, a segment is stored in the directory named after its id.
+#[derive(Default)]
+pub struct SegmentHolder {
+    segments: BTreeMap<SegmentId, LockedSegment>,
+    next_id: SegmentId,
+}
+
+impl SegmentHolder {
+    pub fn add(&mut self, id: SegmentId, segment: Segment) -> LockedSegment {
+        let segment = Arc::new(RwLock::new(segment));
+        self.segments.insert(id, segment.clone());
+        self.next_id = self.next_id.max(id + 1);
+        segment
+    }
+
+    /// Take an id for a segment which is about to be created
+    pub fn reserve_id(&mut self) -> SegmentId {
+        let id = self.next_id;
+        self.next_id += 1;
+        id
+    }
+
+    pub fn get(&self, id: SegmentId) -> Option<&LockedSegment> {
+        self.segments.get(&id)
+    }
+
+    pub fn remove(&mut self, ids: &[SegmentId]) -> Vec<LockedSegment> {
+        ids.iter()
+            .filter_map(|id| self.segments.remove(id))
+            .collect()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (SegmentId, &LockedSegment)> {
+        self.segments.iter().map(|(id, segment)| (*id, segment))
+    }
+
+    pub fn len(&self) -> usize {
+        self.segments.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.segments.is_empty()
+    }
+
+    /// Latest appendable segment, which takes all new points
+    pub fn appendable_segment(&self) -> Option<&LockedSegment> {
+        self.segments
+            .values()
+            .rev()
+            .find(|segment| segment.read().is_appendable())
+    }
+
+    pub fn segment_infos(&self) -> Vec<SegmentInfo> {
+        self.iter()
+            .map(|(id, segment)| SegmentInfo::new(id, &segment.read()))
+            .collect()
+    }
+}
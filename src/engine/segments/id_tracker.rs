@@ -0,0 +1,144 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use io::file_operations::{atomic_save_json, read_json};
+
+use crate::common::operation_error::OperationResult;
+use crate::common::point_id::PointIdType;
+use crate::engine::types::types::PointOffsetType;
+
+const ID_TRACKER_FILE: &str = "id_tracker.json";
+
+/// Links external point ids to offsets in the vector storage of a segment
+///
+/// Offsets of deleted points keep their external id, so the mapping always covers the whole
+/// storage, but only live points can be looked up by external id.
+#[derive(Debug, Default)]
+pub struct IdTracker {
+    internal_to_external: Vec<PointIdType>,
+    external_to_internal: HashMap<PointIdType, PointOffsetType>,
+}
+
+impl IdTracker {
+    pub fn get_path(segment_path: &Path) -> PathBuf {
+        segment_path.join(ID_TRACKER_FILE)
+    }
+
+    /// Load the mapping, points for which `is_deleted` returns true are not linked
+    pub fn load(
+        segment_path: &Path,
+        is_deleted: impl Fn(PointOffsetType) -> bool,
+    ) -> OperationResult<Self> {
+        let path = Self::get_path(segment_path);
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        let internal_to_external: Vec<PointIdType> = read_json(&path)?;
+        let external_to_internal = internal_to_external
+            .iter()
+            .enumerate()
+            .map(|(internal, external)| (*external, internal as PointOffsetType))
+            .filter(|(_, internal)| !is_deleted(*internal))
+            .collect();
+        Ok(Self {
+            internal_to_external,
+            external_to_internal,
+        })
+    }
+
+    pub fn save(&self, segment_path: &Path) -> OperationResult<()> {
+        Ok(atomic_save_json(
+            &Self::get_path(segment_path),
+            &self.internal_to_external,
+        )?)
+    }
+
+    /// Number of linked offsets, including deleted points
+    pub fn total_len(&self) -> usize {
+        self.internal_to_external.len()
+    }
+
+    /// Forget offsets starting from `len`, used when the storage lost unflushed vectors
+    pub fn truncate(&mut self, len: usize) {
+        for external in self.internal_to_external.drain(len.min(self.total_len())..) {
+            self.external_to_internal.remove(&external);
+        }
+    }
+
+    pub fn internal_id(&self, external_id: PointIdType) -> Option<PointOffsetType> {
+        self.external_to_internal.get(&external_id).copied()
+    }
+
+    pub fn external_id(&self, internal_id: PointOffsetType) -> Option<PointIdType> {
+        self.internal_to_external.get(internal_id as usize).copied()
+    }
+
+    /// Link the external id to the offset, the offset is either linked already or the next one
+    pub fn set_link(&mut self, external_id: PointIdType, internal_id: PointOffsetType) {
+        let internal = internal_id as usize;
+        if internal < self.internal_to_external.len() {
+            self.internal_to_external[internal] = external_id;
+        } else {
+            debug_assert_eq!(internal, self.internal_to_external.len());
+            self.internal_to_external.push(external_id);
+        }
+        self.external_to_internal.insert(external_id, internal_id);
+    }
+
+    /// Unlink the external id, returns the offset it was linked to
+    pub fn drop(&mut self, external_id: PointIdType) -> Option<PointOffsetType> {
+        self.external_to_internal.remove(&external_id)
+    }
+
+    /// Live points with their offsets, ordered by offset
+    pub fn iter_points(&self) -> impl Iterator<Item = (PointIdType, PointOffsetType)> + '_ {
+        self.internal_to_external
+            .iter()
+            .enumerate()
+            .map(|(internal, external)| (*external, internal as PointOffsetType))
+            .filter(|(external, internal)| self.internal_id(*external) == Some(*internal))
+    }
+
+    pub fn points_count(&self) -> usize {
+        self.external_to_internal.len()
+    }
+
+    /// Largest numeric id of the live points
+    pub fn max_num_id(&self) -> Option<u64> {
+        self.external_to_internal
+            .keys()
+            .filter_map(|id| match id {
+                PointIdType::NumId(num) => Some(*num),
+                PointIdType::Uuid(_) => None,
+            })
+            .max()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tempfile::Builder;
+
+    use super::*;
+
+    #[test]
+    fn test_links_survive_reload() {
+        let dir = Builder::new().prefix("id_tracker").tempdir().unwrap();
+        let uuid = PointIdType::Uuid(uuid::Uuid::new_v4());
+        let mut tracker = IdTracker::default();
+        tracker.set_link(10.into(), 0);
+        tracker.set_link(uuid, 1);
+        tracker.set_link(3.into(), 2);
+        assert_eq!(tracker.drop(3.into()), Some(2));
+        tracker.save(dir.path()).unwrap();
+
+        let tracker = IdTracker::load(dir.path(), |internal| internal == 2).unwrap();
+        assert_eq!(tracker.total_len(), 3);
+        assert_eq!(tracker.internal_id(uuid), Some(1));
+        assert_eq!(tracker.internal_id(3.into()), None);
+        assert_eq!(tracker.external_id(2), Some(3.into()));
+        assert_eq!(tracker.max_num_id(), Some(10));
+        let points: Vec<_> = tracker.iter_points().collect();
+        assert_eq!(points, vec![(10.into(), 0), (uuid, 1)]);
+    }
+}
@@ -0,0 +1,4 @@
+pub mod holder;
+pub mod id_tracker;
+pub mod optimizer;
+pub mod segment;
@@ -0,0 +1,434 @@
+use std::fs::{remove_dir_all, remove_file};
+use std::num::NonZeroUsize;
+use std::path::Path;
+use std::sync::atomic::AtomicBool;
+
+use io::file_operations::{atomic_save_json, read_json};
+use parking_lot::RwLock;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use validator::Validate;
+
+use crate::common::operation_error::{check_process_stopped, OperationError, OperationResult};
+use crate::engine::segments::holder::{SegmentHolder, SegmentId};
+use crate::engine::segments::segment::{Segment, SegmentConfig};
+use crate::engine::storage::types::VectorStorageType;
+
+const OPTIMIZATION_JOURNAL_FILE: &str = "optimization.json";
+
+/// Default maximum size of vectors for a plain segment, in kilobytes
+pub const DEFAULT_INDEXING_THRESHOLD_KB: usize = 20_000;
+/// Default maximum size of vectors in a segment created by merging, in kilobytes
+pub const DEFAULT_MAX_SEGMENT_SIZE_KB: usize = 200_000;
+
+#[derive(Debug, Deserialize, Serialize, JsonSchema, Validate, Clone, PartialEq)]
+#[serde(default)]
+pub struct OptimizersConfig {
+    /// The minimal fraction of deleted vectors in a segment, required to perform segment optimization
+    #[validate(range(min = 0.0, max = 1.0))]
+    pub deleted_threshold: f64,
+    /// The minimal number of vectors in a segment, required to perform segment optimization
+    pub vacuum_min_vector_number: usize,
+    /// Target amount of segments optimizer will try to keep.
+    /// If `default_segment_number = 0`, will be automatically selected by the number of available CPUs.
+    pub default_segment_number: usize,
+    /// Do not create segments larger this size (in KiloBytes) by merging.
+    #[serde(alias = "max_segment_size")]
+    pub max_segment_size_kb: Option<usize>,
+    /// Maximum size (in KiloBytes) of vectors to store in-memory per segment.
+    /// Segments larger than this threshold will be stored as read-only memmaped file.
+    /// If not set or `0`, memmap optimization is disabled.
+    #[serde(alias = "memmap_threshold")]
+    pub memmap_threshold_kb: Option<usize>,
+    /// Maximum size (in KiloBytes) of vectors allowed for plain index.
+    /// To explicitly disable vector indexing, set to `0`.
+    #[serde(alias = "indexing_threshold")]
+    pub indexing_threshold_kb: Option<usize>,
+    /// Interval between forced flushes.
+    pub flush_interval_sec: u64,
+    /// If `max_optimization_threads = 0`, optimization will be disabled.
+    pub max_optimization_threads: usize,
+}
+
+impl Default for OptimizersConfig {
+    fn default() -> Self {
+        OptimizersConfig {
+            deleted_threshold: 0.2,
+            vacuum_min_vector_number: 1000,
+            default_segment_number: 0,
+            max_segment_size_kb: None,
+            memmap_threshold_kb: None,
+            indexing_threshold_kb: Some(DEFAULT_INDEXING_THRESHOLD_KB),
+            flush_interval_sec: 5,
+            max_optimization_threads: 1,
+        }
+    }
+}
+
+impl OptimizersConfig {
+    pub fn indexing_threshold_kb(&self) -> Option<usize> {
+        match self.indexing_threshold_kb {
+            None => Some(DEFAULT_INDEXING_THRESHOLD_KB),
+            Some(0) => None,
+            Some(threshold) => Some(threshold),
+        }
+    }
+
+    pub fn memmap_threshold_kb(&self) -> Option<usize> {
+        self.memmap_threshold_kb.filter(|threshold| *threshold > 0)
+    }
+
+    pub fn max_segment_size_kb(&self) -> usize {
+        self.max_segment_size_kb
+            .unwrap_or(DEFAULT_MAX_SEGMENT_SIZE_KB)
+    }
+
+    pub fn default_segment_number(&self) -> usize {
+        match self.default_segment_number {
+            0 => std::thread::available_parallelism().map_or(1, NonZeroUsize::get),
+            number => number,
+        }
+    }
+
+    /// Segments of this size should have an HNSW index
+    pub fn should_index(&self, size_kb: usize) -> bool {
+        self.indexing_threshold_kb()
+            .is_some_and(|threshold| size_kb >= threshold)
+    }
+
+    /// Segments of this size should keep vectors in a memory mapped file
+    pub fn should_memmap(&self, size_kb: usize) -> bool {
+        self.memmap_threshold_kb()
+            .is_some_and(|threshold| size_kb >= threshold)
+    }
+
+    /// Select the next optimization to perform, if any segment needs one
+    pub fn plan(&self, segments: &[SegmentInfo]) -> Option<OptimizationPlan> {
+        let single = |kind, info: &SegmentInfo| {
+            Some(OptimizationPlan {
+                kind,
+                segments: vec![info.id],
+            })
+        };
+
+        if let Some(info) = segments.iter().find(|info| {
+            info.deleted_vector_count > 0
+                && info.total_vector_count >= self.vacuum_min_vector_number
+                && info.deleted_ratio() >= self.deleted_threshold
+        }) {
+            return single(OptimizerKind::Vacuum, info);
+        }
+
+        if let Some(info) = segments
+            .iter()
+            .find(|info| !info.indexed && self.should_index(info.size_kb))
+        {
+            return single(OptimizerKind::Indexing, info);
+        }
+
+        if let Some(info) = segments.iter().find(|info| {
+            info.storage_type != VectorStorageType::Memmap && self.should_memmap(info.size_kb)
+        }) {
+            return single(OptimizerKind::Memmap, info);
+        }
+
+        let mut sealed: Vec<&SegmentInfo> =
+            segments.iter().filter(|info| !info.appendable).collect();
+        let target_number = self.default_segment_number();
+        if sealed.len() > target_number {
+            sealed.sort_by_key(|info| info.size_kb);
+            // Merging `n` segments reduces the number of segments by `n - 1`
+            let merge_number = sealed.len() - target_number + 1;
+            let max_size_kb = self.max_segment_size_kb();
+            let mut size_kb = 0;
+            let mut selected = Vec::new();
+            for info in sealed.into_iter().take(merge_number) {
+                if size_kb + info.size_kb > max_size_kb {
+                    break;
+                }
+                size_kb += info.size_kb;
+                selected.push(info.id);
+            }
+            if selected.len() >= 2 {
+                return Some(OptimizationPlan {
+                    kind: OptimizerKind::Merge,
+                    segments: selected,
+                });
+            }
+        }
+
+        None
+    }
+}
+
+/// Vacuum of every segment with deleted points, regardless of the thresholds
+pub fn vacuum_plans(segments: &[SegmentInfo]) -> Vec<OptimizationPlan> {
+    segments
+        .iter()
+        .filter(|info| info.deleted_vector_count > 0)
+        .map(|info| OptimizationPlan {
+            kind: OptimizerKind::Vacuum,
+            segments: vec![info.id],
+        })
+        .collect()
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct SegmentInfo {
+    pub id: SegmentId,
+    pub appendable: bool,
+    pub indexed: bool,
+    pub storage_type: VectorStorageType,
+    pub points_count: usize,
+    pub total_vector_count: usize,
+    pub deleted_vector_count: usize,
+    pub size_kb: usize,
+}
+
+impl SegmentInfo {
+    pub fn new(id: SegmentId, segment: &Segment) -> Self {
+        SegmentInfo {
+            id,
+            appendable: segment.is_appendable(),
+            indexed: segment.is_indexed(),
+            storage_type: segment.config().storage_type,
+            points_count: segment.points_count(),
+            total_vector_count: segment.total_vector_count(),
+            deleted_vector_count: segment.deleted_vector_count(),
+            size_kb: segment.size_kb(),
+        }
+    }
+
+    pub fn deleted_ratio(&self) -> f64 {
+        if self.total_vector_count == 0 {
+            return 0.0;
+        }
+        self.deleted_vector_count as f64 / self.total_vector_count as f64
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OptimizerKind {
+    /// Drop deleted points from the segment
+    Vacuum,
+    /// Build HNSW index for the segment which crossed the indexing threshold
+    Indexing,
+    /// Move vectors of the segment which crossed the memmap threshold to disk
+    Memmap,
+    /// Join small segments
+    Merge,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OptimizationPlan {
+    pub kind: OptimizerKind,
+    pub segments: Vec<SegmentId>,
+}
+
+/// Persisted state of a running optimization, so a restart keeps either the sources or the result
+#[derive(Debug, Deserialize, Serialize)]
+struct OptimizationJournal {
+    new_segment: SegmentId,
+    sources: Vec<SegmentId>,
+    /// Set once the new segment is complete and replaces the sources
+    swapped: bool,
+}
+
+impl OptimizationJournal {
+    fn save(&self, segments_path: &Path) -> OperationResult<()> {
+        Ok(atomic_save_json(
+            &segments_path.join(OPTIMIZATION_JOURNAL_FILE),
+            self,
+        )?)
+    }
+}
+
+/// Remove leftovers of an optimization interrupted by a restart
+pub fn recover_optimization(segments_path: &Path) -> OperationResult<()> {
+    let journal_path = segments_path.join(OPTIMIZATION_JOURNAL_FILE);
+    if !journal_path.exists() {
+        return Ok(());
+    }
+    let journal: OptimizationJournal = read_json(&journal_path)?;
+    let obsolete = if journal.swapped {
+        journal.sources
+    } else {
+        vec![journal.new_segment]
+    };
+    for id in obsolete {
+        let path = segments_path.join(id.to_string());
+        if path.exists() {
+            remove_dir_all(path)?;
+        }
+    }
+    remove_file(journal_path)?;
+    Ok(())
+}
+
+/// Rebuild the planned segments into a single new segment and swap it in
+///
+/// `segment_config` gives the config of the new segment by the size of its vectors in kilobytes.
+/// Appendable segments are sealed first, new points go to a fresh appendable segment meanwhile.
+/// Points deleted while the new segment is built are deleted from it before the swap.
+pub fn optimize(
+    holder: &RwLock<SegmentHolder>,
+    segments_path: &Path,
+    plan: &OptimizationPlan,
+    segment_config: impl Fn(usize) -> SegmentConfig,
+    with_async_io: bool,
+    stopped: &AtomicBool,
+) -> OperationResult<()> {
+    log::debug!("Optimizing segments {:?}: {:?}", plan.segments, plan.kind);
+    let (sources, new_id) = {
+        let mut holder = holder.write();
+        let mut sources = Vec::with_capacity(plan.segments.len());
+        for id in &plan.segments {
+            let source = holder.get(*id).cloned().ok_or_else(|| {
+                OperationError::service_error(format!("segment {id} is not in the collection"))
+            })?;
+            if source.read().is_appendable() {
+                let appendable_id = holder.reserve_id();
+                let config = SegmentConfig {
+                    appendable: true,
+                    ..source.read().config().clone()
+                };
+                let appendable = Segment::create(
+                    &segments_path.join(appendable_id.to_string()),
+                    config,
+                    with_async_io,
+                )?;
+                holder.add(appendable_id, appendable);
+                source.write().set_appendable(false)?;
+            }
+            sources.push(source);
+        }
+        (sources, holder.reserve_id())
+    };
+    let mut journal = OptimizationJournal {
+        new_segment: new_id,
+        sources: plan.segments.clone(),
+        swapped: false,
+    };
+    journal.save(segments_path)?;
+
+    let size_kb = sources.iter().map(|source| source.read().size_kb()).sum();
+    let new_path = segments_path.join(new_id.to_string());
+    let build = || {
+        let mut segment = Segment::create(&new_path, segment_config(size_kb), with_async_io)?;
+        let mut copied = Vec::with_capacity(sources.len());
+        for source in &sources {
+            check_process_stopped(stopped)?;
+            copied.push(segment.append_points_from(&source.read(), stopped)?);
+        }
+        check_process_stopped(stopped)?;
+        segment.build_index()?;
+        segment.flush()?;
+        OperationResult::Ok((segment, copied))
+    };
+    let (mut segment, copied) = match build() {
+        Ok(built) => built,
+        Err(err) => {
+            if new_path.exists() {
+                remove_dir_all(&new_path)?;
+            }
+            remove_file(segments_path.join(OPTIMIZATION_JOURNAL_FILE))?;
+            return Err(err);
+        }
+    };
+
+    let mut holder = holder.write();
+    for (source, copied) in sources.iter().zip(copied) {
+        let source = source.read();
+        let deleted: Vec<_> = copied
+            .into_iter()
+            .filter(|(_, old_internal_id, _)| source.is_deleted(*old_internal_id))
+            .map(|(point_id, _, _)| point_id)
+            .collect();
+        segment.delete_points(&deleted)?;
+    }
+    let segment = if segment.points_count() > 0 {
+        segment.flush()?;
+        Some(segment)
+    } else {
+        drop(segment);
+        remove_dir_all(&new_path)?;
+        None
+    };
+    journal.swapped = true;
+    journal.save(segments_path)?;
+    holder.remove(&plan.segments);
+    if let Some(segment) = segment {
+        holder.add(new_id, segment);
+    }
+    drop(holder);
+
+    for source in sources {
+        let path = source.read().path().to_owned();
+        // Close the storage before removing its files
+        drop(source);
+        remove_dir_all(path)?;
+    }
+    remove_file(segments_path.join(OPTIMIZATION_JOURNAL_FILE))?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn info(id: SegmentId, appendable: bool, size_kb: usize) -> SegmentInfo {
+        SegmentInfo {
+            id,
+            appendable,
+            indexed: false,
+            storage_type: VectorStorageType::Dense,
+            points_count: size_kb,
+            total_vector_count: size_kb,
+            deleted_vector_count: 0,
+            size_kb,
+        }
+    }
+
+    #[test]
+    fn test_plan() {
+        let config = OptimizersConfig {
+            vacuum_min_vector_number: 10,
+            default_segment_number: 2,
+            max_segment_size_kb: Some(100),
+            memmap_threshold_kb: Some(200),
+            indexing_threshold_kb: Some(50),
+            ..Default::default()
+        };
+        let mut segments = vec![info(0, false, 10), info(1, false, 40), info(2, true, 5)];
+        assert_eq!(config.plan(&segments), None);
+
+        segments.push(info(3, false, 30));
+        assert_eq!(
+            config.plan(&segments),
+            Some(OptimizationPlan {
+                kind: OptimizerKind::Merge,
+                segments: vec![0, 3],
+            })
+        );
+
+        segments[2].size_kb = 60;
+        assert_eq!(
+            config.plan(&segments).unwrap().kind,
+            OptimizerKind::Indexing
+        );
+        segments[2].indexed = true;
+
+        segments[2].size_kb = 250;
+        assert_eq!(config.plan(&segments).unwrap().kind, OptimizerKind::Memmap);
+
+        segments[1].deleted_vector_count = 10;
+        assert_eq!(
+            config.plan(&segments),
+            Some(OptimizationPlan {
+                kind: OptimizerKind::Vacuum,
+                segments: vec![1],
+            })
+        );
+    }
+}
@@ -0,0 +1,631 @@
+use std::collections::BTreeMap;
+use std::fs::create_dir_all;
+use std::mem::size_of;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::AtomicBool;
+use std::sync::Arc;
+use std::time::SystemTime;
+
+use atomic_refcell::AtomicRefCell;
+use io::file_operations::{atomic_save_json, read_json};
+use serde::{Deserialize, Serialize};
+
+use crate::common::operation_error::{OperationError, OperationResult};
+use crate::common::point_id::PointIdType;
+use crate::engine::index::hnsw::config::HnswGraphConfig;
+use crate::engine::index::hnsw::index::{HNSWIndex, IndexTelemetry};
+use crate::engine::index::plain::plain_search;
+use crate::engine::segments::id_tracker::IdTracker;
+use crate::engine::storage::payload_storage::PayloadStorage;
+use crate::engine::storage::quantized::config::QuantizationConfig;
+use crate::engine::storage::rocksdb::RocksDbStats;
+use crate::engine::storage::types::VectorStorageType;
+use crate::engine::storage::vector::base::{
+    open_vector_storage, DenseVectorStorage, VectorStorage, VectorStorageEnum,
+};
+use crate::engine::types::distance::Distance;
+use crate::engine::types::types::{
+    Payload, PointOffsetType, ScoreType, ScoredPointOffset, VectorElementType,
+};
+
+const SEGMENT_CONFIG_FILE: &str = "segment.json";
+const PAYLOAD_FILE: &str = "payload.json";
+const VECTOR_STORAGE_PATH: &str = "vectors";
+const INDEX_PATH: &str = "index";
+
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq)]
+pub struct SegmentConfig {
+    pub vector_size: usize,
+    pub distance: Distance,
+    pub storage_type: VectorStorageType,
+    /// Graph parameters of indexed segments. Segments without index are searched by full scan.
+    #[serde(default)]
+    pub index: Option<HnswGraphConfig>,
+    /// Quantization of the indexed vectors, only used together with `index`
+    #[serde(default)]
+    pub quantization: Option<QuantizationConfig>,
+    /// Appendable segments accept new points, other segments only accept deletes
+    pub appendable: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ScoredPoint {
+    pub id: PointIdType,
+    pub distance: ScoreType,
+    pub payload: Payload,
+}
+
+/// Files and memory usage of a segment
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct SegmentTelemetry {
+    /// Files of the vector storage relative to the segment directory, with their sizes in bytes
+    pub vector_files: BTreeMap<String, u64>,
+    /// Size of the vector files and the RocksDB vector column on disk
+    pub disk_bytes: u64,
+    /// Estimated size of the vectors kept in RAM and of the index
+    pub ram_bytes: usize,
+    pub index: Option<IndexTelemetry>,
+}
+
+/// Part of a collection with its own vector storage, point mapping, payloads and index
+pub struct Segment {
+    path: PathBuf,
+    config: SegmentConfig,
+    vector_storage: Arc<AtomicRefCell<VectorStorageEnum>>,
+    /// HNSW graph of indexed segments
+    index: Option<HNSWIndex<'static>>,
+    id_tracker: IdTracker,
+    payload_storage: PayloadStorage,
+}
+
+impl Segment {
+    pub fn create(
+        path: &Path,
+        config: SegmentConfig,
+        with_async_io: bool,
+    ) -> OperationResult<Self> {
+        create_dir_all(path)?;
+        atomic_save_json(&path.join(SEGMENT_CONFIG_FILE), &config)?;
+        Self::open(path, with_async_io)
+    }
+
+    /// Check if the directory contains a segment
+    pub fn exists(path: &Path) -> bool {
+        path.join(SEGMENT_CONFIG_FILE).exists()
+    }
+
+    pub fn open(path: &Path, with_async_io: bool) -> OperationResult<Self> {
+        let config: SegmentConfig = read_json(&path.join(SEGMENT_CONFIG_FILE))?;
+        let vector_storage = open_vector_storage(
+            &path.join(VECTOR_STORAGE_PATH),
+            config.vector_size,
+            config.distance,
+            config.storage_type,
+            with_async_io,
+        )?;
+
+        let id_tracker = {
+            let mut storage = vector_storage.borrow_mut();
+            let mut id_tracker =
+                IdTracker::load(path, |internal_id| storage.is_deleted_vector(internal_id))?;
+            // Vectors and mapping are flushed separately, drop points which are not complete
+            id_tracker.truncate(storage.total_vector_count());
+            for internal_id in id_tracker.total_len()..storage.total_vector_count() {
+                storage.delete_vector(internal_id as PointOffsetType)?;
+            }
+            id_tracker
+        };
+
+        let payload_path = path.join(PAYLOAD_FILE);
+        let payload_storage = if payload_path.exists() {
+            PayloadStorage {
+                payload: read_json(&payload_path)?,
+            }
+        } else {
+            PayloadStorage::default()
+        };
+
+        let mut segment = Segment {
+            path: path.to_owned(),
+            config,
+            vector_storage,
+            index: None,
+            id_tracker,
+            payload_storage,
+        };
+        segment.open_index()?;
+        Ok(segment)
+    }
+
+    /// Load the HNSW graph dumped on close, or build it if the dump is missing, older than the
+    /// storage or does not match it
+    fn open_index(&mut self) -> OperationResult<()> {
+        let Some(graph_config) = self.config.index else {
+            return Ok(());
+        };
+        let mut index = HNSWIndex::with_config(
+            self.vector_storage.clone(),
+            &self.path.join(INDEX_PATH),
+            graph_config,
+            self.config.quantization.clone(),
+        )?;
+        let dumped = index.graph_dump_modified();
+        let storage_modified = self.storage_modified();
+        if dumped.is_some_and(|dumped| storage_modified.is_none_or(|modified| dumped > modified)) {
+            match index.load_graph() {
+                Ok(()) => {
+                    self.index = Some(index);
+                    return Ok(());
+                }
+                Err(err) => log::warn!(
+                    "Rebuilding HNSW graph of segment {}: {err}",
+                    self.path.display()
+                ),
+            }
+        }
+        self.build_index()
+    }
+
+    /// Last time the point mapping or payloads were flushed, vectors are flushed before them
+    fn storage_modified(&self) -> Option<SystemTime> {
+        [
+            IdTracker::get_path(&self.path),
+            self.path.join(PAYLOAD_FILE),
+        ]
+        .iter()
+        .filter_map(|file| {
+            file.metadata()
+                .and_then(|metadata| metadata.modified())
+                .ok()
+        })
+        .max()
+    }
+
+    /// Build the HNSW graph from scratch on all stored points, if the segment is indexed
+    pub fn build_index(&mut self) -> OperationResult<()> {
+        self.index = match self.config.index {
+            Some(graph_config) => {
+                let mut index = HNSWIndex::with_config(
+                    self.vector_storage.clone(),
+                    &self.path.join(INDEX_PATH),
+                    graph_config,
+                    self.config.quantization.clone(),
+                )?;
+                index.build_graph(true)?;
+                index.save()?;
+                Some(index)
+            }
+            None => None,
+        };
+        Ok(())
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn config(&self) -> &SegmentConfig {
+        &self.config
+    }
+
+    pub fn is_appendable(&self) -> bool {
+        self.config.appendable
+    }
+
+    pub fn is_indexed(&self) -> bool {
+        self.index.is_some()
+    }
+
+    pub fn set_appendable(&mut self, appendable: bool) -> OperationResult<()> {
+        self.config.appendable = appendable;
+        Ok(atomic_save_json(
+            &self.path.join(SEGMENT_CONFIG_FILE),
+            &self.config,
+        )?)
+    }
+
+    pub fn has_point(&self, point_id: PointIdType) -> bool {
+        self.id_tracker.internal_id(point_id).is_some()
+    }
+
+    pub fn is_deleted(&self, internal_id: PointOffsetType) -> bool {
+        self.vector_storage.borrow().is_deleted_vector(internal_id)
+    }
+
+    /// Number of live points
+    pub fn points_count(&self) -> usize {
+        self.id_tracker.points_count()
+    }
+
+    /// Number of stored vectors, including deleted ones
+    pub fn total_vector_count(&self) -> usize {
+        self.vector_storage.borrow().total_vector_count()
+    }
+
+    pub fn deleted_vector_count(&self) -> usize {
+        self.vector_storage.borrow().deleted_vector_count()
+    }
+
+    /// Points per layer of the HNSW graph, empty for segments without index
+    pub fn index_layer_sizes(&self) -> Vec<usize> {
+        self.index
+            .as_ref()
+            .map(HNSWIndex::layer_sizes)
+            .unwrap_or_default()
+    }
+
+    /// Statistics of the RocksDB column of segments with RocksDB vector storage
+    pub fn rocksdb_stats(&self) -> OperationResult<Option<RocksDbStats>> {
+        match &*self.vector_storage.borrow() {
+            VectorStorageEnum::DenseSimple(storage) => storage.rocksdb_stats().map(Some),
+            VectorStorageEnum::Memmap(_) | VectorStorageEnum::AppendableMemmap(_) => Ok(None),
+        }
+    }
+
+    /// Sizes of the storage files and an estimate of the memory usage
+    ///
+    /// Memory mapped vectors are not counted in RAM, they live in the page cache.
+    pub fn telemetry(&self) -> OperationResult<SegmentTelemetry> {
+        let vector_storage = self.vector_storage.borrow();
+        let mut vector_files = BTreeMap::new();
+        for file in vector_storage.files() {
+            // Chunks of appendable storages may not be created yet
+            let Ok(metadata) = file.metadata() else {
+                continue;
+            };
+            let name = file.strip_prefix(&self.path).unwrap_or(&file);
+            vector_files.insert(name.to_string_lossy().into_owned(), metadata.len());
+        }
+        let mut disk_bytes = vector_files.values().sum();
+        let mut ram_bytes = 0;
+        if let VectorStorageEnum::DenseSimple(storage) = &*vector_storage {
+            disk_bytes += storage.rocksdb_stats()?.sst_files_bytes;
+            ram_bytes += vector_storage.total_vector_count()
+                * self.config.vector_size
+                * size_of::<VectorElementType>();
+        }
+        drop(vector_storage);
+        let index = self.index.as_ref().map(HNSWIndex::telemetry);
+        ram_bytes += index.as_ref().map_or(0, |index| index.ram_bytes);
+        Ok(SegmentTelemetry {
+            vector_files,
+            disk_bytes,
+            ram_bytes,
+            index,
+        })
+    }
+
+    /// Size of the live vectors in kilobytes
+    pub fn size_kb(&self) -> usize {
+        self.points_count() * self.config.vector_size * size_of::<VectorElementType>() / 1024
+    }
+
+    /// Vector of the point, if the point is in the segment
+    pub fn vector(&self, point_id: PointIdType) -> Option<Vec<VectorElementType>> {
+        let internal_id = self.id_tracker.internal_id(point_id)?;
+        Some(self.vector_storage.borrow().get_dense(internal_id).to_vec())
+    }
+
+    /// Ids of the live points
+    pub fn point_ids(&self) -> impl Iterator<Item = PointIdType> + '_ {
+        self.id_tracker.iter_points().map(|(point_id, _)| point_id)
+    }
+
+    pub fn max_num_id(&self) -> Option<u64> {
+        self.id_tracker.max_num_id()
+    }
+
+    /// Insert the point or replace its vector and payload
+    pub fn upsert_point(
+        &mut self,
+        point_id: PointIdType,
+        vector: &[VectorElementType],
+        payload: Payload,
+    ) -> OperationResult<()> {
+        if !self.config.appendable {
+            return Err(OperationError::service_error(format!(
+                "segment {} is not appendable",
+                self.path.display()
+            )));
+        }
+        let internal_id = self
+            .id_tracker
+            .internal_id(point_id)
+            .unwrap_or(self.id_tracker.total_len() as PointOffsetType);
+        // Payloads are kept by the segment, the storage only keeps vectors
+        self.vector_storage.borrow_mut().insert_vector(
+            internal_id,
+            vector.into(),
+            Payload::default(),
+        )?;
+        self.id_tracker.set_link(point_id, internal_id);
+        self.payload_storage.drop(internal_id)?;
+        if !payload.is_empty() {
+            self.payload_storage.assign(internal_id, &payload)?;
+        }
+        if let Some(index) = &mut self.index {
+            index.upsert(vector, internal_id);
+        }
+        Ok(())
+    }
+
+    /// Returns true if the point was in the segment
+    pub fn delete_point(&mut self, point_id: PointIdType) -> OperationResult<bool> {
+        Ok(self.delete_points(&[point_id])? > 0)
+    }
+
+    /// Returns the number of points which were in the segment
+    ///
+    /// The graph of indexed segments is repaired once for all the points, prefer it to
+    /// [`Self::delete_point`] for many points.
+    pub fn delete_points(&mut self, point_ids: &[PointIdType]) -> OperationResult<usize> {
+        let mut deleted = Vec::new();
+        for point_id in point_ids {
+            let Some(internal_id) = self.id_tracker.drop(*point_id) else {
+                continue;
+            };
+            self.vector_storage
+                .borrow_mut()
+                .delete_vector(internal_id)?;
+            self.payload_storage.drop(internal_id)?;
+            deleted.push(internal_id);
+        }
+        if let Some(index) = &mut self.index {
+            index.delete(&deleted);
+        }
+        Ok(deleted.len())
+    }
+
+    /// Copy live points of the other segment to the end of this one
+    ///
+    /// Returns copied points with their offsets in the other segment and in this one.
+    pub fn append_points_from(
+        &mut self,
+        other: &Segment,
+        stopped: &AtomicBool,
+    ) -> OperationResult<Vec<(PointIdType, PointOffsetType, PointOffsetType)>> {
+        let points: Vec<(PointIdType, PointOffsetType)> = other.id_tracker.iter_points().collect();
+        let other_storage = other.vector_storage.borrow();
+        let new_range = self.vector_storage.borrow_mut().update_from(
+            &other_storage,
+            &mut points.iter().map(|(_, internal_id)| *internal_id),
+            stopped,
+        )?;
+
+        let mut copied = Vec::with_capacity(points.len());
+        let mut replaced = Vec::new();
+        for ((point_id, old_internal_id), new_internal_id) in points.into_iter().zip(new_range) {
+            if let Some(previous) = self.id_tracker.internal_id(point_id) {
+                self.vector_storage.borrow_mut().delete_vector(previous)?;
+                self.payload_storage.drop(previous)?;
+                replaced.push(previous);
+            }
+            self.id_tracker.set_link(point_id, new_internal_id);
+            let payload = other.payload_storage.payload(old_internal_id)?;
+            if !payload.is_empty() {
+                self.payload_storage.assign(new_internal_id, &payload)?;
+            }
+            copied.push((point_id, old_internal_id, new_internal_id));
+        }
+        // Copied vectors are linked when the index is built
+        if let Some(index) = &mut self.index {
+            index.delete(&replaced);
+        }
+        Ok(copied)
+    }
+
+    /// `ef` of the HNSW search, indexed segments use their `ef_construct` if not set
+    pub fn search(
+        &self,
+        query: &[VectorElementType],
+        top: usize,
+        ef: Option<usize>,
+    ) -> OperationResult<Vec<ScoredPoint>> {
+        let vector_storage = self.vector_storage.borrow();
+        let scored: Vec<ScoredPointOffset> = match &self.index {
+            // Deleted points are unlinked from the graph, it only returns live points
+            Some(index) => index
+                .search_neighbours(query, top, ef)
+                .into_iter()
+                .map(|neighbour| ScoredPointOffset {
+                    idx: neighbour.d_id as PointOffsetType,
+                    score: neighbour.distance,
+                })
+                .collect(),
+            None => plain_search(&vector_storage, query, top),
+        };
+
+        let mut result = Vec::with_capacity(scored.len());
+        for scored in scored {
+            let Some(id) = self.id_tracker.external_id(scored.idx) else {
+                continue;
+            };
+            result.push(ScoredPoint {
+                id,
+                distance: scored.score,
+                payload: self.payload_storage.payload(scored.idx)?,
+            });
+        }
+        Ok(result)
+    }
+
+    pub fn flush(&self) -> OperationResult<()> {
+        // Vectors go first, points without complete vectors are dropped on load
+        self.vector_storage.borrow().flusher()()?;
+        self.id_tracker.save(&self.path)?;
+        atomic_save_json(&self.path.join(PAYLOAD_FILE), &self.payload_storage.payload)?;
+        Ok(())
+    }
+
+    /// Dump the HNSW graph of indexed segments, it is loaded when the segment is opened again
+    ///
+    /// The segment must be flushed first, dumps older than the storage are not loaded.
+    pub fn dump_index(&self) -> OperationResult<()> {
+        match &self.index {
+            Some(index) => index.dump_graph(),
+            None => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+    use tempfile::Builder;
+
+    use super::*;
+
+    fn segment_config(storage_type: VectorStorageType, index: bool) -> SegmentConfig {
+        SegmentConfig {
+            vector_size: 2,
+            distance: Distance::Euclidean,
+            storage_type,
+            index: index.then(|| {
+                HnswGraphConfig::new(100, 32, 32, 8, false, 16, 2, false, false, 100, 100, 10)
+            }),
+            quantization: None,
+            appendable: !index,
+        }
+    }
+
+    fn payload(value: u64) -> Payload {
+        serde_json::from_value(json!({ "value": value })).unwrap()
+    }
+
+    #[test]
+    fn test_upsert_delete_reopen() {
+        let dir = Builder::new().prefix("segment").tempdir().unwrap();
+        {
+            let config = segment_config(VectorStorageType::Dense, false);
+            let mut segment = Segment::create(dir.path(), config, false).unwrap();
+            for i in 0..10u64 {
+                segment
+                    .upsert_point(i.into(), &[i as f32, 0.0], payload(i))
+                    .unwrap();
+            }
+            // Replace in place
+            segment
+                .upsert_point(3.into(), &[30.0, 0.0], payload(30))
+                .unwrap();
+            assert!(segment.delete_point(4.into()).unwrap());
+            assert!(!segment.delete_point(4.into()).unwrap());
+            segment.flush().unwrap();
+        }
+
+        let segment = Segment::open(dir.path(), false).unwrap();
+        assert_eq!(segment.points_count(), 9);
+        assert_eq!(segment.total_vector_count(), 10);
+        let result = segment.search(&[4.2, 0.0], 3, None).unwrap();
+        let ids: Vec<_> = result.iter().map(|point| point.id).collect();
+        assert_eq!(ids, vec![5.into(), 6.into(), 2.into()]);
+        assert_eq!(result[0].payload, payload(5));
+
+        let result = segment.search(&[30.0, 0.0], 1, None).unwrap();
+        assert_eq!(result[0].id, 3.into());
+        assert_eq!(result[0].payload, payload(30));
+    }
+
+    #[test]
+    fn test_build_indexed_segment() {
+        let dir = Builder::new().prefix("segment").tempdir().unwrap();
+        let config = segment_config(VectorStorageType::AppendableMemmap, false);
+        let mut source = Segment::create(&dir.path().join("0"), config, false).unwrap();
+        for i in 0..50u64 {
+            source
+                .upsert_point(i.into(), &[i as f32, 1.0], payload(i))
+                .unwrap();
+        }
+        source.delete_point(7.into()).unwrap();
+
+        let config = segment_config(VectorStorageType::Memmap, true);
+        let mut indexed = Segment::create(&dir.path().join("1"), config, false).unwrap();
+        let copied = indexed
+            .append_points_from(&source, &AtomicBool::new(false))
+            .unwrap();
+        assert_eq!(copied.len(), 49);
+        indexed.build_index().unwrap();
+        assert!(indexed.is_indexed());
+
+        indexed.delete_point(8.into()).unwrap();
+        let result = indexed.search(&[8.2, 1.0], 2, None).unwrap();
+        let ids: Vec<_> = result.iter().map(|point| point.id).collect();
+        assert_eq!(ids, vec![9.into(), 10.into()]);
+        assert_eq!(result[1].payload, payload(10));
+    }
+
+    #[test]
+    fn test_reopen_indexed_segment_with_dumped_graph() {
+        let dir = Builder::new().prefix("segment").tempdir().unwrap();
+        let config = segment_config(VectorStorageType::AppendableMemmap, false);
+        let mut source = Segment::create(&dir.path().join("0"), config, false).unwrap();
+        for i in 0..50u64 {
+            source
+                .upsert_point(i.into(), &[(i % 10) as f32, (i / 10) as f32], payload(i))
+                .unwrap();
+        }
+        let config = segment_config(VectorStorageType::Memmap, true);
+        let path = dir.path().join("1");
+        let mut indexed = Segment::create(&path, config, false).unwrap();
+        indexed
+            .append_points_from(&source, &AtomicBool::new(false))
+            .unwrap();
+        indexed.build_index().unwrap();
+        indexed.delete_point(8.into()).unwrap();
+        let deleted_points = |segment: &Segment| segment.index.as_ref().unwrap().deleted_points();
+        // Deleted points keep their slot in the graph until it is built again
+        assert_eq!(deleted_points(&indexed), 1);
+        let layer_sizes = indexed.index_layer_sizes();
+        indexed.flush().unwrap();
+        indexed.dump_index().unwrap();
+        drop(indexed);
+
+        let mut indexed = Segment::open(&path, false).unwrap();
+        assert_eq!(deleted_points(&indexed), 1);
+        assert_eq!(indexed.index_layer_sizes(), layer_sizes);
+        let result = indexed.search(&[8.2, 0.0], 2, None).unwrap();
+        let ids: Vec<_> = result.iter().map(|point| point.id).collect();
+        assert_eq!(ids, vec![9.into(), 18.into()]);
+        assert_eq!(result[0].payload, payload(9));
+
+        // The dump is older than the storage after this delete, the graph is built again
+        indexed.delete_point(9.into()).unwrap();
+        indexed.flush().unwrap();
+        drop(indexed);
+        let indexed = Segment::open(&path, false).unwrap();
+        assert_eq!(deleted_points(&indexed), 0);
+        assert_eq!(indexed.points_count(), 48);
+        let result = indexed.search(&[8.2, 0.0], 1, None).unwrap();
+        assert_eq!(result[0].id, 18.into());
+    }
+
+    #[test]
+    fn test_update_indexed_segment() {
+        let dir = Builder::new().prefix("segment").tempdir().unwrap();
+        let config = SegmentConfig {
+            appendable: true,
+            ..segment_config(VectorStorageType::AppendableMemmap, true)
+        };
+        let mut segment = Segment::create(dir.path(), config, false).unwrap();
+        // Points of a grid, the graph of points on a line is too sparse to be searched reliably
+        for i in 0..50u64 {
+            segment
+                .upsert_point(i.into(), &[(i % 10) as f32, (i / 10) as f32], payload(i))
+                .unwrap();
+        }
+        // The graph follows replaced and deleted vectors
+        segment
+            .upsert_point(3.into(), &[4.5, 2.5], payload(100))
+            .unwrap();
+        let deleted = segment
+            .delete_points(&[4.into(), 5.into(), 60.into()])
+            .unwrap();
+        assert_eq!(deleted, 2);
+        let result = segment.search(&[4.2, 0.0], 2, None).unwrap();
+        let ids: Vec<_> = result.iter().map(|point| point.id).collect();
+        assert_eq!(ids, vec![14.into(), 15.into()]);
+
+        let result = segment.search(&[4.5, 2.5], 1, None).unwrap();
+        assert_eq!(result[0].id, 3.into());
+        assert_eq!(result[0].payload, payload(100));
+    }
+}
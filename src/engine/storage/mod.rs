@@ -1,4 +1,5 @@
 pub mod payload_storage;
+pub mod quantized;
 pub mod rocksdb;
 pub mod types;
 pub mod vector;
@@ -0,0 +1,83 @@
+use hnsw_rs::dist::Distance as HnswDistance;
+
+use crate::engine::types::types::VectorElementType;
+
+const BITS_PER_WORD: usize = u64::BITS as usize;
+
+/// Keeps a single bit per vector component: set if the component is positive
+///
+/// Bits are packed into `u64` words, the last word is padded with zeros.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BinaryQuantizer {
+    dim: usize,
+}
+
+impl BinaryQuantizer {
+    pub fn new(dim: usize) -> Self {
+        Self { dim }
+    }
+
+    pub fn dim(&self) -> usize {
+        self.dim
+    }
+
+    /// Number of `u64` words per encoded vector
+    pub fn words(&self) -> usize {
+        self.dim.div_ceil(BITS_PER_WORD)
+    }
+
+    pub fn encode(&self, vector: &[VectorElementType]) -> Vec<u64> {
+        let mut words = vec![0u64; self.words()];
+        for (i, _) in vector.iter().enumerate().filter(|(_, x)| **x > 0.0) {
+            words[i / BITS_PER_WORD] |= 1 << (i % BITS_PER_WORD);
+        }
+        words
+    }
+}
+
+/// Number of differing bits of packed binary vectors
+///
+/// `hnsw_rs::dist::DistHamming` compares whole `u64` words, which is too coarse for packed bits.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct BinaryHammingDistance;
+
+impl HnswDistance<u64> for BinaryHammingDistance {
+    fn eval(&self, va: &[u64], vb: &[u64]) -> f32 {
+        va.iter()
+            .zip(vb)
+            .map(|(a, b)| (a ^ b).count_ones())
+            .sum::<u32>() as f32
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_encode_packs_sign_bits() {
+        let quantizer = BinaryQuantizer::new(70);
+        let mut vector = vec![-1.0; 70];
+        vector[0] = 0.5;
+        vector[63] = 2.0;
+        vector[64] = 0.1;
+        vector[69] = 1.0;
+
+        let codes = quantizer.encode(&vector);
+        assert_eq!(codes, vec![1 | 1 << 63, 1 | 1 << 5]);
+    }
+
+    #[test]
+    fn test_hamming_counts_bits() {
+        let quantizer = BinaryQuantizer::new(128);
+        let a = quantizer.encode(&[1.0; 128]);
+        let mut b = vec![1.0; 128];
+        b[3] = -1.0;
+        b[100] = -1.0;
+        b[101] = 0.0;
+        let b = quantizer.encode(&b);
+
+        assert_eq!(BinaryHammingDistance.eval(&a, &a), 0.0);
+        assert_eq!(BinaryHammingDistance.eval(&a, &b), 3.0);
+    }
+}
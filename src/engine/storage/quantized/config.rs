@@ -0,0 +1,110 @@
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use validator::Validate;
+
+#[derive(Debug, Deserialize, Serialize, JsonSchema, Clone, Copy, PartialEq, Eq, Hash, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum ScalarType {
+    /// Every vector component is stored as a single byte
+    #[default]
+    Int8,
+}
+
+#[derive(Debug, Deserialize, Serialize, JsonSchema, Validate, Clone, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub struct ScalarQuantizationConfig {
+    /// Type of quantized vector components
+    #[serde(default)]
+    pub r#type: ScalarType,
+    /// Quantile of vector components used for calibration, must be in `[0.5, 1.0]`.
+    /// Components outside of the quantile are clamped. If not set, min/max of all components is used.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[validate(range(min = 0.5, max = 1.0))]
+    pub quantile: Option<f32>,
+    /// Re-rank the candidates found on quantized vectors with the original vectors. Default: true
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rescore: Option<bool>,
+    /// Number of candidates fetched from the quantized index is `oversampling * k`. Default: 2.0
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[validate(range(min = 1.0))]
+    pub oversampling: Option<f64>,
+}
+
+#[derive(Debug, Deserialize, Serialize, JsonSchema, Validate, Clone, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub struct ProductQuantizationConfig {
+    /// Number of sub-spaces every vector is split into. Every sub-space is encoded with one byte,
+    /// so a vector takes `subspaces` bytes. Must not exceed the vector dimension.
+    #[validate(range(min = 1))]
+    pub subspaces: usize,
+    /// Number of centroids in the codebook of every sub-space, at most 256. Default: 256
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[validate(range(min = 1, max = 256))]
+    pub centroids: Option<usize>,
+    /// Re-rank the candidates found on quantized vectors with the original vectors. Default: true
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rescore: Option<bool>,
+    /// Number of candidates fetched from the quantized index is `oversampling * k`. Default: 2.0
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[validate(range(min = 1.0))]
+    pub oversampling: Option<f64>,
+}
+
+#[derive(Debug, Deserialize, Serialize, JsonSchema, Validate, Clone, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub struct BinaryQuantizationConfig {
+    /// Re-rank the candidates found on quantized vectors with the original vectors. Default: true
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rescore: Option<bool>,
+    /// Number of candidates fetched from the quantized index is `oversampling * k`. Default: 2.0
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[validate(range(min = 1.0))]
+    pub oversampling: Option<f64>,
+}
+
+#[derive(Debug, Deserialize, Serialize, JsonSchema, Clone, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum QuantizationConfig {
+    Scalar(ScalarQuantizationConfig),
+    /// Vectors are only kept as product quantization codes in the index and searched
+    /// with asymmetric distance computation instead of the HNSW graph
+    Product(ProductQuantizationConfig),
+    /// Every vector component is stored as a single bit, the HNSW graph is traversed
+    /// with Hamming distance
+    Binary(BinaryQuantizationConfig),
+}
+
+impl Validate for QuantizationConfig {
+    fn validate(&self) -> Result<(), validator::ValidationErrors> {
+        match self {
+            QuantizationConfig::Scalar(config) => config.validate(),
+            QuantizationConfig::Product(config) => config.validate(),
+            QuantizationConfig::Binary(config) => config.validate(),
+        }
+    }
+}
+
+const DEFAULT_OVERSAMPLING: f64 = 2.0;
+
+impl QuantizationConfig {
+    pub fn rescore(&self) -> bool {
+        match self {
+            QuantizationConfig::Scalar(config) => config.rescore,
+            QuantizationConfig::Product(config) => config.rescore,
+            QuantizationConfig::Binary(config) => config.rescore,
+        }
+        .unwrap_or(true)
+    }
+
+    /// Number of candidates to fetch from the quantized vectors to answer a top-`top` query
+    pub fn oversampled_top(&self, top: usize) -> usize {
+        let oversampling = match self {
+            QuantizationConfig::Scalar(config) => config.oversampling,
+            QuantizationConfig::Product(config) => config.oversampling,
+            QuantizationConfig::Binary(config) => config.oversampling,
+        }
+        .unwrap_or(DEFAULT_OVERSAMPLING)
+        .max(1.0);
+        (top as f64 * oversampling).ceil() as usize
+    }
+}
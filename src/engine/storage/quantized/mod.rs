@@ -0,0 +1,10 @@
+pub mod binary;
+pub mod config;
+pub mod product;
+pub mod scalar;
+
+use crate::engine::types::types::VectorElementType;
+
+/// Range of vector components assumed when there are no vectors to calibrate on.
+/// Fits normalized embeddings.
+pub const DEFAULT_RANGE: (VectorElementType, VectorElementType) = (-1.0, 1.0);
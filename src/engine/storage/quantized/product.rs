@@ -0,0 +1,394 @@
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+
+use io::file_operations::{atomic_save_json, read_json};
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::common::operation_error::{OperationError, OperationResult};
+use crate::engine::storage::quantized::config::ProductQuantizationConfig;
+use crate::engine::storage::quantized::DEFAULT_RANGE;
+use crate::engine::types::distance::Distance;
+use crate::engine::types::types::VectorElementType;
+
+pub const PRODUCT_QUANTIZER_FILE: &str = "product_quantizer.json";
+
+/// Maximal number of vectors used to train the codebooks
+const TRAINING_SAMPLE_SIZE: usize = 10_000;
+
+const KMEANS_ITERATIONS: usize = 16;
+
+const KMEANS_SEED: u64 = 42;
+
+const DEFAULT_CENTROIDS: usize = 256;
+
+/// Splits vectors into sub-spaces and encodes every sub-vector with the index of its nearest
+/// centroid in the sub-space codebook
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq)]
+pub struct ProductQuantizer {
+    dim: usize,
+    distance: Distance,
+    /// Centroids of every sub-space, flattened. Centroid `c` of sub-space `s` is
+    /// `codebooks[s][c * sub_dim..(c + 1) * sub_dim]`.
+    codebooks: Vec<Vec<VectorElementType>>,
+}
+
+impl ProductQuantizer {
+    /// Train the codebooks with k-means on a sample of the given vectors
+    ///
+    /// If there are no vectors to train on, centroids are spread randomly over the default range.
+    pub fn train<'a>(
+        vectors: impl Iterator<Item = &'a [VectorElementType]>,
+        dim: usize,
+        distance: Distance,
+        config: &ProductQuantizationConfig,
+    ) -> OperationResult<Self> {
+        check_distance(distance)?;
+        if config.subspaces == 0 || config.subspaces > dim {
+            return Err(OperationError::ValidationError {
+                description: format!(
+                    "number of product quantization subspaces must be in 1..={dim}, got {}",
+                    config.subspaces
+                ),
+            });
+        }
+
+        let sample: Vec<&[VectorElementType]> = vectors
+            .filter(|vector| vector.len() == dim && vector.iter().all(|x| x.is_finite()))
+            .take(TRAINING_SAMPLE_SIZE)
+            .collect();
+        let centroids = config
+            .centroids
+            .unwrap_or(DEFAULT_CENTROIDS)
+            .clamp(1, DEFAULT_CENTROIDS);
+
+        let codebooks = (0..config.subspaces)
+            .into_par_iter()
+            .map(|subspace| {
+                let range = subspace_range(dim, config.subspaces, subspace);
+                let points: Vec<&[VectorElementType]> =
+                    sample.iter().map(|vector| &vector[range.clone()]).collect();
+                train_codebook(
+                    &points,
+                    range.len(),
+                    centroids,
+                    KMEANS_SEED + subspace as u64,
+                )
+            })
+            .collect();
+
+        Ok(Self {
+            dim,
+            distance,
+            codebooks,
+        })
+    }
+
+    pub fn dim(&self) -> usize {
+        self.dim
+    }
+
+    /// Number of codes per vector
+    pub fn subspaces(&self) -> usize {
+        self.codebooks.len()
+    }
+
+    fn subspace_range(&self, subspace: usize) -> Range<usize> {
+        subspace_range(self.dim, self.subspaces(), subspace)
+    }
+
+    pub fn encode(&self, vector: &[VectorElementType]) -> Vec<u8> {
+        self.codebooks
+            .iter()
+            .enumerate()
+            .map(|(subspace, codebook)| {
+                let range = self.subspace_range(subspace);
+                nearest_centroid(codebook, range.len(), &vector[range]) as u8
+            })
+            .collect()
+    }
+
+    pub fn decode(&self, codes: &[u8]) -> Vec<VectorElementType> {
+        codes
+            .iter()
+            .zip(&self.codebooks)
+            .enumerate()
+            .flat_map(|(subspace, (code, codebook))| {
+                let sub_dim = self.subspace_range(subspace).len();
+                let start = *code as usize * sub_dim;
+                codebook[start..start + sub_dim].iter().copied()
+            })
+            .collect()
+    }
+
+    /// Precompute distances between the sub-vectors of a query and all centroids
+    pub fn lookup_table(&self, query: &[VectorElementType]) -> DistanceTable {
+        let mut partial = Vec::with_capacity(self.subspaces());
+        let mut norms = Vec::with_capacity(self.subspaces());
+        for (subspace, codebook) in self.codebooks.iter().enumerate() {
+            let range = self.subspace_range(subspace);
+            let sub_query = &query[range.clone()];
+            let centroids = codebook.chunks_exact(range.len());
+            match self.distance {
+                Distance::Euclidean => {
+                    partial.push(centroids.map(|c| squared_l2(sub_query, c)).collect())
+                }
+                Distance::Manhatten => partial.push(
+                    centroids
+                        .map(|c| sub_query.iter().zip(c).map(|(a, b)| (a - b).abs()).sum())
+                        .collect(),
+                ),
+                Distance::DotProduct | Distance::Cosine => {
+                    partial.push(centroids.clone().map(|c| dot(sub_query, c)).collect());
+                    norms.push(centroids.map(|c| dot(c, c)).collect());
+                }
+                _ => unreachable!("distance is checked when the quantizer is created"),
+            }
+        }
+        DistanceTable {
+            distance: self.distance,
+            partial,
+            norms,
+            query_norm: dot(query, query).sqrt(),
+        }
+    }
+
+    pub fn get_path(path: &Path) -> PathBuf {
+        path.join(PRODUCT_QUANTIZER_FILE)
+    }
+
+    pub fn load(path: &Path) -> OperationResult<Self> {
+        let quantizer: Self = read_json(path)?;
+        check_distance(quantizer.distance)?;
+        Ok(quantizer)
+    }
+
+    pub fn save(&self, path: &Path) -> OperationResult<()> {
+        Ok(atomic_save_json(path, self)?)
+    }
+}
+
+/// Asymmetric distance between a query and product quantization codes
+pub struct DistanceTable {
+    distance: Distance,
+    /// Per sub-space and centroid terms of the distance
+    partial: Vec<Vec<f32>>,
+    /// Squared norms of the centroids, only used for cosine distance
+    norms: Vec<Vec<f32>>,
+    query_norm: f32,
+}
+
+impl DistanceTable {
+    pub fn eval(&self, codes: &[u8]) -> f32 {
+        let sum = |table: &[Vec<f32>]| -> f32 {
+            codes
+                .iter()
+                .zip(table)
+                .map(|(code, terms)| terms[*code as usize])
+                .sum()
+        };
+        match self.distance {
+            Distance::Euclidean => sum(&self.partial).sqrt(),
+            Distance::Manhatten | Distance::DotProduct => sum(&self.partial),
+            Distance::Cosine => {
+                let norm = sum(&self.norms).sqrt();
+                if self.query_norm > 0.0 && norm > 0.0 {
+                    (1.0 - sum(&self.partial) / (self.query_norm * norm)).max(0.0)
+                } else {
+                    0.0
+                }
+            }
+            _ => unreachable!("distance is checked when the quantizer is created"),
+        }
+    }
+}
+
+fn check_distance(distance: Distance) -> OperationResult<()> {
+    match distance {
+        Distance::Euclidean | Distance::Manhatten | Distance::DotProduct | Distance::Cosine => {
+            Ok(())
+        }
+        _ => Err(OperationError::ValidationError {
+            description: format!("product quantization is not supported for {distance:?} distance"),
+        }),
+    }
+}
+
+/// Dimensions of the sub-space, the first `dim % subspaces` sub-spaces are one dimension shorter
+fn subspace_range(dim: usize, subspaces: usize, subspace: usize) -> Range<usize> {
+    subspace * dim / subspaces..(subspace + 1) * dim / subspaces
+}
+
+fn squared_l2(a: &[VectorElementType], b: &[VectorElementType]) -> f32 {
+    a.iter().zip(b).map(|(a, b)| (a - b) * (a - b)).sum()
+}
+
+fn dot(a: &[VectorElementType], b: &[VectorElementType]) -> f32 {
+    a.iter().zip(b).map(|(a, b)| a * b).sum()
+}
+
+fn nearest_centroid(
+    codebook: &[VectorElementType],
+    sub_dim: usize,
+    point: &[VectorElementType],
+) -> usize {
+    codebook
+        .chunks_exact(sub_dim)
+        .map(|centroid| squared_l2(point, centroid))
+        .enumerate()
+        .min_by(|(_, a), (_, b)| a.total_cmp(b))
+        .map_or(0, |(centroid, _)| centroid)
+}
+
+/// Lloyd's k-means, initialized with distinct random points
+fn train_codebook(
+    points: &[&[VectorElementType]],
+    sub_dim: usize,
+    centroids: usize,
+    seed: u64,
+) -> Vec<VectorElementType> {
+    let mut rng = StdRng::seed_from_u64(seed);
+    if points.is_empty() {
+        let (low, high) = DEFAULT_RANGE;
+        return (0..centroids * sub_dim)
+            .map(|_| rng.gen_range(low..high))
+            .collect();
+    }
+
+    let centroids = centroids.min(points.len());
+    let mut codebook: Vec<VectorElementType> =
+        rand::seq::index::sample(&mut rng, points.len(), centroids)
+            .iter()
+            .flat_map(|point| points[point].iter().copied())
+            .collect();
+
+    let mut assignment = vec![usize::MAX; points.len()];
+    for _ in 0..KMEANS_ITERATIONS {
+        let mut changed = false;
+        for (point, assigned) in points.iter().zip(assignment.iter_mut()) {
+            let nearest = nearest_centroid(&codebook, sub_dim, point);
+            changed |= nearest != *assigned;
+            *assigned = nearest;
+        }
+        if !changed {
+            break;
+        }
+
+        let mut sums = vec![0.0; codebook.len()];
+        let mut counts = vec![0usize; centroids];
+        for (point, centroid) in points.iter().zip(&assignment) {
+            counts[*centroid] += 1;
+            let sum = &mut sums[centroid * sub_dim..(centroid + 1) * sub_dim];
+            for (sum, x) in sum.iter_mut().zip(point.iter()) {
+                *sum += x;
+            }
+        }
+        // Empty clusters keep their previous centroid
+        for (centroid, count) in counts.iter().enumerate().filter(|(_, count)| **count > 0) {
+            let range = centroid * sub_dim..(centroid + 1) * sub_dim;
+            for (value, sum) in codebook[range.clone()].iter_mut().zip(&sums[range]) {
+                *value = sum / *count as f32;
+            }
+        }
+    }
+    codebook
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(subspaces: usize, centroids: Option<usize>) -> ProductQuantizationConfig {
+        ProductQuantizationConfig {
+            subspaces,
+            centroids,
+            rescore: None,
+            oversampling: None,
+        }
+    }
+
+    fn random_vectors(count: usize, dim: usize) -> Vec<Vec<f32>> {
+        let mut rng = StdRng::seed_from_u64(7);
+        (0..count)
+            .map(|_| (0..dim).map(|_| rng.gen_range(-1.0..1.0)).collect())
+            .collect()
+    }
+
+    #[test]
+    fn test_clustered_vectors_roundtrip() {
+        // Every sub-vector takes one of 4 values, so 4 centroids encode them exactly
+        let values = [[0.0, 1.0], [1.0, 0.0], [-1.0, 0.5], [0.5, -1.0]];
+        let vectors: Vec<Vec<f32>> = (0..64)
+            .map(|i| [values[i % 4], values[(i / 4) % 4], values[(i / 16) % 4]].concat())
+            .collect();
+        let quantizer = ProductQuantizer::train(
+            vectors.iter().map(|v| v.as_slice()),
+            6,
+            Distance::Euclidean,
+            &config(3, Some(4)),
+        )
+        .unwrap();
+
+        assert_eq!(quantizer.subspaces(), 3);
+        for vector in &vectors {
+            let codes = quantizer.encode(vector);
+            assert_eq!(codes.len(), 3);
+            assert_eq!(&quantizer.decode(&codes), vector);
+        }
+    }
+
+    #[test]
+    fn test_lookup_table_matches_decoded_distance() {
+        let dim = 10;
+        let vectors = random_vectors(500, dim);
+        let query = random_vectors(1, dim).pop().unwrap();
+        for distance in [
+            Distance::Euclidean,
+            Distance::Manhatten,
+            Distance::DotProduct,
+            Distance::Cosine,
+        ] {
+            // 10 dimensions over 4 sub-spaces checks uneven splits
+            let quantizer = ProductQuantizer::train(
+                vectors.iter().map(|v| v.as_slice()),
+                dim,
+                distance,
+                &config(4, Some(16)),
+            )
+            .unwrap();
+            let table = quantizer.lookup_table(&query);
+            for vector in vectors.iter().take(20) {
+                let codes = quantizer.encode(vector);
+                let expected = hnsw_rs::dist::Distance::eval(
+                    &distance,
+                    query.as_slice(),
+                    quantizer.decode(&codes).as_slice(),
+                );
+                let actual = table.eval(&codes);
+                assert!((expected - actual).abs() < 1e-4, "{distance:?}");
+            }
+        }
+    }
+
+    #[test]
+    fn test_train_without_vectors() {
+        let quantizer =
+            ProductQuantizer::train(std::iter::empty(), 8, Distance::Euclidean, &config(2, None))
+                .unwrap();
+        let codes = quantizer.encode(&[0.5; 8]);
+        assert_eq!(codes.len(), 2);
+        assert_eq!(quantizer.decode(&codes).len(), 8);
+    }
+
+    #[test]
+    fn test_invalid_subspaces() {
+        let result =
+            ProductQuantizer::train(std::iter::empty(), 4, Distance::Euclidean, &config(5, None));
+        assert!(result.is_err());
+        let result =
+            ProductQuantizer::train(std::iter::empty(), 4, Distance::Hamming, &config(2, None));
+        assert!(result.is_err());
+    }
+}
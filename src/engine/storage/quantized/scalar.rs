@@ -0,0 +1,246 @@
+use std::path::{Path, PathBuf};
+
+use hnsw_rs::dist::{DistL1, DistL2, Distance as HnswDistance};
+use io::file_operations::{atomic_save_json, read_json};
+use serde::{Deserialize, Serialize};
+
+use crate::common::operation_error::{OperationError, OperationResult};
+use crate::engine::storage::quantized::config::ScalarQuantizationConfig;
+use crate::engine::storage::quantized::DEFAULT_RANGE;
+use crate::engine::types::distance::Distance;
+use crate::engine::types::types::VectorElementType;
+
+pub const SCALAR_QUANTIZER_FILE: &str = "scalar_quantizer.json";
+
+/// Maximal number of vectors used to calibrate the quantization range
+const CALIBRATION_SAMPLE_SIZE: usize = 10_000;
+
+const MAX_CODE: VectorElementType = u8::MAX as VectorElementType;
+
+/// Maps every vector component linearly onto `0..=255`
+///
+/// A component `x` is encoded as `round((x - offset) / alpha)` and decoded as `alpha * code + offset`.
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, PartialEq)]
+pub struct ScalarQuantizer {
+    dim: usize,
+    distance: Distance,
+    /// Width of a single quantization step
+    alpha: f32,
+    /// Value decoded from code `0`
+    offset: f32,
+}
+
+impl ScalarQuantizer {
+    /// Calibrate the quantization range on a sample of the given vectors
+    pub fn train<'a>(
+        vectors: impl Iterator<Item = &'a [VectorElementType]>,
+        dim: usize,
+        distance: Distance,
+        config: &ScalarQuantizationConfig,
+    ) -> OperationResult<Self> {
+        check_distance(distance)?;
+
+        let mut components: Vec<VectorElementType> = vectors
+            .take(CALIBRATION_SAMPLE_SIZE)
+            .flat_map(|vector| vector.iter().copied())
+            .filter(|x| x.is_finite())
+            .collect();
+
+        let (min, max) = if components.is_empty() {
+            DEFAULT_RANGE
+        } else if let Some(quantile) = config.quantile {
+            components.sort_unstable_by(|a, b| a.total_cmp(b));
+            let last = (components.len() - 1) as f32;
+            let low = ((1.0 - quantile) / 2.0 * last).round() as usize;
+            let high = ((1.0 + quantile) / 2.0 * last).round() as usize;
+            (components[low], components[high])
+        } else {
+            components
+                .iter()
+                .fold((f32::MAX, f32::MIN), |(min, max), x| {
+                    (min.min(*x), max.max(*x))
+                })
+        };
+
+        let alpha = if max > min {
+            (max - min) / MAX_CODE
+        } else {
+            1.0
+        };
+        Ok(Self {
+            dim,
+            distance,
+            alpha,
+            offset: min,
+        })
+    }
+
+    pub fn dim(&self) -> usize {
+        self.dim
+    }
+
+    pub fn encode(&self, vector: &[VectorElementType]) -> Vec<u8> {
+        vector
+            .iter()
+            .map(|x| {
+                ((x - self.offset) / self.alpha)
+                    .round()
+                    .clamp(0.0, MAX_CODE) as u8
+            })
+            .collect()
+    }
+
+    pub fn decode(&self, codes: &[u8]) -> Vec<VectorElementType> {
+        codes
+            .iter()
+            .map(|code| self.alpha * *code as f32 + self.offset)
+            .collect()
+    }
+
+    /// Distance to use for an HNSW graph built on the encoded vectors
+    pub fn quantized_distance(&self) -> ScalarQuantizedDistance {
+        ScalarQuantizedDistance {
+            distance: self.distance,
+            alpha: self.alpha,
+            offset: self.offset,
+        }
+    }
+
+    pub fn get_path(path: &Path) -> PathBuf {
+        path.join(SCALAR_QUANTIZER_FILE)
+    }
+
+    pub fn load(path: &Path) -> OperationResult<Self> {
+        let quantizer: Self = read_json(path)?;
+        check_distance(quantizer.distance)?;
+        Ok(quantizer)
+    }
+
+    pub fn save(&self, path: &Path) -> OperationResult<()> {
+        Ok(atomic_save_json(path, self)?)
+    }
+}
+
+fn check_distance(distance: Distance) -> OperationResult<()> {
+    match distance {
+        Distance::Euclidean | Distance::Manhatten | Distance::Cosine => Ok(()),
+        _ => Err(OperationError::ValidationError {
+            description: format!("scalar quantization is not supported for {distance:?} distance"),
+        }),
+    }
+}
+
+/// Approximates the original distance on scalar quantized vectors
+///
+/// Euclidean and Manhattan distances are uniformly scaled by the quantization, so the integer
+/// implementations of `hnsw_rs` are reused. Cosine distance depends on the offset, the components
+/// are decoded on the fly instead.
+#[derive(Debug, Clone, Copy)]
+pub struct ScalarQuantizedDistance {
+    distance: Distance,
+    alpha: f32,
+    offset: f32,
+}
+
+impl HnswDistance<u8> for ScalarQuantizedDistance {
+    fn eval(&self, va: &[u8], vb: &[u8]) -> f32 {
+        match self.distance {
+            Distance::Euclidean => DistL2.eval(va, vb) * self.alpha,
+            Distance::Manhatten => DistL1.eval(va, vb) * self.alpha,
+            Distance::Cosine => {
+                let (dot, norm_a, norm_b) =
+                    va.iter()
+                        .zip(vb)
+                        .fold((0.0, 0.0, 0.0), |(dot, norm_a, norm_b), (a, b)| {
+                            let a = self.alpha * *a as f32 + self.offset;
+                            let b = self.alpha * *b as f32 + self.offset;
+                            (dot + a * b, norm_a + a * a, norm_b + b * b)
+                        });
+                if norm_a > 0.0 && norm_b > 0.0 {
+                    (1.0 - dot / (norm_a * norm_b).sqrt()).max(0.0)
+                } else {
+                    0.0
+                }
+            }
+            _ => unreachable!("distance is checked when the quantizer is created"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(quantile: Option<f32>) -> ScalarQuantizationConfig {
+        ScalarQuantizationConfig {
+            r#type: Default::default(),
+            quantile,
+            rescore: None,
+            oversampling: None,
+        }
+    }
+
+    #[test]
+    fn test_encode_decode_roundtrip() {
+        let vectors = vec![vec![-1.0, 0.0, 0.5], vec![0.25, 1.0, -0.5]];
+        let quantizer = ScalarQuantizer::train(
+            vectors.iter().map(|v| v.as_slice()),
+            3,
+            Distance::Euclidean,
+            &config(None),
+        )
+        .unwrap();
+
+        for vector in &vectors {
+            let decoded = quantizer.decode(&quantizer.encode(vector));
+            for (original, decoded) in vector.iter().zip(&decoded) {
+                assert!((original - decoded).abs() <= 2.0 / MAX_CODE);
+            }
+        }
+    }
+
+    #[test]
+    fn test_quantile_clamps_outliers() {
+        let mut vector: Vec<f32> = (0..1000).map(|i| i as f32 / 1000.0).collect();
+        vector.push(1000.0);
+        let quantizer = ScalarQuantizer::train(
+            std::iter::once(vector.as_slice()),
+            vector.len(),
+            Distance::Euclidean,
+            &config(Some(0.99)),
+        )
+        .unwrap();
+
+        let codes = quantizer.encode(&[0.5, 1000.0]);
+        assert_eq!(codes[1], u8::MAX);
+        // Outlier does not squash the rest of the range
+        assert!(codes[0] > 100 && codes[0] < 155);
+    }
+
+    #[test]
+    fn test_quantized_distance_close_to_original() {
+        let a = vec![0.1, -0.3, 0.8, 0.5];
+        let b = vec![-0.2, 0.4, 0.6, -0.1];
+        for distance in [Distance::Euclidean, Distance::Manhatten, Distance::Cosine] {
+            let quantizer = ScalarQuantizer::train(
+                [a.as_slice(), b.as_slice()].into_iter(),
+                4,
+                distance,
+                &config(None),
+            )
+            .unwrap();
+            let original = HnswDistance::eval(&distance, a.as_slice(), b.as_slice());
+            let quantized = quantizer
+                .quantized_distance()
+                .eval(&quantizer.encode(&a), &quantizer.encode(&b));
+            assert!((original - quantized).abs() < 0.05, "{distance:?}");
+        }
+    }
+
+    #[test]
+    fn test_unsupported_distance() {
+        let result =
+            ScalarQuantizer::train(std::iter::empty(), 4, Distance::DotProduct, &config(None));
+        assert!(result.is_err());
+    }
+}
@@ -1,4 +1,5 @@
 use rocksdb::{DBRecoveryMode, LogLevel, Options};
+use serde::Serialize;
 
 use crate::common::operation_error::OperationResult;
 
@@ -22,6 +23,17 @@ pub const DB_PAYLOAD_CF: &str = "payload";
 pub const DB_MAPPING_CF: &str = "mapping";
 pub const DB_VERSIONS_CF: &str = "version";
 
+/// Size statistics of a RocksDB column family
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize)]
+pub struct RocksDbStats {
+  /// Estimated number of keys
+  pub estimated_keys: u64,
+  /// Total size of the SST files
+  pub sst_files_bytes: u64,
+  /// Size of the memtables, not yet written to SST files
+  pub memtable_bytes: u64,
+}
+
 #[cfg(feature = "rock")]
 pub fn db_options() -> Options {
   let mut options: Options = Options::default();
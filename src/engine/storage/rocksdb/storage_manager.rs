@@ -20,7 +20,7 @@ use crate::{
     LockedDatabaseColumnWrapper,
   },
 };
-use crate::engine::storage::rocksdb::{DB_MAPPING_CF, db_options, DB_PAYLOAD_CF, DB_VERSIONS_CF, Flusher};
+use crate::engine::storage::rocksdb::{DB_MAPPING_CF, db_options, DB_PAYLOAD_CF, DB_VERSIONS_CF, Flusher, RocksDbStats};
 
 pub struct StorageManager
 {
@@ -214,6 +214,26 @@ impl DatabaseColumnWrapper
     Ok(db.cf_handle(&self.column_name).is_some())
   }
 
+  /// Integer property of the column family, like `rocksdb.estimate-num-keys`
+  pub fn property_int(&self, name: &str) -> OperationResult<Option<u64>>
+  {
+    let db = self.database.read();
+    let cf_handle = self.get_column_family(&db)?;
+    db.property_int_value_cf(cf_handle, name).map_err(|err| {
+      OperationError::service_error(format!("RocksDB property_int_value_cf error: {err}"))
+    })
+  }
+
+  /// Size statistics of the column family
+  pub fn stats(&self) -> OperationResult<RocksDbStats>
+  {
+    Ok(RocksDbStats {
+      estimated_keys: self.property_int("rocksdb.estimate-num-keys")?.unwrap_or_default(),
+      sst_files_bytes: self.property_int("rocksdb.total-sst-files-size")?.unwrap_or_default(),
+      memtable_bytes: self.property_int("rocksdb.cur-size-all-mem-tables")?.unwrap_or_default(),
+    })
+  }
+
   fn get_write_options() -> WriteOptions
   {
     let mut write_options = WriteOptions::default();
@@ -1,6 +1,11 @@
-use serde::Deserialize;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
 use validator::Validate;
 
+use crate::actix::table::collections::HnswConfig;
+use crate::engine::segments::optimizer::OptimizersConfig;
+use crate::engine::storage::quantized::config::QuantizationConfig;
+
 #[derive(Clone, Debug, Deserialize, Validate)]
 pub struct StorageConfig {
     #[validate(length(min = 1))]
@@ -13,6 +18,66 @@ pub struct StorageConfig {
     pub temp_path: Option<String>,
     #[serde(default = "default_on_disk_payload")]
     pub on_disk_payload: bool,
+    /// Default type of vector storage, could be overridden for each collection
+    #[serde(default)]
+    pub vector_storage_type: VectorStorageType,
+    #[serde(default)]
+    pub performance: PerformanceConfig,
+    /// Inserts fail with `OutOfMemory` while less memory is available to the process, in
+    /// megabytes. If `None`, inserts are not limited.
+    #[serde(default)]
+    pub min_available_memory_mb: Option<u64>,
+    /// Default quantization of indexed vectors. If `None`, vectors are not quantized.
+    #[serde(default)]
+    #[validate]
+    pub quantization: Option<QuantizationConfig>,
+    /// Default parameters of HNSW index, could be overridden for each collection
+    #[serde(default)]
+    #[validate]
+    pub hnsw_index: HnswConfig,
+    /// Default parameters of segment optimizers, could be overridden for each collection
+    #[serde(default)]
+    #[validate]
+    pub optimizers: OptimizersConfig,
+}
+
+#[derive(Debug, Deserialize, Serialize, JsonSchema, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum VectorStorageType {
+    /// Vectors are kept in RAM and persisted in RocksDB
+    #[default]
+    Dense,
+    /// Vectors are kept in a read-only memory mapped file. Used for large sealed data,
+    /// vectors can only be added with `VectorStorage::update_from`.
+    Memmap,
+    /// Vectors are kept in memory mapped chunks which accept inserts, updates and deletes
+    AppendableMemmap,
+}
+
+#[derive(Clone, Debug, Deserialize, Validate)]
+pub struct PerformanceConfig {
+    /// Number of parallel threads used for search operations. If 0 - auto selection.
+    #[serde(default)]
+    pub max_search_threads: usize,
+    /// Max number of threads used for optimizations across all collections
+    #[serde(default = "default_max_optimization_threads")]
+    pub max_optimization_threads: usize,
+    /// Read memory mapped vectors with io_uring, only available on Linux
+    #[serde(default)]
+    pub async_scorer: bool,
+}
+
+impl Default for PerformanceConfig {
+    fn default() -> Self {
+        PerformanceConfig {
+            max_search_threads: 0,
+            max_optimization_threads: default_max_optimization_threads(),
+            async_scorer: false,
+        }
+    }
+}
+
+fn default_max_optimization_threads() -> usize {
+    1
 }
 
 fn default_snapshots_path() -> String {
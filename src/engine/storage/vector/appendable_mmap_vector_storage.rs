@@ -0,0 +1,238 @@
+use std::fs::create_dir_all;
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::AtomicBool;
+use std::sync::Arc;
+
+use atomic_refcell::AtomicRefCell;
+use bitvec::prelude::BitSlice;
+
+use crate::common::operation_error::{check_process_stopped, OperationResult};
+use crate::engine::storage::payload_storage::PayloadStorage;
+use crate::engine::storage::rocksdb::Flusher;
+use crate::engine::storage::vector::base::{DenseVectorStorage, VectorStorage, VectorStorageEnum};
+use crate::engine::storage::vector::chunked_mmap_vectors::ChunkedMmapVectors;
+use crate::engine::storage::vector::dynamic_mmap_flags::DynamicMmapFlags;
+use crate::engine::types::cow_vector::CowVector;
+use crate::engine::types::distance::Distance;
+use crate::engine::types::types::{Payload, PointOffsetType, VectorElementType};
+use crate::engine::types::vector::VectorRef;
+
+const VECTORS_DIR_PATH: &str = "vectors";
+const DELETED_PATH: &str = "deleted.dat";
+
+/// Memory mapped vector storage which supports inserts, in-place updates and deletes
+pub struct AppendableMmapVectorStorage {
+    vectors: ChunkedMmapVectors<VectorElementType>,
+    deleted: DynamicMmapFlags,
+    distance: Distance,
+    payload_storage: PayloadStorage,
+    /// Current number of deleted vectors.
+    deleted_count: usize,
+}
+
+pub fn open_appendable_memmap_vector_storage(
+    path: &Path,
+    dim: usize,
+    distance: Distance,
+) -> OperationResult<Arc<AtomicRefCell<VectorStorageEnum>>> {
+    create_dir_all(path)?;
+
+    let vectors = ChunkedMmapVectors::open(&path.join(VECTORS_DIR_PATH), dim)?;
+    let deleted = DynamicMmapFlags::open(&path.join(DELETED_PATH))?;
+    let deleted_count = deleted.count_flags();
+
+    Ok(Arc::new(AtomicRefCell::new(
+        VectorStorageEnum::AppendableMemmap(Box::new(AppendableMmapVectorStorage {
+            vectors,
+            deleted,
+            distance,
+            payload_storage: PayloadStorage::default(),
+            deleted_count,
+        })),
+    )))
+}
+
+impl AppendableMmapVectorStorage {
+    /// Set deleted flag for given key. Returns previous deleted state.
+    fn set_deleted(&mut self, key: PointOffsetType, deleted: bool) -> OperationResult<bool> {
+        if key as usize >= self.vectors.len() {
+            return Ok(false);
+        }
+        let was_deleted = self.deleted.set(key as usize, deleted)?;
+        if was_deleted != deleted {
+            if !was_deleted {
+                self.deleted_count += 1;
+            } else {
+                self.deleted_count -= 1;
+            }
+        }
+        Ok(was_deleted)
+    }
+}
+
+impl DenseVectorStorage for AppendableMmapVectorStorage {
+    fn get_dense(&self, key: PointOffsetType) -> &[VectorElementType] {
+        self.vectors.get(key).expect("vector not found")
+    }
+}
+
+impl VectorStorage for AppendableMmapVectorStorage {
+    fn vector_dim(&self) -> usize {
+        self.vectors.dim()
+    }
+
+    fn distance(&self) -> Distance {
+        self.distance
+    }
+
+    fn is_on_disk(&self) -> bool {
+        true
+    }
+
+    fn total_vector_count(&self) -> usize {
+        self.vectors.len()
+    }
+
+    fn get_vector(&self, key: PointOffsetType) -> CowVector<'_> {
+        self.get_dense(key).into()
+    }
+
+    fn get_vector_opt(&self, key: PointOffsetType) -> Option<CowVector<'_>> {
+        self.vectors.get(key).map(CowVector::from)
+    }
+
+    fn insert_vector(
+        &mut self,
+        key: PointOffsetType,
+        vector: VectorRef,
+        payload: Payload,
+    ) -> OperationResult<()> {
+        let vector = vector.try_into()?;
+        self.vectors.insert(key, vector)?;
+        self.set_deleted(key, false)?;
+        self.payload_storage.assign(key, &payload)?;
+        Ok(())
+    }
+
+    fn get_payload(&self, key: PointOffsetType) -> OperationResult<Payload> {
+        self.payload_storage.payload(key)
+    }
+
+    fn update_from(
+        &mut self,
+        other: &VectorStorageEnum,
+        other_ids: &mut dyn Iterator<Item = PointOffsetType>,
+        stopped: &AtomicBool,
+    ) -> OperationResult<Range<PointOffsetType>> {
+        let start_index = self.vectors.len() as PointOffsetType;
+        for point_id in other_ids {
+            check_process_stopped(stopped)?;
+            // Do not perform preprocessing - vectors should be already processed
+            let other_vector = other.get_vector(point_id);
+            let other_vector = other_vector.as_vec_ref().try_into()?;
+            let other_deleted = other.is_deleted_vector(point_id);
+            let new_id = self.vectors.push(other_vector)?;
+            self.set_deleted(new_id, other_deleted)?;
+        }
+        let end_index = self.vectors.len() as PointOffsetType;
+        Ok(start_index..end_index)
+    }
+
+    fn flusher(&self) -> Flusher {
+        let vectors_flusher = self.vectors.flusher();
+        let deleted_flusher = self.deleted.flusher();
+        Box::new(move || {
+            vectors_flusher()?;
+            deleted_flusher()?;
+            Ok(())
+        })
+    }
+
+    fn files(&self) -> Vec<PathBuf> {
+        let mut files = self.vectors.files();
+        files.push(self.deleted.path().to_owned());
+        files
+    }
+
+    fn delete_vector(&mut self, key: PointOffsetType) -> OperationResult<bool> {
+        if key as usize >= self.vectors.len() {
+            return Ok(false);
+        }
+        Ok(!self.set_deleted(key, true)?)
+    }
+
+    fn is_deleted_vector(&self, key: PointOffsetType) -> bool {
+        self.deleted.get(key as usize)
+    }
+
+    fn deleted_vector_count(&self) -> usize {
+        self.deleted_count
+    }
+
+    fn deleted_vector_bitslice(&self) -> &BitSlice {
+        self.deleted.get_bitslice()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tempfile::Builder;
+
+    use super::*;
+
+    #[test]
+    fn test_insert_update_delete_persistence() {
+        let dir = Builder::new().prefix("storage_dir").tempdir().unwrap();
+        let points = [
+            vec![1.0, 0.0, 1.0, 1.0],
+            vec![1.0, 0.0, 1.0, 0.0],
+            vec![1.0, 1.0, 1.0, 1.0],
+        ];
+
+        {
+            let storage =
+                open_appendable_memmap_vector_storage(dir.path(), 4, Distance::Euclidean).unwrap();
+            let mut storage = storage.borrow_mut();
+            for (key, point) in points.iter().enumerate() {
+                storage
+                    .insert_vector(
+                        key as PointOffsetType,
+                        point.as_slice().into(),
+                        Payload::default(),
+                    )
+                    .unwrap();
+            }
+            // Update in place
+            storage
+                .insert_vector(
+                    0,
+                    [0.0, 0.0, 0.0, 1.0].as_slice().into(),
+                    Payload::default(),
+                )
+                .unwrap();
+
+            assert!(storage.delete_vector(1).unwrap());
+            assert!(!storage.delete_vector(1).unwrap());
+            assert!(!storage.delete_vector(10).unwrap());
+            assert_eq!(storage.deleted_vector_count(), 1);
+            storage.flusher()().unwrap();
+        }
+
+        let storage =
+            open_appendable_memmap_vector_storage(dir.path(), 4, Distance::Euclidean).unwrap();
+        let mut storage = storage.borrow_mut();
+        assert_eq!(storage.total_vector_count(), 3);
+        assert_eq!(storage.available_vector_count(), 2);
+        assert!(storage.is_deleted_vector(1));
+        assert_eq!(storage.get_dense(0), &[0.0, 0.0, 0.0, 1.0]);
+        assert_eq!(storage.get_dense(2), points[2].as_slice());
+
+        // Reinserting a deleted vector restores it
+        storage
+            .insert_vector(1, points[1].as_slice().into(), Payload::default())
+            .unwrap();
+        assert!(!storage.is_deleted_vector(1));
+        assert_eq!(storage.deleted_vector_count(), 0);
+    }
+}
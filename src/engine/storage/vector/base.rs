@@ -1,14 +1,25 @@
 use std::ops::Range;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::sync::atomic::AtomicBool;
+use std::sync::Arc;
 
+use atomic_refcell::AtomicRefCell;
 use bitvec::prelude::BitSlice;
 use clap::Parser;
 
-use crate::common::operation_error::OperationResult;
-use crate::engine::storage::rocksdb::Flusher;
-use crate::engine::storage::vector::dense_vector_storage::SimpleDenseVectorStorage;
-use crate::engine::storage::vector::mmap_vector_storage::MemmapVectorStorage;
+use crate::common::operation_error::{OperationError, OperationResult};
+use crate::engine::storage::rocksdb::storage_manager::StorageManager;
+use crate::engine::storage::rocksdb::{Flusher, DB_VECTOR_CF};
+use crate::engine::storage::types::VectorStorageType;
+use crate::engine::storage::vector::appendable_mmap_vector_storage::{
+    open_appendable_memmap_vector_storage, AppendableMmapVectorStorage,
+};
+use crate::engine::storage::vector::dense_vector_storage::{
+    open_simple_vector_storage, SimpleDenseVectorStorage,
+};
+use crate::engine::storage::vector::mmap_vector_storage::{
+    open_memmap_vector_storage_with_async_io, MemmapVectorStorage,
+};
 use crate::engine::types::cow_vector::CowVector;
 use crate::engine::types::distance::Distance;
 use crate::engine::types::types::{Payload, PointOffsetType, VectorElementType};
@@ -105,7 +116,34 @@ pub trait DenseVectorStorage: VectorStorage {
 pub enum VectorStorageEnum {
     DenseSimple(SimpleDenseVectorStorage),
     Memmap(Box<MemmapVectorStorage>),
-    // AppendableMemmap(Box<AppendableMmapVectorStorage>),
+    AppendableMemmap(Box<AppendableMmapVectorStorage>),
+}
+
+/// Open vector storage of the given type in the given directory
+pub fn open_vector_storage(
+    path: &Path,
+    dim: usize,
+    distance: Distance,
+    storage_type: VectorStorageType,
+    with_async_io: bool,
+) -> OperationResult<Arc<AtomicRefCell<VectorStorageEnum>>> {
+    match storage_type {
+        VectorStorageType::Dense => {
+            let database = StorageManager::open_db_with_existing_cf(path).map_err(|err| {
+                OperationError::service_error(format!("RocksDB open error: {err}"))
+            })?;
+            StorageManager::create_db_cf_if_not_exists(database.clone(), DB_VECTOR_CF).map_err(
+                |err| OperationError::service_error(format!("RocksDB create_cf error: {err}")),
+            )?;
+            open_simple_vector_storage(database, DB_VECTOR_CF, dim, distance)
+        }
+        VectorStorageType::Memmap => {
+            open_memmap_vector_storage_with_async_io(path, dim, distance, with_async_io)
+        }
+        VectorStorageType::AppendableMemmap => {
+            open_appendable_memmap_vector_storage(path, dim, distance)
+        }
+    }
 }
 
 impl VectorStorage for VectorStorageEnum {
@@ -113,6 +151,7 @@ impl VectorStorage for VectorStorageEnum {
         match self {
             VectorStorageEnum::DenseSimple(v) => v.vector_dim(),
             VectorStorageEnum::Memmap(v) => v.vector_dim(),
+            VectorStorageEnum::AppendableMemmap(v) => v.vector_dim(),
         }
     }
 
@@ -120,6 +159,7 @@ impl VectorStorage for VectorStorageEnum {
         match self {
             VectorStorageEnum::DenseSimple(v) => v.distance(),
             VectorStorageEnum::Memmap(v) => v.distance(),
+            VectorStorageEnum::AppendableMemmap(v) => v.distance(),
         }
     }
 
@@ -127,6 +167,7 @@ impl VectorStorage for VectorStorageEnum {
         match self {
             VectorStorageEnum::DenseSimple(v) => v.is_on_disk(),
             VectorStorageEnum::Memmap(v) => v.is_on_disk(),
+            VectorStorageEnum::AppendableMemmap(v) => v.is_on_disk(),
         }
     }
 
@@ -134,6 +175,7 @@ impl VectorStorage for VectorStorageEnum {
         match self {
             VectorStorageEnum::DenseSimple(v) => v.total_vector_count(),
             VectorStorageEnum::Memmap(v) => v.total_vector_count(),
+            VectorStorageEnum::AppendableMemmap(v) => v.total_vector_count(),
         }
     }
 
@@ -141,6 +183,7 @@ impl VectorStorage for VectorStorageEnum {
         match self {
             VectorStorageEnum::DenseSimple(v) => v.get_vector(key),
             VectorStorageEnum::Memmap(v) => v.get_vector(key),
+            VectorStorageEnum::AppendableMemmap(v) => v.get_vector(key),
         }
     }
 
@@ -148,6 +191,7 @@ impl VectorStorage for VectorStorageEnum {
         match self {
             VectorStorageEnum::DenseSimple(v) => v.get_vector_opt(key),
             VectorStorageEnum::Memmap(v) => v.get_vector_opt(key),
+            VectorStorageEnum::AppendableMemmap(v) => v.get_vector_opt(key),
         }
     }
 
@@ -160,6 +204,7 @@ impl VectorStorage for VectorStorageEnum {
         match self {
             VectorStorageEnum::DenseSimple(v) => v.insert_vector(key, vector, payload),
             VectorStorageEnum::Memmap(v) => v.insert_vector(key, vector, payload),
+            VectorStorageEnum::AppendableMemmap(v) => v.insert_vector(key, vector, payload),
         }
     }
 
@@ -172,6 +217,7 @@ impl VectorStorage for VectorStorageEnum {
         match self {
             VectorStorageEnum::DenseSimple(v) => v.update_from(other, other_ids, stopped),
             VectorStorageEnum::Memmap(v) => v.update_from(other, other_ids, stopped),
+            VectorStorageEnum::AppendableMemmap(v) => v.update_from(other, other_ids, stopped),
         }
     }
 
@@ -179,6 +225,7 @@ impl VectorStorage for VectorStorageEnum {
         match self {
             VectorStorageEnum::DenseSimple(v) => v.flusher(),
             VectorStorageEnum::Memmap(v) => v.flusher(),
+            VectorStorageEnum::AppendableMemmap(v) => v.flusher(),
         }
     }
 
@@ -186,6 +233,7 @@ impl VectorStorage for VectorStorageEnum {
         match self {
             VectorStorageEnum::DenseSimple(v) => v.files(),
             VectorStorageEnum::Memmap(v) => v.files(),
+            VectorStorageEnum::AppendableMemmap(v) => v.files(),
         }
     }
 
@@ -193,6 +241,7 @@ impl VectorStorage for VectorStorageEnum {
         match self {
             VectorStorageEnum::DenseSimple(v) => v.delete_vector(key),
             VectorStorageEnum::Memmap(v) => v.delete_vector(key),
+            VectorStorageEnum::AppendableMemmap(v) => v.delete_vector(key),
         }
     }
 
@@ -200,6 +249,7 @@ impl VectorStorage for VectorStorageEnum {
         match self {
             VectorStorageEnum::DenseSimple(v) => v.is_deleted_vector(key),
             VectorStorageEnum::Memmap(v) => v.is_deleted_vector(key),
+            VectorStorageEnum::AppendableMemmap(v) => v.is_deleted_vector(key),
         }
     }
 
@@ -207,6 +257,7 @@ impl VectorStorage for VectorStorageEnum {
         match self {
             VectorStorageEnum::DenseSimple(v) => v.deleted_vector_count(),
             VectorStorageEnum::Memmap(v) => v.deleted_vector_count(),
+            VectorStorageEnum::AppendableMemmap(v) => v.deleted_vector_count(),
         }
     }
 
@@ -214,6 +265,7 @@ impl VectorStorage for VectorStorageEnum {
         match self {
             VectorStorageEnum::DenseSimple(v) => v.deleted_vector_bitslice(),
             VectorStorageEnum::Memmap(v) => v.deleted_vector_bitslice(),
+            VectorStorageEnum::AppendableMemmap(v) => v.deleted_vector_bitslice(),
         }
     }
 
@@ -221,6 +273,17 @@ impl VectorStorage for VectorStorageEnum {
         match self {
             VectorStorageEnum::DenseSimple(v) => v.get_payload(key),
             VectorStorageEnum::Memmap(v) => v.get_payload(key),
+            VectorStorageEnum::AppendableMemmap(v) => v.get_payload(key),
+        }
+    }
+}
+
+impl DenseVectorStorage for VectorStorageEnum {
+    fn get_dense(&self, key: PointOffsetType) -> &[VectorElementType] {
+        match self {
+            VectorStorageEnum::DenseSimple(v) => v.get_dense(key),
+            VectorStorageEnum::Memmap(v) => v.get_dense(key),
+            VectorStorageEnum::AppendableMemmap(v) => v.get_dense(key),
         }
     }
 }
@@ -233,3 +296,35 @@ impl VectorStorageEnum {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use tempfile::Builder;
+
+    use super::*;
+
+    #[test]
+    fn test_open_vector_storage_types() {
+        let dir = Builder::new().prefix("storage_dir").tempdir().unwrap();
+        let vector = [1.0, 0.0, 1.0];
+        for storage_type in [
+            VectorStorageType::Dense,
+            VectorStorageType::Memmap,
+            VectorStorageType::AppendableMemmap,
+        ] {
+            let path = dir.path().join(format!("{storage_type:?}"));
+            let storage =
+                open_vector_storage(&path, 3, Distance::Euclidean, storage_type, false).unwrap();
+            let mut storage = storage.borrow_mut();
+            assert_eq!(storage.vector_dim(), 3);
+            assert_eq!(
+                storage.is_on_disk(),
+                storage_type != VectorStorageType::Dense
+            );
+
+            let inserted = storage.insert_vector(0, vector.as_slice().into(), Payload::default());
+            // Memmap storage is read-only
+            assert_eq!(inserted.is_ok(), storage_type != VectorStorageType::Memmap);
+        }
+    }
+}
@@ -0,0 +1,235 @@
+use std::cmp::max;
+use std::fs::create_dir_all;
+use std::mem::size_of;
+use std::path::{Path, PathBuf};
+
+use io::file_operations::{atomic_save_json, read_json};
+use memory::mmap_ops::{create_and_ensure_length, open_write_mmap};
+use serde::{Deserialize, Serialize};
+
+use crate::common::mmap_type::{MmapSlice, MmapType};
+use crate::common::operation_error::{OperationError, OperationResult};
+use crate::engine::storage::rocksdb::Flusher;
+use crate::engine::types::types::PointOffsetType;
+
+const CONFIG_FILE_NAME: &str = "config.json";
+const STATUS_FILE_NAME: &str = "status.dat";
+
+const CHUNK_SIZE: usize = 32 * 1024 * 1024;
+
+// if dimension is too high, use this capacity
+const MIN_CHUNK_CAPACITY: usize = 16;
+
+#[repr(C)]
+struct Status {
+    /// Number of stored vectors in all chunks
+    len: usize,
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, PartialEq, Eq)]
+struct ChunkedMmapConfig {
+    dim: usize,
+    /// Maximum number of vectors in each chunk file
+    chunk_capacity: usize,
+}
+
+/// Vectors split over fixed size memory mapped chunk files
+///
+/// Chunk files are created on demand, so the storage can grow without remapping existing data.
+pub struct ChunkedMmapVectors<T: Sized + 'static> {
+    directory: PathBuf,
+    config: ChunkedMmapConfig,
+    status: MmapType<Status>,
+    chunks: Vec<MmapSlice<T>>,
+}
+
+impl<T: Sized + Copy + 'static> ChunkedMmapVectors<T> {
+    pub fn open(directory: &Path, dim: usize) -> OperationResult<Self> {
+        let chunk_capacity = max(CHUNK_SIZE / size_of::<T>() / dim, MIN_CHUNK_CAPACITY);
+        Self::open_with_chunk_capacity(directory, dim, chunk_capacity)
+    }
+
+    fn open_with_chunk_capacity(
+        directory: &Path,
+        dim: usize,
+        chunk_capacity: usize,
+    ) -> OperationResult<Self> {
+        create_dir_all(directory)?;
+
+        let config_path = directory.join(CONFIG_FILE_NAME);
+        let config = if config_path.exists() {
+            let config: ChunkedMmapConfig = read_json(&config_path)?;
+            if config.dim != dim {
+                return Err(OperationError::InconsistentStorage {
+                    description: format!(
+                        "vector dimension of {} is {}, expected {dim}",
+                        directory.display(),
+                        config.dim
+                    ),
+                });
+            }
+            config
+        } else {
+            let config = ChunkedMmapConfig {
+                dim,
+                chunk_capacity,
+            };
+            atomic_save_json(&config_path, &config)?;
+            config
+        };
+
+        let status_path = directory.join(STATUS_FILE_NAME);
+        if !status_path.exists() {
+            create_and_ensure_length(&status_path, size_of::<Status>())?;
+        }
+        let status = unsafe { MmapType::try_from(open_write_mmap(&status_path)?)? };
+
+        let mut chunks = Vec::new();
+        loop {
+            let chunk_path = chunk_path(directory, chunks.len());
+            if !chunk_path.exists() {
+                break;
+            }
+            chunks.push(unsafe { MmapSlice::try_from(open_write_mmap(&chunk_path)?)? });
+        }
+
+        Ok(Self {
+            directory: directory.to_owned(),
+            config,
+            status,
+            chunks,
+        })
+    }
+
+    pub fn dim(&self) -> usize {
+        self.config.dim
+    }
+
+    pub fn len(&self) -> usize {
+        self.status.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Position of the vector: chunk index and offset of the first element in the chunk
+    fn position(&self, key: usize) -> (usize, usize) {
+        (
+            key / self.config.chunk_capacity,
+            (key % self.config.chunk_capacity) * self.config.dim,
+        )
+    }
+
+    pub fn get<TKey>(&self, key: TKey) -> Option<&[T]>
+    where
+        TKey: num_traits::cast::AsPrimitive<usize>,
+    {
+        let key: usize = key.as_();
+        if key >= self.len() {
+            return None;
+        }
+        let (chunk_idx, offset) = self.position(key);
+        Some(&self.chunks[chunk_idx][offset..offset + self.config.dim])
+    }
+
+    fn add_chunk(&mut self) -> OperationResult<()> {
+        let chunk_path = chunk_path(&self.directory, self.chunks.len());
+        let chunk_size = self.config.chunk_capacity * self.config.dim * size_of::<T>();
+        create_and_ensure_length(&chunk_path, chunk_size)?;
+        let chunk = unsafe { MmapSlice::try_from(open_write_mmap(&chunk_path)?)? };
+        self.chunks.push(chunk);
+        Ok(())
+    }
+
+    /// Write the vector at the given key, overwriting a previous vector in place
+    ///
+    /// Vectors between the previous length and the key are filled with zeroes.
+    pub fn insert(&mut self, key: PointOffsetType, vector: &[T]) -> OperationResult<()> {
+        if vector.len() != self.config.dim {
+            return Err(OperationError::WrongVector {
+                expected_dim: self.config.dim,
+                received_dim: vector.len(),
+            });
+        }
+        let key = key as usize;
+        let (chunk_idx, offset) = self.position(key);
+        while self.chunks.len() <= chunk_idx {
+            self.add_chunk()?;
+        }
+        self.chunks[chunk_idx][offset..offset + self.config.dim].copy_from_slice(vector);
+        if key >= self.status.len {
+            self.status.len = key + 1;
+        }
+        Ok(())
+    }
+
+    pub fn push(&mut self, vector: &[T]) -> OperationResult<PointOffsetType> {
+        let new_id = self.len() as PointOffsetType;
+        self.insert(new_id, vector)?;
+        Ok(new_id)
+    }
+
+    pub fn flusher(&self) -> Flusher {
+        let chunk_flushers: Vec<_> = self.chunks.iter().map(|chunk| chunk.flusher()).collect();
+        let status_flusher = self.status.flusher();
+        Box::new(move || {
+            for flusher in chunk_flushers {
+                flusher()?;
+            }
+            // Flush status last, so it never points past flushed data
+            status_flusher()
+        })
+    }
+
+    pub fn files(&self) -> Vec<PathBuf> {
+        let mut files = vec![
+            self.directory.join(CONFIG_FILE_NAME),
+            self.directory.join(STATUS_FILE_NAME),
+        ];
+        files.extend((0..self.chunks.len()).map(|idx| chunk_path(&self.directory, idx)));
+        files
+    }
+}
+
+fn chunk_path(directory: &Path, chunk_idx: usize) -> PathBuf {
+    directory.join(format!("chunk_{chunk_idx}.mmap"))
+}
+
+#[cfg(test)]
+mod tests {
+    use tempfile::Builder;
+
+    use super::*;
+
+    #[test]
+    fn test_insert_and_reopen() {
+        let dir = Builder::new().prefix("chunked_mmap").tempdir().unwrap();
+        {
+            let mut vectors: ChunkedMmapVectors<f32> =
+                ChunkedMmapVectors::open_with_chunk_capacity(dir.path(), 3, 2).unwrap();
+            assert!(vectors.is_empty());
+            assert_eq!(vectors.push(&[1.0, 2.0, 3.0]).unwrap(), 0);
+            assert_eq!(vectors.push(&[4.0, 5.0, 6.0]).unwrap(), 1);
+            // Skips a chunk
+            vectors.insert(6, &[7.0, 8.0, 9.0]).unwrap();
+            // Update in place
+            vectors.insert(0, &[0.0, 0.5, 1.0]).unwrap();
+            assert!(vectors.insert(1, &[1.0]).is_err());
+
+            assert_eq!(vectors.len(), 7);
+            assert_eq!(vectors.files().len(), 2 + 4);
+            vectors.flusher()().unwrap();
+        }
+
+        let vectors: ChunkedMmapVectors<f32> = ChunkedMmapVectors::open(dir.path(), 3).unwrap();
+        assert_eq!(vectors.len(), 7);
+        assert_eq!(vectors.get(0).unwrap(), &[0.0, 0.5, 1.0]);
+        assert_eq!(vectors.get(1).unwrap(), &[4.0, 5.0, 6.0]);
+        assert_eq!(vectors.get(3).unwrap(), &[0.0, 0.0, 0.0]);
+        assert_eq!(vectors.get(6).unwrap(), &[7.0, 8.0, 9.0]);
+        assert!(vectors.get(7).is_none());
+
+        assert!(ChunkedMmapVectors::<f32>::open(dir.path(), 4).is_err());
+    }
+}
@@ -66,12 +66,14 @@ impl<T: Clone + Copy + Send + Sync + Default> ChunkedVectors<T> {
         Ok(new_id)
     }
 
+    /// Allocation failures are returned as error, the vectors are left unchanged then
     pub fn insert(&mut self, key: PointOffsetType, vector: &[T]) -> Result<(), TryReserveError> {
         let key = key as usize;
 
-        self.len = max(self.len, key + 1);
-        self.chunks
-            .resize_with(self.len.div_ceil(self.chunk_capacity), Vec::new);
+        let len = max(self.len, key + 1);
+        let num_chunks = len.div_ceil(self.chunk_capacity);
+        self.chunks.try_set_capacity(num_chunks)?;
+        self.chunks.resize_with(num_chunks, Vec::new);
 
         let chunk_idx = key / self.chunk_capacity;
         let chunk_data = &mut self.chunks[chunk_idx];
@@ -91,6 +93,8 @@ impl<T: Clone + Copy + Send + Sync + Default> ChunkedVectors<T> {
             if chunk_idx != 0 {
                 let desired_capacity = self.chunk_capacity * self.dim;
                 chunk_data.try_set_capacity_exact(desired_capacity)?;
+            } else {
+                chunk_data.try_set_capacity(idx + self.dim)?;
             }
             chunk_data.resize_with(idx + self.dim, Default::default);
         }
@@ -101,6 +105,7 @@ impl<T: Clone + Copy + Send + Sync + Default> ChunkedVectors<T> {
             vector.len()
         );
         data.copy_from_slice(vector);
+        self.len = len;
         Ok(())
     }
 }
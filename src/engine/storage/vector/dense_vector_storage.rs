@@ -16,7 +16,7 @@ use crate::common::operation_error::{check_process_stopped, OperationError, Oper
 use crate::engine::storage::payload_storage::PayloadStorage;
 use crate::engine::storage::rocksdb::rocksdb_wrapper::DatabaseColumnWrapper;
 use crate::engine::storage::rocksdb::storage_manager::StorageManager;
-use crate::engine::storage::rocksdb::Flusher;
+use crate::engine::storage::rocksdb::{Flusher, RocksDbStats};
 use crate::engine::storage::vector::base::{DenseVectorStorage, VectorStorage, VectorStorageEnum};
 use crate::engine::storage::vector::bitvec::bitvec_set_deleted;
 use crate::engine::storage::vector::chunked_vectors::ChunkedVectors;
@@ -136,6 +136,11 @@ impl SimpleDenseVectorStorage {
         &self.vectors
     }
 
+    /// Statistics of the RocksDB column holding the vectors
+    pub fn rocksdb_stats(&self) -> OperationResult<RocksDbStats> {
+        self.db_wrapper.stats()
+    }
+
     fn update_stored(
         &mut self,
         key: PointOffsetType,
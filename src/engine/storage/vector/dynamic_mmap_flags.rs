@@ -0,0 +1,105 @@
+use std::cmp::max;
+use std::path::{Path, PathBuf};
+
+use bitvec::prelude::BitSlice;
+use memory::mmap_ops::{create_and_ensure_length, open_write_mmap};
+
+use crate::common::mmap_type::MmapBitSlice;
+use crate::common::operation_error::OperationResult;
+use crate::engine::storage::rocksdb::Flusher;
+
+/// Minimal size of the flags file in bytes
+const MIN_FLAGS_FILE_SIZE: usize = 1024;
+
+/// Growable bit flags persisted in a memory mapped file
+///
+/// Flags outside of the file are unset. The file grows when such a flag is set.
+pub struct DynamicMmapFlags {
+    path: PathBuf,
+    flags: MmapBitSlice,
+}
+
+impl DynamicMmapFlags {
+    pub fn open(path: &Path) -> OperationResult<Self> {
+        if !path.exists() {
+            create_and_ensure_length(path, MIN_FLAGS_FILE_SIZE)?;
+        }
+        let flags = MmapBitSlice::try_from(open_write_mmap(path)?, 0)?;
+        Ok(Self {
+            path: path.to_owned(),
+            flags,
+        })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Remap the file so it fits at least `len` flags
+    fn grow(&mut self, len: usize) -> OperationResult<()> {
+        self.flusher()()?;
+        let size = max(len.div_ceil(u8::BITS as usize), MIN_FLAGS_FILE_SIZE).next_power_of_two();
+        create_and_ensure_length(&self.path, size)?;
+        self.flags = MmapBitSlice::try_from(open_write_mmap(&self.path)?, 0)?;
+        Ok(())
+    }
+
+    pub fn get(&self, key: usize) -> bool {
+        self.flags.get(key).is_some_and(|flag| *flag)
+    }
+
+    /// Set the flag at the given key, returns the previous value
+    pub fn set(&mut self, key: usize, value: bool) -> OperationResult<bool> {
+        if key >= self.flags.len() {
+            if !value {
+                return Ok(false);
+            }
+            self.grow(key + 1)?;
+        }
+        Ok(self.flags.replace(key, value))
+    }
+
+    pub fn count_flags(&self) -> usize {
+        self.flags.count_ones()
+    }
+
+    /// All flags in the file, the slice may be larger than the number of used flags
+    pub fn get_bitslice(&self) -> &BitSlice {
+        &self.flags
+    }
+
+    pub fn flusher(&self) -> Flusher {
+        self.flags.flusher()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tempfile::Builder;
+
+    use super::*;
+
+    #[test]
+    fn test_grow_and_reopen() {
+        let dir = Builder::new().prefix("flags").tempdir().unwrap();
+        let path = dir.path().join("flags.dat");
+        {
+            let mut flags = DynamicMmapFlags::open(&path).unwrap();
+            assert!(!flags.set(3, true).unwrap());
+            assert!(flags.set(3, true).unwrap());
+            assert!(!flags.set(1_000_000, false).unwrap());
+            assert!(flags.get_bitslice().len() < 1_000_000);
+
+            assert!(!flags.set(100_000, true).unwrap());
+            assert!(flags.get_bitslice().len() > 100_000);
+            flags.flusher()().unwrap();
+        }
+
+        let flags = DynamicMmapFlags::open(&path).unwrap();
+        assert!(flags.get(3));
+        assert!(flags.get(100_000));
+        assert!(!flags.get(4));
+        assert!(!flags.get(10_000_000));
+        assert_eq!(flags.count_flags(), 2);
+    }
+}
@@ -13,7 +13,7 @@ use cgroups_rs::Controller;
 
 use memory::mmap_ops;
 
-use crate::common::operation_error::{check_process_stopped, OperationResult};
+use crate::common::operation_error::{check_process_stopped, OperationError, OperationResult};
 use crate::engine::storage::rocksdb::Flusher;
 use crate::engine::storage::vector::async_common::get_async_scorer;
 use crate::engine::storage::vector::base::{DenseVectorStorage, VectorStorage, VectorStorageEnum};
@@ -117,7 +117,9 @@ impl VectorStorage for MemmapVectorStorage {
         _vector: VectorRef,
         _payload: Payload,
     ) -> OperationResult<()> {
-        panic!("Can't directly update vector in mmap storage")
+        Err(OperationError::service_error(
+            "Can't directly update vector in mmap storage, use appendable mmap storage instead",
+        ))
     }
 
     fn update_from(
@@ -144,12 +146,12 @@ impl VectorStorage for MemmapVectorStorage {
             let vector: DenseVector = other.get_vector(id).try_into()?;
             let raw_bites = mmap_ops::transmute_to_u8_slice(&vector);
             vectors_file.write_all(raw_bites)?;
-            end_index += 1;
 
             // Remember deleted IDs so we can propagate deletions later
             if other.is_deleted_vector(id) {
-                deleted_ids.push((start_index + id) as PointOffsetType);
+                deleted_ids.push(end_index);
             }
+            end_index += 1;
         }
         vectors_file.flush()?;
         drop(vectors_file);
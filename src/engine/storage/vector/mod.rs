@@ -1,10 +1,13 @@
+pub mod appendable_mmap_vector_storage;
 mod async_common;
 #[cfg(target_os = "linux")]
 mod async_io;
 pub mod base;
 mod bitvec;
+mod chunked_mmap_vectors;
 mod chunked_vectors;
 pub mod dense_vector_storage;
+mod dynamic_mmap_flags;
 
 pub mod async_io_mock;
 mod mmap_vector;
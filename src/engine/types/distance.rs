@@ -8,7 +8,9 @@ use hnsw_rs::dist::{
 
 use crate::engine::types::types::{ScoreType, VectorElementType};
 
-#[derive(Debug, Deserialize, Serialize, JsonSchema, Clone, Copy, PartialEq, Eq, Hash)]
+#[derive(
+    Debug, Deserialize, Serialize, JsonSchema, Clone, Copy, PartialEq, Eq, Hash, clap::ValueEnum,
+)]
 pub enum Distance {
     Manhatten,
     Euclidean,
@@ -6,25 +6,69 @@ mod cli;
 mod common;
 mod engine;
 mod setting;
+mod tonic;
 mod utils;
 
-use std::env;
+use std::{env, sync::Arc};
 
 use clap::Parser;
 use cli::Args;
 use setting::Settings;
 use tokio::main;
-use tracing::{event, Level};
+use tracing::{error, event, info, Level};
 use tracing_subscriber::{EnvFilter, FmtSubscriber};
 
-use actix::init;
+use actix::{init, table::toc::TableOfContent};
 #[main]
 async fn main() {
   let args = Args::parse();
-  let settings = Settings::new(args.config_path).unwrap();
+  let settings = match Settings::new(args.config_path) {
+    Ok(settings) => settings,
+    Err(err) => {
+      eprintln!("Failed to load settings: {err}");
+      std::process::exit(1);
+    }
+  };
 
   tracing_subscriber();
-  init(settings).await.unwrap();
+  if let Some(command) = args.command {
+    if let Err(err) = command.run(settings.storage).await {
+      error!("{err}");
+      std::process::exit(1);
+    }
+    return;
+  }
+  // Collections are shared by the REST and gRPC apis. They are loaded while the servers are
+  // already running, so the readiness probe can report the progress.
+  let toc = TableOfContent::open(Arc::new(settings.storage.clone()))
+    .map(Arc::new)
+    .unwrap_or_else(|e| panic!("Error opening collections: {}", e));
+  tokio::spawn(toc.clone().load_collections());
+  let shutdown = actix::shutdown_receiver();
+  let grpc_server = settings.service.grpc_port.map(|grpc_port| {
+    let toc = toc.clone();
+    let settings = settings.clone();
+    let shutdown = shutdown.clone();
+    tokio::spawn(async move {
+      if let Err(err) = tonic::init(toc, settings, grpc_port, shutdown).await {
+        error!("gRPC server failed: {err}");
+        std::process::exit(1);
+      }
+    })
+  });
+  if let Err(err) = init(settings, toc.clone(), shutdown).await {
+    error!("HTTP server failed: {err}");
+    std::process::exit(1);
+  }
+  if let Some(grpc_server) = grpc_server {
+    let _ = grpc_server.await;
+  }
+  // Both servers are drained, flush and close the storages before exiting
+  if let Err(err) = toc.shutdown().await {
+    error!("Failed to close collections: {err}");
+    std::process::exit(1);
+  }
+  info!("Shutdown complete");
 }
 
 fn tracing_subscriber() {
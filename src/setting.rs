@@ -1,17 +1,22 @@
-use crate::engine::storage::types::StorageConfig;
-use config::{Config, File, FileFormat, Source};
+use crate::{common::validation::describe_errors, engine::storage::types::StorageConfig};
+use config::{Config, ConfigError, Environment, File, FileFormat, Source};
 use fs_extra::error;
 use serde::Deserialize;
 use tracing::error;
-use validator::Validate;
+use validator::{Validate, ValidationError};
 
 #[derive(Debug, Deserialize, Validate, Clone)]
 pub struct ServiceConfig {
     #[validate(length(min = 1))]
     pub host: String,
+    #[validate(range(min = 1))]
     pub http_port: u16,
     pub grpc_port: Option<u16>, // None means that gRPC is disabled
+    #[validate(range(min = 1))]
     pub max_request_size_mb: usize,
+    /// Limit of multipart uploads, like dataset files, which are spooled to disk
+    #[validate(range(min = 1))]
+    pub max_upload_size_mb: usize,
     pub max_workers: Option<usize>,
     #[serde(default = "default_cors")]
     pub enable_cors: bool,
@@ -21,6 +26,9 @@ pub struct ServiceConfig {
     pub verify_https_client_certificate: bool,
     pub api_key: Option<String>,
     pub read_only_api_key: Option<String>,
+    /// Paths served without an api key, each also exempts the paths below it
+    #[serde(default = "default_api_key_exempt_paths")]
+    pub api_key_exempt_paths: Vec<String>,
 
     /// Directory where static files are served from.
     /// For example, the Web-UI should be placed here.
@@ -33,7 +41,24 @@ pub struct ServiceConfig {
     pub enable_static_content: Option<bool>,
 }
 
+/// Certificates used when `service.enable_tls` is set. Files are PEM encoded.
+#[derive(Debug, Deserialize, Validate, Clone)]
+pub struct TlsConfig {
+    /// Server certificate chain
+    #[validate(length(min = 1))]
+    pub cert: String,
+    /// Private key of the server certificate
+    #[validate(length(min = 1))]
+    pub key: String,
+    /// Certificate authority which client certificates are verified against
+    pub ca_cert: Option<String>,
+    /// How often the certificate and key files are checked for changes, 0 disables reloading
+    #[serde(default = "default_cert_reload_interval_sec")]
+    pub cert_reload_interval_sec: u64,
+}
+
 #[derive(Debug, Deserialize, Clone, Validate)]
+#[validate(schema(function = "validate_tls"))]
 pub struct Settings {
     #[serde(default = "default_log_level")]
     pub log_level: String,
@@ -41,6 +66,33 @@ pub struct Settings {
     pub storage: StorageConfig,
     #[validate]
     pub service: ServiceConfig,
+    #[serde(default)]
+    #[validate]
+    pub tls: Option<TlsConfig>,
+}
+
+fn validate_tls(settings: &Settings) -> Result<(), ValidationError> {
+    let service = &settings.service;
+    if service.enable_tls && settings.tls.is_none() {
+        return Err(ValidationError::new("enable_tls requires a tls section"));
+    }
+    if service.verify_https_client_certificate {
+        if !service.enable_tls {
+            return Err(ValidationError::new(
+                "verify_https_client_certificate requires enable_tls",
+            ));
+        }
+        if settings
+            .tls
+            .as_ref()
+            .is_some_and(|tls| tls.ca_cert.is_none())
+        {
+            return Err(ValidationError::new(
+                "verify_https_client_certificate requires tls.ca_cert",
+            ));
+        }
+    }
+    Ok(())
 }
 
 fn default_log_level() -> String {
@@ -50,20 +102,145 @@ fn default_log_level() -> String {
 fn default_cors() -> bool {
     true
 }
+
+fn default_cert_reload_interval_sec() -> u64 {
+    10
+}
+
+fn default_api_key_exempt_paths() -> Vec<String> {
+    ["/healthz", "/readyz", "/livez", "/swagger-ui", "/api-docs"]
+        .map(String::from)
+        .to_vec()
+}
 const DEFAULT_CONFIG: &str = include_str!("../config/config.yaml");
 
+/// Prefix of environment variables overriding settings, e.g. `QUANTIXAR__SERVICE__HTTP_PORT`
+const ENV_PREFIX: &str = "QUANTIXAR";
+const ENV_SEPARATOR: &str = "__";
+
 impl Settings {
-    pub fn new(path: Option<String>) -> Result<Self, config::ConfigError> {
+    /// Settings are layered, each source overrides the previous ones:
+    /// 1. compile-time defaults from `config/config.yaml`
+    /// 2. the config file given by `path`, if any
+    /// 3. `QUANTIXAR__SECTION__KEY` environment variables
+    pub fn new(path: Option<String>) -> Result<Self, ConfigError> {
+        Self::load(path, Self::environment())
+    }
+
+    fn environment() -> Environment {
+        Environment::with_prefix(ENV_PREFIX)
+            .separator(ENV_SEPARATOR)
+            .try_parsing(true)
+    }
+
+    fn load(path: Option<String>, environment: Environment) -> Result<Self, ConfigError> {
         let config_exists = |path| File::with_name(path).collect().is_ok();
+        let mut config = Config::builder()
+            // Start with compile-time base config
+            .add_source(File::from_str(DEFAULT_CONFIG, FileFormat::Yaml));
         if let Some(path) = path {
             if !config_exists(&path) {
-                error!("Config file not found: {}", path);
+                return Err(ConfigError::Message(format!(
+                    "Config file not found: {path}"
+                )));
             }
+            config = config.add_source(File::with_name(&path));
         }
-        let config = Config::builder()
-            // Start with compile-time base config
-            .add_source(File::from_str(DEFAULT_CONFIG, FileFormat::Yaml));
-        let setting: Settings = config.build()?.try_deserialize()?;
-        Ok(setting)
+        config = config.add_source(environment);
+
+        let settings: Settings = config.build()?.try_deserialize()?;
+        if let Err(errors) = settings.validate() {
+            return Err(ConfigError::Message(format!(
+                "Invalid settings: {}",
+                describe_errors(&errors).join("; ")
+            )));
+        }
+        Ok(settings)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use config::Map;
+    use tempfile::Builder;
+
+    use super::*;
+
+    #[test]
+    fn test_layered_settings() {
+        let dir = Builder::new().prefix("settings").tempdir().unwrap();
+        let config_path = dir.path().join("config.yaml");
+        fs::write(
+            &config_path,
+            "service:\n  http_port: 7000\n  max_request_size_mb: 8\nstorage:\n  optimizers:\n    flush_interval_sec: 1\n",
+        )
+        .unwrap();
+        let config_path = config_path.to_string_lossy().into_owned();
+
+        // Variables are given to the source, the process environment is shared by parallel tests
+        let environment = |max_request_size_mb: &str| {
+            let variables = Map::from([(
+                "QUANTIXAR__SERVICE__MAX_REQUEST_SIZE_MB".to_string(),
+                max_request_size_mb.to_string(),
+            )]);
+            Settings::environment().source(Some(variables))
+        };
+        let settings = Settings::load(Some(config_path.clone()), environment("16"));
+        let invalid = Settings::load(Some(config_path), environment("0"));
+
+        let settings = settings.unwrap();
+        assert_eq!(settings.service.http_port, 7000);
+        assert_eq!(settings.service.max_request_size_mb, 16);
+        assert_eq!(settings.storage.optimizers.flush_interval_sec, 1);
+        // Untouched values keep their defaults
+        assert_eq!(settings.service.host, "0.0.0.0");
+        assert_eq!(settings.service.max_upload_size_mb, 1024);
+
+        let message = invalid.unwrap_err().to_string();
+        assert!(
+            message.contains("service.max_request_size_mb"),
+            "unexpected error: {message}"
+        );
+
+        let missing = Settings::new(Some(
+            dir.path().join("missing.yaml").to_string_lossy().into(),
+        ));
+        assert!(missing.unwrap_err().to_string().contains("not found"));
+    }
+
+    #[test]
+    fn test_tls_settings() {
+        // Environment overrides are left out
+        let settings = |overrides: &str| -> Settings {
+            Config::builder()
+                .add_source(File::from_str(DEFAULT_CONFIG, FileFormat::Yaml))
+                .add_source(File::from_str(overrides, FileFormat::Yaml))
+                .build()
+                .unwrap()
+                .try_deserialize()
+                .unwrap()
+        };
+        let tls = "tls:\n  cert: cert.pem\n  key: key.pem\n";
+
+        assert!(settings("service:\n  enable_tls: false\n")
+            .validate()
+            .is_ok());
+        let errors = settings("service:\n  enable_tls: true\n")
+            .validate()
+            .unwrap_err();
+        assert_eq!(
+            describe_errors(&errors),
+            ["__all__: enable_tls requires a tls section"]
+        );
+        let settings_with_tls = settings(&format!("service:\n  enable_tls: true\n{tls}"));
+        assert!(settings_with_tls.validate().is_ok());
+        assert_eq!(settings_with_tls.tls.unwrap().cert_reload_interval_sec, 10);
+
+        let mtls = "service:\n  enable_tls: true\n  verify_https_client_certificate: true\n";
+        assert!(settings(&format!("{mtls}{tls}")).validate().is_err());
+        let with_ca = format!("{mtls}{tls}  ca_cert: ca.pem\n");
+        assert!(settings(&with_ca).validate().is_ok());
     }
 }
@@ -0,0 +1,95 @@
+use std::{sync::Arc, time::Instant};
+
+use tonic::{Request, Response, Status};
+
+use crate::{
+  actix::table::toc::TableOfContent,
+  tonic::{
+    proto::{
+      collections_server::Collections, CollectionOperationResponse, CreateCollection,
+      DeleteCollection, ListCollectionsRequest, ListCollectionsResponse, VacuumCollection,
+      VacuumResponse,
+    },
+    ApiKeyCheck,
+  },
+};
+
+pub struct CollectionsService {
+  toc: Arc<TableOfContent>,
+  api_keys: ApiKeyCheck,
+}
+
+impl CollectionsService {
+  pub fn new(toc: Arc<TableOfContent>, api_keys: ApiKeyCheck) -> Self {
+    CollectionsService { toc, api_keys }
+  }
+}
+
+#[tonic::async_trait]
+impl Collections for CollectionsService {
+  async fn list(
+    &self,
+    request: Request<ListCollectionsRequest>,
+  ) -> Result<Response<ListCollectionsResponse>, Status> {
+    self.api_keys.check(request.metadata(), true)?;
+    let timing = Instant::now();
+    let collections = self.toc.list_collections().await;
+    Ok(Response::new(ListCollectionsResponse {
+      collections,
+      time: timing.elapsed().as_secs_f64(),
+    }))
+  }
+
+  async fn create(
+    &self,
+    request: Request<CreateCollection>,
+  ) -> Result<Response<CollectionOperationResponse>, Status> {
+    self.api_keys.check(request.metadata(), false)?;
+    let timing = Instant::now();
+    let request = request.into_inner();
+    let config = request
+      .config
+      .ok_or_else(|| Status::invalid_argument("Collection config is required"))?;
+    self
+      .toc
+      .create_collection(&request.collection_name, config.try_into()?)
+      .await?;
+    Ok(Response::new(CollectionOperationResponse {
+      result: true,
+      time: timing.elapsed().as_secs_f64(),
+    }))
+  }
+
+  async fn delete(
+    &self,
+    request: Request<DeleteCollection>,
+  ) -> Result<Response<CollectionOperationResponse>, Status> {
+    self.api_keys.check(request.metadata(), false)?;
+    let timing = Instant::now();
+    let deleted = self
+      .toc
+      .delete_collection(&request.into_inner().collection_name)
+      .await?;
+    Ok(Response::new(CollectionOperationResponse {
+      result: deleted,
+      time: timing.elapsed().as_secs_f64(),
+    }))
+  }
+
+  async fn vacuum(
+    &self,
+    request: Request<VacuumCollection>,
+  ) -> Result<Response<VacuumResponse>, Status> {
+    self.api_keys.check(request.metadata(), false)?;
+    let timing = Instant::now();
+    let report = self
+      .toc
+      .vacuum_collection(&request.into_inner().collection_name)
+      .await?;
+    Ok(Response::new(VacuumResponse {
+      segments: report.segments as u64,
+      reclaimed_vectors: report.reclaimed_vectors as u64,
+      time: timing.elapsed().as_secs_f64(),
+    }))
+  }
+}
@@ -0,0 +1,3 @@
+pub mod collections_api;
+pub mod points_api;
+pub mod snapshots_api;
@@ -0,0 +1,151 @@
+use std::{sync::Arc, time::Instant};
+
+use tokio::sync::mpsc;
+use tokio_stream::wrappers::ReceiverStream;
+use tonic::{Request, Response, Status, Streaming};
+
+use crate::{
+  actix::{model::points::PointStruct, table::toc::TableOfContent},
+  tonic::{
+    conversions::point_ids_from_proto,
+    proto::{
+      points_server::Points, DeletePoints, DeletePointsResponse, RecommendPoints, SearchPoints,
+      SearchResponse, UpsertPoints, UpsertPointsResponse,
+    },
+    ApiKeyCheck,
+  },
+};
+
+/// Responses of a batch upsert stream waiting for the client
+const UPSERT_STREAM_BUFFER: usize = 16;
+
+pub struct PointsService {
+  toc: Arc<TableOfContent>,
+  api_keys: ApiKeyCheck,
+}
+
+impl PointsService {
+  pub fn new(toc: Arc<TableOfContent>, api_keys: ApiKeyCheck) -> Self {
+    PointsService { toc, api_keys }
+  }
+}
+
+async fn upsert(
+  toc: &TableOfContent,
+  request: UpsertPoints,
+) -> Result<UpsertPointsResponse, Status> {
+  let timing = Instant::now();
+  let points = request
+    .points
+    .into_iter()
+    .map(PointStruct::try_from)
+    .collect::<Result<Vec<_>, _>>()?;
+  let ids = toc.upsert_points(&request.collection_name, points).await?;
+  Ok(UpsertPointsResponse {
+    ids: ids.into_iter().map(Into::into).collect(),
+    time: timing.elapsed().as_secs_f64(),
+  })
+}
+
+#[tonic::async_trait]
+impl Points for PointsService {
+  async fn upsert(
+    &self,
+    request: Request<UpsertPoints>,
+  ) -> Result<Response<UpsertPointsResponse>, Status> {
+    self.api_keys.check(request.metadata(), false)?;
+    Ok(Response::new(
+      upsert(&self.toc, request.into_inner()).await?,
+    ))
+  }
+
+  type UpsertStreamStream = ReceiverStream<Result<UpsertPointsResponse, Status>>;
+
+  async fn upsert_stream(
+    &self,
+    request: Request<Streaming<UpsertPoints>>,
+  ) -> Result<Response<Self::UpsertStreamStream>, Status> {
+    self.api_keys.check(request.metadata(), false)?;
+    let mut batches = request.into_inner();
+    let toc = self.toc.clone();
+    let (sender, receiver) = mpsc::channel(UPSERT_STREAM_BUFFER);
+    tokio::spawn(async move {
+      loop {
+        let response = match batches.message().await {
+          Ok(Some(batch)) => upsert(&toc, batch).await,
+          Ok(None) => break,
+          Err(status) => Err(status),
+        };
+        let failed = response.is_err();
+        // Stop on the first error, or once the client is gone
+        if sender.send(response).await.is_err() || failed {
+          break;
+        }
+      }
+    });
+    Ok(Response::new(ReceiverStream::new(receiver)))
+  }
+
+  async fn delete(
+    &self,
+    request: Request<DeletePoints>,
+  ) -> Result<Response<DeletePointsResponse>, Status> {
+    self.api_keys.check(request.metadata(), false)?;
+    let timing = Instant::now();
+    let request = request.into_inner();
+    let ids = point_ids_from_proto(request.points)?;
+    let deleted = self
+      .toc
+      .delete_points(&request.collection_name, &ids)
+      .await?;
+    Ok(Response::new(DeletePointsResponse {
+      deleted: deleted as u64,
+      time: timing.elapsed().as_secs_f64(),
+    }))
+  }
+
+  async fn search(
+    &self,
+    request: Request<SearchPoints>,
+  ) -> Result<Response<SearchResponse>, Status> {
+    self.api_keys.check(request.metadata(), true)?;
+    let timing = Instant::now();
+    let request = request.into_inner();
+    let result = self
+      .toc
+      .search(
+        &request.collection_name,
+        &request.vector,
+        request.k as usize,
+      )
+      .await?;
+    Ok(Response::new(SearchResponse {
+      result: result.into_iter().map(Into::into).collect(),
+      time: timing.elapsed().as_secs_f64(),
+    }))
+  }
+
+  async fn recommend(
+    &self,
+    request: Request<RecommendPoints>,
+  ) -> Result<Response<SearchResponse>, Status> {
+    self.api_keys.check(request.metadata(), true)?;
+    let timing = Instant::now();
+    let request = request.into_inner();
+    let positive = point_ids_from_proto(request.positive)?;
+    let negative = point_ids_from_proto(request.negative)?;
+    let result = self
+      .toc
+      .recommend(
+        &request.collection_name,
+        &positive,
+        &negative,
+        request.k as usize,
+      )
+      .await?;
+    Ok(Response::new(SearchResponse {
+      result: result.into_iter().map(Into::into).collect(),
+      time: timing.elapsed().as_secs_f64(),
+    }))
+  }
+}
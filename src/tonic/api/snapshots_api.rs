@@ -0,0 +1,76 @@
+use std::{sync::Arc, time::Instant};
+
+use tonic::{Request, Response, Status};
+
+use crate::{
+  actix::table::toc::TableOfContent,
+  tonic::{
+    proto::{
+      snapshots_server::Snapshots, CreateSnapshotRequest, CreateSnapshotResponse,
+      DeleteSnapshotRequest, DeleteSnapshotResponse, ListSnapshotsRequest, ListSnapshotsResponse,
+    },
+    ApiKeyCheck,
+  },
+};
+
+pub struct SnapshotsService {
+  toc: Arc<TableOfContent>,
+  api_keys: ApiKeyCheck,
+}
+
+impl SnapshotsService {
+  pub fn new(toc: Arc<TableOfContent>, api_keys: ApiKeyCheck) -> Self {
+    SnapshotsService { toc, api_keys }
+  }
+}
+
+#[tonic::async_trait]
+impl Snapshots for SnapshotsService {
+  async fn create(
+    &self,
+    request: Request<CreateSnapshotRequest>,
+  ) -> Result<Response<CreateSnapshotResponse>, Status> {
+    self.api_keys.check(request.metadata(), false)?;
+    let timing = Instant::now();
+    let snapshot = self
+      .toc
+      .create_snapshot(&request.into_inner().collection_name)
+      .await?;
+    Ok(Response::new(CreateSnapshotResponse {
+      snapshot_description: Some(snapshot.into()),
+      time: timing.elapsed().as_secs_f64(),
+    }))
+  }
+
+  async fn list(
+    &self,
+    request: Request<ListSnapshotsRequest>,
+  ) -> Result<Response<ListSnapshotsResponse>, Status> {
+    self.api_keys.check(request.metadata(), true)?;
+    let timing = Instant::now();
+    let snapshots = self
+      .toc
+      .list_snapshots(&request.into_inner().collection_name)
+      .await?;
+    Ok(Response::new(ListSnapshotsResponse {
+      snapshot_descriptions: snapshots.into_iter().map(Into::into).collect(),
+      time: timing.elapsed().as_secs_f64(),
+    }))
+  }
+
+  async fn delete(
+    &self,
+    request: Request<DeleteSnapshotRequest>,
+  ) -> Result<Response<DeleteSnapshotResponse>, Status> {
+    self.api_keys.check(request.metadata(), false)?;
+    let timing = Instant::now();
+    let request = request.into_inner();
+    self
+      .toc
+      .delete_snapshot(&request.collection_name, &request.snapshot_name)
+      .await?;
+    Ok(Response::new(DeleteSnapshotResponse {
+      time: timing.elapsed().as_secs_f64(),
+    }))
+  }
+}
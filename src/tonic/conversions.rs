@@ -0,0 +1,264 @@
+use std::{collections::HashMap, str::FromStr};
+
+use chrono::NaiveDateTime;
+use prost_types::{value::Kind, ListValue, Struct, Timestamp};
+use serde_json::{Map, Number, Value};
+use tonic::Status;
+use uuid::Uuid;
+
+use super::proto::{self, point_id::PointIdOptions};
+use crate::{
+  actix::{
+    model::points::PointStruct,
+    table::{
+      collections::{CollectionConfig, HnswConfig},
+      snapshots::SnapshotDescription,
+    },
+  },
+  common::{operation_error::OperationError, point_id::PointIdType},
+  engine::{
+    segments::segment::ScoredPoint,
+    storage::types::VectorStorageType,
+    types::{distance::Distance, types::Payload},
+  },
+};
+
+impl From<OperationError> for Status {
+  fn from(err: OperationError) -> Self {
+    let description = err.to_string();
+    match err {
+      OperationError::WrongVector { .. }
+      | OperationError::MissedVectorName { .. }
+      | OperationError::TypeError { .. }
+      | OperationError::TypeInferenceError { .. }
+      | OperationError::ValidationError { .. }
+      | OperationError::WrongSparse => Status::invalid_argument(description),
+      OperationError::VectorNameNotExists { .. }
+      | OperationError::PointIdError { .. }
+      | OperationError::NotFound { .. } => Status::not_found(description),
+      OperationError::OutOfMemory { .. } => Status::resource_exhausted(description),
+      OperationError::Cancelled { .. } => Status::cancelled(description),
+      OperationError::Unavailable { .. } => Status::unavailable(description),
+      OperationError::ServiceError { .. } | OperationError::InconsistentStorage { .. } => {
+        Status::internal(description)
+      }
+    }
+  }
+}
+
+impl From<PointIdType> for proto::PointId {
+  fn from(id: PointIdType) -> Self {
+    let options = match id {
+      PointIdType::NumId(num) => PointIdOptions::Num(num),
+      PointIdType::Uuid(uuid) => PointIdOptions::Uuid(uuid.to_string()),
+    };
+    proto::PointId {
+      point_id_options: Some(options),
+    }
+  }
+}
+
+impl TryFrom<proto::PointId> for PointIdType {
+  type Error = Status;
+
+  fn try_from(id: proto::PointId) -> Result<Self, Self::Error> {
+    match id.point_id_options {
+      Some(PointIdOptions::Num(num)) => Ok(PointIdType::NumId(num)),
+      Some(PointIdOptions::Uuid(uuid)) => Uuid::from_str(&uuid)
+        .map(PointIdType::Uuid)
+        .map_err(|err| Status::invalid_argument(format!("Invalid point id {uuid}: {err}"))),
+      None => Err(Status::invalid_argument("Point id is empty")),
+    }
+  }
+}
+
+pub fn point_ids_from_proto(ids: Vec<proto::PointId>) -> Result<Vec<PointIdType>, Status> {
+  ids.into_iter().map(PointIdType::try_from).collect()
+}
+
+impl TryFrom<proto::PointStruct> for PointStruct {
+  type Error = Status;
+
+  fn try_from(point: proto::PointStruct) -> Result<Self, Self::Error> {
+    Ok(PointStruct {
+      id: point.id.map(PointIdType::try_from).transpose()?,
+      vector: point.vector,
+      payload: (!point.payload.is_empty()).then(|| payload_from_proto(point.payload)),
+    })
+  }
+}
+
+impl From<ScoredPoint> for proto::ScoredPoint {
+  fn from(point: ScoredPoint) -> Self {
+    proto::ScoredPoint {
+      id: Some(point.id.into()),
+      distance: point.distance,
+      payload: payload_to_proto(point.payload),
+    }
+  }
+}
+
+impl TryFrom<proto::CollectionConfig> for CollectionConfig {
+  type Error = Status;
+
+  fn try_from(config: proto::CollectionConfig) -> Result<Self, Self::Error> {
+    let distance = match proto::Distance::try_from(config.distance) {
+      Ok(proto::Distance::Manhatten) => Distance::Manhatten,
+      Ok(proto::Distance::Euclidean) => Distance::Euclidean,
+      Ok(proto::Distance::DotProduct) => Distance::DotProduct,
+      Ok(proto::Distance::Cosine) => Distance::Cosine,
+      Ok(proto::Distance::Hamming) => Distance::Hamming,
+      Ok(proto::Distance::Jaccard) => Distance::Jaccard,
+      Ok(proto::Distance::Hellinger) => Distance::Hellinger,
+      Ok(proto::Distance::Jeffreys) => Distance::Jeffreys,
+      Ok(proto::Distance::JensenShannon) => Distance::JensenShannon,
+      Ok(proto::Distance::UnknownDistance) | Err(_) => {
+        return Err(Status::invalid_argument("Unknown distance"))
+      }
+    };
+    let vector_storage_type = config
+      .vector_storage_type
+      .map(
+        |storage_type| match proto::VectorStorageType::try_from(storage_type) {
+          Ok(proto::VectorStorageType::Dense) => Ok(VectorStorageType::Dense),
+          Ok(proto::VectorStorageType::Memmap) => Ok(VectorStorageType::Memmap),
+          Ok(proto::VectorStorageType::AppendableMemmap) => Ok(VectorStorageType::AppendableMemmap),
+          Err(_) => Err(Status::invalid_argument("Unknown vector storage type")),
+        },
+      )
+      .transpose()?;
+    Ok(CollectionConfig {
+      vector_size: config.vector_size as usize,
+      distance,
+      hnsw_config: config.hnsw_config.map(|hnsw| HnswConfig {
+        m: hnsw.m as usize,
+        ef_construct: hnsw.ef_construct as usize,
+        full_scan_threshold: hnsw.full_scan_threshold as usize,
+        max_indexing_threads: hnsw.max_indexing_threads as usize,
+        on_disk: hnsw.on_disk,
+        payload_m: hnsw.payload_m.map(|m| m as usize),
+      }),
+      quantization_config: None,
+      vector_storage_type,
+      optimizers_config: None,
+      normalize: config.normalize,
+    })
+  }
+}
+
+impl From<SnapshotDescription> for proto::SnapshotDescription {
+  fn from(snapshot: SnapshotDescription) -> Self {
+    proto::SnapshotDescription {
+      name: snapshot.name,
+      creation_time: snapshot.creation_time.map(timestamp),
+      size: snapshot.size,
+    }
+  }
+}
+
+fn timestamp(time: NaiveDateTime) -> Timestamp {
+  let time = time.and_utc();
+  Timestamp {
+    seconds: time.timestamp(),
+    nanos: time.timestamp_subsec_nanos() as i32,
+  }
+}
+
+pub fn payload_to_proto(payload: Payload) -> HashMap<String, prost_types::Value> {
+  payload
+    .0
+    .into_iter()
+    .map(|(key, value)| (key, json_to_proto(value)))
+    .collect()
+}
+
+pub fn payload_from_proto(payload: HashMap<String, prost_types::Value>) -> Payload {
+  Payload(
+    payload
+      .into_iter()
+      .map(|(key, value)| (key, json_from_proto(value)))
+      .collect(),
+  )
+}
+
+#[cfg(test)]
+pub fn payload_to_proto_value(value: impl Into<Value>) -> prost_types::Value {
+  json_to_proto(value.into())
+}
+
+fn json_to_proto(value: Value) -> prost_types::Value {
+  let kind = match value {
+    Value::Null => Kind::NullValue(0),
+    Value::Bool(value) => Kind::BoolValue(value),
+    Value::Number(number) => Kind::NumberValue(number.as_f64().unwrap_or_default()),
+    Value::String(value) => Kind::StringValue(value),
+    Value::Array(values) => Kind::ListValue(ListValue {
+      values: values.into_iter().map(json_to_proto).collect(),
+    }),
+    Value::Object(map) => Kind::StructValue(Struct {
+      fields: map
+        .into_iter()
+        .map(|(key, value)| (key, json_to_proto(value)))
+        .collect(),
+    }),
+  };
+  prost_types::Value { kind: Some(kind) }
+}
+
+fn json_from_proto(value: prost_types::Value) -> Value {
+  match value.kind {
+    None | Some(Kind::NullValue(_)) => Value::Null,
+    Some(Kind::BoolValue(value)) => Value::Bool(value),
+    // Protobuf only has doubles, integral values are restored as integers
+    Some(Kind::NumberValue(number)) if number.fract() == 0.0 && number.abs() < i64::MAX as f64 => {
+      Value::Number((number as i64).into())
+    }
+    Some(Kind::NumberValue(number)) => Number::from_f64(number).map_or(Value::Null, Value::Number),
+    Some(Kind::StringValue(value)) => Value::String(value),
+    Some(Kind::ListValue(list)) => {
+      Value::Array(list.values.into_iter().map(json_from_proto).collect())
+    }
+    Some(Kind::StructValue(fields)) => Value::Object(
+      fields
+        .fields
+        .into_iter()
+        .map(|(key, value)| (key, json_from_proto(value)))
+        .collect::<Map<_, _>>(),
+    ),
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use serde_json::json;
+
+  use super::*;
+
+  #[test]
+  fn test_payload_conversion() {
+    let payload = json!({
+      "name": "point",
+      "count": 3,
+      "score": 0.5,
+      "tags": ["a", null, true],
+      "nested": {"depth": -2},
+    });
+    let Value::Object(map) = payload.clone() else {
+      unreachable!()
+    };
+    let converted = payload_from_proto(payload_to_proto(Payload(map)));
+    assert_eq!(Value::Object(converted.0), payload);
+  }
+
+  #[test]
+  fn test_point_id_conversion() {
+    let uuid = Uuid::new_v4();
+    for id in [PointIdType::NumId(42), PointIdType::Uuid(uuid)] {
+      assert_eq!(PointIdType::try_from(proto::PointId::from(id)).unwrap(), id);
+    }
+    let invalid = proto::PointId {
+      point_id_options: Some(PointIdOptions::Uuid("not a uuid".to_string())),
+    };
+    assert!(PointIdType::try_from(invalid).is_err());
+  }
+}
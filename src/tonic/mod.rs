@@ -0,0 +1,365 @@
+// `tonic::Status` is the error type of every generated service method
+#![allow(clippy::result_large_err)]
+
+pub mod api;
+mod conversions;
+
+use std::{io, sync::Arc, time::Duration};
+
+use actix_web::http::StatusCode;
+use tokio::{
+  net::{TcpListener, TcpStream},
+  sync::{mpsc, watch},
+};
+use tokio_rustls::{server::TlsStream, TlsAcceptor};
+use tokio_stream::wrappers::{ReceiverStream, TcpListenerStream};
+use tonic::{
+  metadata::MetadataMap,
+  transport::{server::Router, Server},
+  Status,
+};
+
+use crate::{
+  actix::{
+    auth::{ApiKeys, API_KEY_HEADER},
+    certificate_helpers::tls_server_config,
+    table::toc::TableOfContent,
+    wait_for_shutdown,
+  },
+  setting::Settings,
+};
+use api::{
+  collections_api::CollectionsService, points_api::PointsService, snapshots_api::SnapshotsService,
+};
+use proto::{
+  collections_server::CollectionsServer, points_server::PointsServer,
+  snapshots_server::SnapshotsServer,
+};
+
+pub mod proto {
+  tonic::include_proto!("quantixar");
+}
+
+/// Connections waiting for the server after their TLS handshake
+const TLS_ACCEPT_BACKLOG: usize = 128;
+
+/// Serve the gRPC api on `grpc_port` until `shutdown` reports the shutdown and in-flight calls
+/// are drained
+pub async fn init(
+  toc: Arc<TableOfContent>,
+  settings: Settings,
+  grpc_port: u16,
+  shutdown: watch::Receiver<bool>,
+) -> io::Result<()> {
+  let listener = TcpListener::bind((settings.service.host.as_str(), grpc_port)).await?;
+  let router = router(
+    toc,
+    ApiKeyCheck::new(ApiKeys::from_config(&settings.service)),
+    settings.service.max_request_size_mb * 1024 * 1024,
+  );
+
+  let served = if settings.service.enable_tls {
+    let tls_config = settings.tls.as_ref().expect("TLS config is validated");
+    let mut server_config =
+      tls_server_config(tls_config, settings.service.verify_https_client_certificate)?;
+    server_config.alpn_protocols = vec![b"h2".to_vec()];
+    log::info!("Starting gRPC server with TLS on port {grpc_port}");
+    router
+      .serve_with_incoming_shutdown(
+        tls_incoming(listener, Arc::new(server_config)),
+        wait_for_shutdown(shutdown),
+      )
+      .await
+  } else {
+    log::info!("Starting gRPC server on port {grpc_port}");
+    router
+      .serve_with_incoming_shutdown(
+        TcpListenerStream::new(listener),
+        wait_for_shutdown(shutdown),
+      )
+      .await
+  };
+  served.map_err(io::Error::other)
+}
+
+fn router(toc: Arc<TableOfContent>, api_keys: ApiKeyCheck, max_message_size: usize) -> Router {
+  Server::builder()
+    .add_service(
+      CollectionsServer::new(CollectionsService::new(toc.clone(), api_keys.clone()))
+        .max_decoding_message_size(max_message_size),
+    )
+    .add_service(
+      PointsServer::new(PointsService::new(toc.clone(), api_keys.clone()))
+        .max_decoding_message_size(max_message_size),
+    )
+    .add_service(
+      SnapshotsServer::new(SnapshotsService::new(toc, api_keys))
+        .max_decoding_message_size(max_message_size),
+    )
+}
+
+/// Connections of `listener` after their TLS handshake
+///
+/// Handshakes run concurrently, so that a slow client does not hold the other ones back.
+fn tls_incoming(
+  listener: TcpListener,
+  server_config: Arc<rustls::ServerConfig>,
+) -> ReceiverStream<io::Result<TlsStream<TcpStream>>> {
+  let acceptor = TlsAcceptor::from(server_config);
+  let (sender, receiver) = mpsc::channel(TLS_ACCEPT_BACKLOG);
+  tokio::spawn(async move {
+    while !sender.is_closed() {
+      let stream = match listener.accept().await {
+        Ok((stream, _)) => stream,
+        Err(err) => {
+          log::warn!("Failed to accept gRPC connection: {err}");
+          tokio::time::sleep(Duration::from_millis(100)).await;
+          continue;
+        }
+      };
+      let acceptor = acceptor.clone();
+      let sender = sender.clone();
+      tokio::spawn(async move {
+        match acceptor.accept(stream).await {
+          Ok(tls_stream) => {
+            let _ = sender.send(Ok(tls_stream)).await;
+          }
+          Err(err) => log::debug!("TLS handshake of gRPC connection failed: {err}"),
+        }
+      });
+    }
+  });
+  ReceiverStream::new(receiver)
+}
+
+/// Checks the `api-key` metadata of calls, like the REST middleware checks headers
+#[derive(Clone)]
+pub struct ApiKeyCheck {
+  api_keys: Option<Arc<ApiKeys>>,
+}
+
+impl ApiKeyCheck {
+  pub fn new(api_keys: Option<ApiKeys>) -> Self {
+    ApiKeyCheck {
+      api_keys: api_keys.map(Arc::new),
+    }
+  }
+
+  pub fn check(&self, metadata: &MetadataMap, read_only: bool) -> Result<(), Status> {
+    let Some(api_keys) = &self.api_keys else {
+      return Ok(());
+    };
+    let key = match metadata.get(API_KEY_HEADER) {
+      Some(key) => key.to_str().ok(),
+      None => metadata
+        .get("authorization")
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "))
+        .map(str::trim),
+    };
+    api_keys
+      .check_key(key, read_only)
+      .map_err(|(status, description)| match status {
+        StatusCode::FORBIDDEN => Status::permission_denied(description),
+        _ => Status::unauthenticated(description),
+      })
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use serde_json::json;
+  use tempfile::Builder;
+  use tonic::{transport::Channel, Code, Request};
+
+  use super::{proto::*, *};
+  use crate::{common::point_id::PointIdType, setting::ServiceConfig};
+
+  fn with_key<T>(message: T, key: &str) -> Request<T> {
+    let mut request = Request::new(message);
+    request
+      .metadata_mut()
+      .insert(API_KEY_HEADER, key.parse().unwrap());
+    request
+  }
+
+  fn point(id: u64, vector: Vec<f32>) -> PointStruct {
+    PointStruct {
+      id: Some(PointId {
+        point_id_options: Some(point_id::PointIdOptions::Num(id)),
+      }),
+      vector,
+      payload: [("id".to_string(), conversions::payload_to_proto_value(id))].into(),
+    }
+  }
+
+  #[tokio::test]
+  async fn test_grpc_api() {
+    let dir = Builder::new().prefix("grpc").tempdir().unwrap();
+    let storage_config = serde_json::from_value(json!({
+      "storage_path": dir.path().join("storage"),
+      "snapshots_path": dir.path().join("snapshots"),
+    }))
+    .unwrap();
+    let toc = Arc::new(TableOfContent::new(Arc::new(storage_config)).unwrap());
+    let service_config: ServiceConfig = serde_json::from_value(json!({
+      "host": "127.0.0.1",
+      "http_port": 1,
+      "max_request_size_mb": 1,
+      "max_upload_size_mb": 1,
+      "api_key": "secret",
+      "read_only_api_key": "reader",
+    }))
+    .unwrap();
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let address = listener.local_addr().unwrap();
+    let api_keys = ApiKeyCheck::new(ApiKeys::from_config(&service_config));
+    tokio::spawn(
+      router(toc, api_keys, 1024 * 1024).serve_with_incoming(TcpListenerStream::new(listener)),
+    );
+
+    let channel = Channel::from_shared(format!("http://{address}"))
+      .unwrap()
+      .connect()
+      .await
+      .unwrap();
+    let mut collections = collections_client::CollectionsClient::new(channel.clone());
+    let mut points = points_client::PointsClient::new(channel.clone());
+    let mut snapshots = snapshots_client::SnapshotsClient::new(channel);
+
+    let denied = collections
+      .list(ListCollectionsRequest {})
+      .await
+      .unwrap_err();
+    assert_eq!(denied.code(), Code::Unauthenticated);
+    let config = CollectionConfig {
+      vector_size: 2,
+      distance: Distance::Euclidean as i32,
+      hnsw_config: None,
+      vector_storage_type: Some(VectorStorageType::AppendableMemmap as i32),
+      normalize: false,
+    };
+    let create = CreateCollection {
+      collection_name: "test".to_string(),
+      config: Some(config),
+    };
+    let denied = collections
+      .create(with_key(create.clone(), "reader"))
+      .await
+      .unwrap_err();
+    assert_eq!(denied.code(), Code::PermissionDenied);
+    collections
+      .create(with_key(create, "secret"))
+      .await
+      .unwrap();
+    let listed = collections
+      .list(with_key(ListCollectionsRequest {}, "reader"))
+      .await
+      .unwrap()
+      .into_inner();
+    assert_eq!(listed.collections, ["test"]);
+
+    // Batches are answered one by one, the stream ends at the first failed batch
+    let batches: Vec<_> = (0..3u64)
+      .map(|batch| UpsertPoints {
+        collection_name: "test".to_string(),
+        points: (batch * 10..batch * 10 + 10)
+          .map(|id| point(id, vec![id as f32, 0.0]))
+          .collect(),
+      })
+      .chain([UpsertPoints {
+        collection_name: "test".to_string(),
+        points: vec![point(100, vec![1.0])],
+      }])
+      .collect();
+    let mut responses = points
+      .upsert_stream(with_key(tokio_stream::iter(batches), "secret"))
+      .await
+      .unwrap()
+      .into_inner();
+    for _ in 0..3 {
+      let response = responses.message().await.unwrap().unwrap();
+      assert_eq!(response.ids.len(), 10);
+    }
+    let failed = responses.message().await.unwrap_err();
+    assert_eq!(failed.code(), Code::InvalidArgument);
+
+    let search = SearchPoints {
+      collection_name: "test".to_string(),
+      vector: vec![12.2, 0.0],
+      k: 2,
+    };
+    let found = points
+      .search(with_key(search.clone(), "reader"))
+      .await
+      .unwrap()
+      .into_inner();
+    let ids: Vec<_> = found
+      .result
+      .iter()
+      .map(|point| PointIdType::try_from(point.id.clone().unwrap()).unwrap())
+      .collect();
+    assert_eq!(ids, [12.into(), 13.into()]);
+    assert_eq!(
+      found.result[0].payload["id"],
+      conversions::payload_to_proto_value(12)
+    );
+
+    let recommend = RecommendPoints {
+      collection_name: "test".to_string(),
+      positive: vec![PointIdType::from(4).into()],
+      negative: vec![PointIdType::from(2).into()],
+      k: 1,
+    };
+    let recommended = points
+      .recommend(with_key(recommend, "reader"))
+      .await
+      .unwrap()
+      .into_inner();
+    assert_eq!(recommended.result[0].id, Some(PointIdType::from(6).into()));
+
+    let missing = SearchPoints {
+      collection_name: "missing".to_string(),
+      ..search
+    };
+    let not_found = points
+      .search(with_key(missing, "secret"))
+      .await
+      .unwrap_err();
+    assert_eq!(not_found.code(), Code::NotFound);
+
+    let created = snapshots
+      .create(with_key(
+        CreateSnapshotRequest {
+          collection_name: "test".to_string(),
+        },
+        "secret",
+      ))
+      .await
+      .unwrap()
+      .into_inner()
+      .snapshot_description
+      .unwrap();
+    let listed = snapshots
+      .list(with_key(
+        ListSnapshotsRequest {
+          collection_name: "test".to_string(),
+        },
+        "reader",
+      ))
+      .await
+      .unwrap()
+      .into_inner();
+    assert_eq!(listed.snapshot_descriptions, std::slice::from_ref(&created));
+    snapshots
+      .delete(with_key(
+        DeleteSnapshotRequest {
+          collection_name: "test".to_string(),
+          snapshot_name: created.name,
+        },
+        "secret",
+      ))
+      .await
+      .unwrap();
+  }
+}
@@ -18,7 +18,6 @@ impl Mem
         }
     }
 
-    #[allow(dead_code)]
     pub fn refresh(&mut self)
     {
         #[cfg(target_os = "linux")]